@@ -1,5 +1,7 @@
 use clap::Parser;
 
+use crate::git::{Backend, RemoteFormat};
+use crate::sort::SortKey;
 use crate::Error;
 
 #[derive(clap::Parser, Debug)]
@@ -19,6 +21,122 @@ pub struct Args {
         help = "List both remote-tracking branches and local branches"
     )]
     pub all: bool,
+    #[clap(
+        long,
+        value_parser,
+        action = clap::ArgAction::Append,
+        value_name = "NAME",
+        help = "List remote-tracking branches of the given remote as well as local branches. Can be given multiple times"
+    )]
+    pub remote: Vec<String>,
+    #[clap(
+        long,
+        action,
+        conflicts_with = "tags",
+        help = "List only remote-tracking branches (of the remotes given by --remote, if any)"
+    )]
+    pub remotes_only: bool,
+    #[clap(
+        long,
+        value_enum,
+        default_value_t,
+        value_name = "FORMAT",
+        help = "How to print remote-tracking branches: full (remotes/origin/main), short (origin/main) or branch (main)"
+    )]
+    pub remote_format: RemoteFormat,
+    #[clap(long, action, help = "List tags as well as branches")]
+    pub tags: bool,
+    #[clap(
+        long,
+        action,
+        conflicts_with_all = &["all", "remote", "remotes-only", "tags"],
+        help = "List the entries of the stash instead of branches, and print the selected entries oldest first (e.g., stash@{2} stash@{0}) so they can be dropped in turn"
+    )]
+    pub stash: bool,
+    #[clap(
+        long,
+        value_parser,
+        value_name = "N",
+        min_values = 0,
+        max_values = 1,
+        default_missing_value = "10",
+        conflicts_with_all = &["all", "remote", "remotes-only", "tags", "stash"],
+        help = "List only the N local branches most recently checked out (10 if not given), most recent first"
+    )]
+    pub recent: Option<usize>,
+    #[clap(
+        long,
+        action,
+        help = "Do not list the branch that is currently checked out"
+    )]
+    pub exclude_current: bool,
+    #[clap(
+        long,
+        action,
+        help = "Do not list branches that are checked out in other worktrees"
+    )]
+    pub exclude_worktrees: bool,
+    #[clap(
+        long,
+        action,
+        help = "List only local branches that track an upstream branch"
+    )]
+    pub tracking: bool,
+    #[clap(
+        long,
+        action,
+        help = "List only local branches whose upstream branch no longer exists, and select them all"
+    )]
+    pub gone: bool,
+    #[clap(
+        long,
+        visible_alias = "list",
+        action,
+        help = "Print every matching branch instead of selecting branches interactively, e.g., for use in scripts"
+    )]
+    pub no_interactive: bool,
+    #[clap(
+        long,
+        action,
+        help = "Allow selecting branches that match the protected patterns of the config file or the repository's 'bselect.protected' git config"
+    )]
+    pub allow_protected: bool,
+    #[clap(
+        long,
+        value_parser,
+        value_name = "REF",
+        min_values = 0,
+        max_values = 1,
+        default_missing_value = "HEAD",
+        help = "List only branches whose tip is reachable from the given commit (HEAD if not given)"
+    )]
+    pub merged: Option<String>,
+    #[clap(
+        long,
+        value_parser,
+        value_name = "REF",
+        min_values = 0,
+        max_values = 1,
+        default_missing_value = "HEAD",
+        help = "List only branches whose tip is not reachable from the given commit (HEAD if not given)"
+    )]
+    pub no_merged: Option<String>,
+    #[clap(
+        long,
+        value_parser,
+        value_name = "REF",
+        help = "Count commits ahead of and behind the given branch, instead of each branch's upstream"
+    )]
+    pub base: Option<String>,
+    #[clap(
+        long,
+        value_parser,
+        action = clap::ArgAction::Append,
+        allow_hyphen_values = true,
+        value_name = "KEY",
+        help = "Sort branches by the given key: name, committerdate, type or remote. Prefix with '-' to reverse the order. The last of multiple keys takes precedence"
+    )]
+    pub sort: Vec<SortKey>,
     #[clap(
         long,
         action,
@@ -33,6 +151,16 @@ pub struct Args {
         help = "Path to git repository"
     )]
     pub git_dir: std::path::PathBuf,
+    #[clap(
+        long,
+        value_enum,
+        help = "How to read branches: from the repository's files (fs) or using the git command (git)"
+    )]
+    pub backend: Option<Backend>,
+    /// The patterns of branches that cannot be selected, from the config
+    /// file.
+    #[clap(skip)]
+    pub protected: Vec<String>,
 }
 
 pub fn parse_args<I, T>(argv: I) -> Result<Option<Args>, Error>
@@ -135,6 +263,231 @@ mod tests {
         assert!(args.unwrap().unwrap().all);
     }
 
+    #[test]
+    fn remote_is_empty_given_no_args() {
+        let argv: Vec<&str> = vec!["bselect"];
+
+        let args = parse_args(argv.iter());
+
+        assert!(args.unwrap().unwrap().remote.is_empty());
+    }
+
+    #[test]
+    fn remote_is_set_given_remote_args() {
+        let argv: Vec<&str> = vec!["bselect", "--remote", "origin", "--remote", "upstream"];
+
+        let args = parse_args(argv.iter());
+
+        assert_eq!(args.unwrap().unwrap().remote, vec!["origin", "upstream"]);
+    }
+
+    #[test]
+    fn remotes_only_is_false_given_no_args() {
+        let argv: Vec<&str> = vec!["bselect"];
+
+        let args = parse_args(argv.iter());
+
+        assert!(!args.unwrap().unwrap().remotes_only);
+    }
+
+    #[test]
+    fn remotes_only_is_true_given_remotes_only_flag() {
+        let argv: Vec<&str> = vec!["bselect", "--remotes-only"];
+
+        let args = parse_args(argv.iter());
+
+        assert!(args.unwrap().unwrap().remotes_only);
+    }
+
+    #[test]
+    fn error_given_remotes_only_and_tags() {
+        let argv: Vec<&str> = vec!["bselect", "--remotes-only", "--tags"];
+
+        let args = parse_args(argv.iter());
+
+        assert!(args.is_err());
+    }
+
+    #[test]
+    fn remote_format_is_full_given_no_args() {
+        let argv: Vec<&str> = vec!["bselect"];
+
+        let args = parse_args(argv.iter());
+
+        assert_eq!(args.unwrap().unwrap().remote_format, RemoteFormat::Full);
+    }
+
+    #[test]
+    fn remote_format_is_set_given_remote_format_arg() {
+        let argv: Vec<&str> = vec!["bselect", "--remote-format", "branch"];
+
+        let args = parse_args(argv.iter());
+
+        assert_eq!(args.unwrap().unwrap().remote_format, RemoteFormat::Branch);
+    }
+
+    #[test]
+    fn tags_is_false_given_no_args() {
+        let argv: Vec<&str> = vec!["bselect"];
+
+        let args = parse_args(argv.iter());
+
+        assert!(!args.unwrap().unwrap().tags);
+    }
+
+    #[test]
+    fn tags_is_true_given_tags_flag() {
+        let argv: Vec<&str> = vec!["bselect", "--tags"];
+
+        let args = parse_args(argv.iter());
+
+        assert!(args.unwrap().unwrap().tags);
+    }
+
+    #[test]
+    fn stash_is_false_given_no_args() {
+        let argv: Vec<&str> = vec!["bselect"];
+
+        let args = parse_args(argv.iter());
+
+        assert!(!args.unwrap().unwrap().stash);
+    }
+
+    #[test]
+    fn stash_is_true_given_stash_flag() {
+        let argv: Vec<&str> = vec!["bselect", "--stash"];
+
+        let args = parse_args(argv.iter());
+
+        assert!(args.unwrap().unwrap().stash);
+    }
+
+    #[test]
+    fn error_given_stash_and_tags() {
+        let argv: Vec<&str> = vec!["bselect", "--stash", "--tags"];
+
+        let args = parse_args(argv.iter());
+
+        assert!(args.is_err());
+    }
+
+    #[test]
+    fn exclude_current_is_false_given_no_args() {
+        let argv: Vec<&str> = vec!["bselect"];
+
+        let args = parse_args(argv.iter());
+
+        assert!(!args.unwrap().unwrap().exclude_current);
+    }
+
+    #[test]
+    fn exclude_current_is_true_given_exclude_current_flag() {
+        let argv: Vec<&str> = vec!["bselect", "--exclude-current"];
+
+        let args = parse_args(argv.iter());
+
+        assert!(args.unwrap().unwrap().exclude_current);
+    }
+
+    #[test]
+    fn exclude_worktrees_is_false_given_no_args() {
+        let argv: Vec<&str> = vec!["bselect"];
+
+        let args = parse_args(argv.iter());
+
+        assert!(!args.unwrap().unwrap().exclude_worktrees);
+    }
+
+    #[test]
+    fn exclude_worktrees_is_true_given_exclude_worktrees_flag() {
+        let argv: Vec<&str> = vec!["bselect", "--exclude-worktrees"];
+
+        let args = parse_args(argv.iter());
+
+        assert!(args.unwrap().unwrap().exclude_worktrees);
+    }
+
+    #[test]
+    fn tracking_is_false_given_no_args() {
+        let argv: Vec<&str> = vec!["bselect"];
+
+        let args = parse_args(argv.iter());
+
+        assert!(!args.unwrap().unwrap().tracking);
+    }
+
+    #[test]
+    fn tracking_is_true_given_tracking_flag() {
+        let argv: Vec<&str> = vec!["bselect", "--tracking"];
+
+        let args = parse_args(argv.iter());
+
+        assert!(args.unwrap().unwrap().tracking);
+    }
+
+    #[test]
+    fn gone_is_false_given_no_args() {
+        let argv: Vec<&str> = vec!["bselect"];
+
+        let args = parse_args(argv.iter());
+
+        assert!(!args.unwrap().unwrap().gone);
+    }
+
+    #[test]
+    fn gone_is_true_given_gone_flag() {
+        let argv: Vec<&str> = vec!["bselect", "--gone"];
+
+        let args = parse_args(argv.iter());
+
+        assert!(args.unwrap().unwrap().gone);
+    }
+
+    #[test]
+    fn no_interactive_is_false_given_no_args() {
+        let argv: Vec<&str> = vec!["bselect"];
+
+        let args = parse_args(argv.iter());
+
+        assert!(!args.unwrap().unwrap().no_interactive);
+    }
+
+    #[test]
+    fn no_interactive_is_true_given_no_interactive_flag() {
+        let argv: Vec<&str> = vec!["bselect", "--no-interactive"];
+
+        let args = parse_args(argv.iter());
+
+        assert!(args.unwrap().unwrap().no_interactive);
+    }
+
+    #[test]
+    fn no_interactive_is_true_given_list_flag() {
+        let argv: Vec<&str> = vec!["bselect", "--list"];
+
+        let args = parse_args(argv.iter());
+
+        assert!(args.unwrap().unwrap().no_interactive);
+    }
+
+    #[test]
+    fn allow_protected_is_false_given_no_args() {
+        let argv: Vec<&str> = vec!["bselect"];
+
+        let args = parse_args(argv.iter());
+
+        assert!(!args.unwrap().unwrap().allow_protected);
+    }
+
+    #[test]
+    fn allow_protected_is_true_given_allow_protected_flag() {
+        let argv: Vec<&str> = vec!["bselect", "--allow-protected"];
+
+        let args = parse_args(argv.iter());
+
+        assert!(args.unwrap().unwrap().allow_protected);
+    }
+
     #[test]
     fn config_is_false_given_flag_not_present() {
         let argv: Vec<&str> = vec!["bselect"];
@@ -173,4 +526,168 @@ mod tests {
 
         assert_eq!(args.unwrap().unwrap().git_dir.to_string_lossy(), ".");
     }
+
+    #[test]
+    fn sort_is_empty_given_no_args() {
+        let argv: Vec<&str> = vec!["bselect"];
+
+        let args = parse_args(argv.iter());
+
+        assert!(args.unwrap().unwrap().sort.is_empty());
+    }
+
+    #[test]
+    fn sort_stores_keys_in_order_given_sort_args() {
+        let argv: Vec<&str> = vec!["bselect", "--sort", "-committerdate", "--sort", "type"];
+
+        let args = parse_args(argv.iter());
+
+        assert_eq!(
+            args.unwrap().unwrap().sort,
+            vec![
+                "-committerdate".parse::<SortKey>().unwrap(),
+                "type".parse::<SortKey>().unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn error_given_invalid_sort_key() {
+        let argv: Vec<&str> = vec!["bselect", "--sort", "size"];
+
+        let args = parse_args(argv.iter());
+
+        assert!(args.is_err());
+    }
+
+    #[test]
+    fn recent_is_none_given_no_args() {
+        let argv: Vec<&str> = vec!["bselect"];
+
+        let args = parse_args(argv.iter());
+
+        assert_eq!(args.unwrap().unwrap().recent, None);
+    }
+
+    #[test]
+    fn recent_is_10_given_recent_flag_without_value() {
+        let argv: Vec<&str> = vec!["bselect", "--recent"];
+
+        let args = parse_args(argv.iter());
+
+        assert_eq!(args.unwrap().unwrap().recent, Some(10));
+    }
+
+    #[test]
+    fn recent_is_n_given_recent_flag_with_value() {
+        let argv: Vec<&str> = vec!["bselect", "--recent", "3", "pattern"];
+
+        let args = parse_args(argv.iter()).unwrap().unwrap();
+
+        assert_eq!(args.recent, Some(3));
+        assert_eq!(args.filters, vec!["pattern"]);
+    }
+
+    #[test]
+    fn error_given_recent_and_stash() {
+        let argv: Vec<&str> = vec!["bselect", "--recent", "--stash"];
+
+        let args = parse_args(argv.iter());
+
+        assert!(args.is_err());
+    }
+
+    #[test]
+    fn merged_is_none_given_no_args() {
+        let argv: Vec<&str> = vec!["bselect"];
+
+        let args = parse_args(argv.iter());
+
+        assert_eq!(args.unwrap().unwrap().merged, None);
+    }
+
+    #[test]
+    fn merged_is_head_given_merged_flag_without_value() {
+        let argv: Vec<&str> = vec!["bselect", "--merged"];
+
+        let args = parse_args(argv.iter());
+
+        assert_eq!(args.unwrap().unwrap().merged.as_deref(), Some("HEAD"));
+    }
+
+    #[test]
+    fn merged_is_ref_given_merged_flag_with_value() {
+        let argv: Vec<&str> = vec!["bselect", "--merged", "main", "pattern"];
+
+        let args = parse_args(argv.iter()).unwrap().unwrap();
+
+        assert_eq!(args.merged.as_deref(), Some("main"));
+        assert_eq!(args.filters, vec!["pattern"]);
+    }
+
+    #[test]
+    fn no_merged_is_none_given_no_args() {
+        let argv: Vec<&str> = vec!["bselect"];
+
+        let args = parse_args(argv.iter());
+
+        assert_eq!(args.unwrap().unwrap().no_merged, None);
+    }
+
+    #[test]
+    fn no_merged_is_ref_given_no_merged_flag_with_value() {
+        let argv: Vec<&str> = vec!["bselect", "--no-merged=origin/main"];
+
+        let args = parse_args(argv.iter());
+
+        assert_eq!(
+            args.unwrap().unwrap().no_merged.as_deref(),
+            Some("origin/main")
+        );
+    }
+
+    #[test]
+    fn base_is_none_given_no_args() {
+        let argv: Vec<&str> = vec!["bselect"];
+
+        let args = parse_args(argv.iter());
+
+        assert_eq!(args.unwrap().unwrap().base, None);
+    }
+
+    #[test]
+    fn base_is_ref_given_base_arg() {
+        let argv: Vec<&str> = vec!["bselect", "--base", "main"];
+
+        let args = parse_args(argv.iter());
+
+        assert_eq!(args.unwrap().unwrap().base.as_deref(), Some("main"));
+    }
+
+    #[test]
+    fn backend_is_none_given_no_args() {
+        let argv: Vec<&str> = vec!["bselect"];
+
+        let args = parse_args(argv.iter());
+
+        assert_eq!(args.unwrap().unwrap().backend, None);
+    }
+
+    #[test]
+    fn backend_is_git_given_backend_git() {
+        let argv: Vec<&str> = vec!["bselect", "--backend", "git"];
+
+        let args = parse_args(argv.iter());
+
+        assert_eq!(args.unwrap().unwrap().backend, Some(Backend::Git));
+    }
+
+    #[test]
+    fn error_given_invalid_backend() {
+        let argv: Vec<&str> = vec!["bselect", "--backend", "svn"];
+
+        let args = parse_args(argv.iter());
+
+        assert!(args.is_err());
+    }
 }
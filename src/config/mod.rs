@@ -2,7 +2,10 @@ pub mod file;
 
 use serde::{Deserialize, Serialize};
 
+use crate::git::Backend;
+use crate::select::columns::{default_columns, Column, DateFormat};
 use crate::select::theme::{ConsoleTheme, DEFAULT_THEME};
+use crate::sort::SortKey;
 use crate::Error;
 
 const COULD_NOT_PARSE: &str = "could not parse config file";
@@ -13,6 +16,21 @@ const COULD_NOT_SERIALIZE: &str = "could not serialize config";
 pub struct Config {
     pub theme: String,
     pub themes: Vec<ConsoleTheme>,
+    /// The details of each branch's tip commit to show next to its name.
+    #[serde(default = "default_columns")]
+    pub columns: Vec<Column>,
+    #[serde(default)]
+    pub date_format: DateFormat,
+    /// The key to sort branches by when none is given on the command line.
+    #[serde(default)]
+    pub sort: Option<SortKey>,
+    /// How to read branches when `--backend` is not given.
+    #[serde(default)]
+    pub backend: Backend,
+    /// Wildcard patterns of branches that cannot be selected, e.g.,
+    /// `release/*`, unless `--allow-protected` is given.
+    #[serde(default)]
+    pub protected: Vec<String>,
 }
 
 impl Default for Config {
@@ -20,6 +38,11 @@ impl Default for Config {
         Config {
             theme: crate::select::theme::DEFAULT_THEME.to_string(),
             themes: vec![ConsoleTheme::default()],
+            columns: default_columns(),
+            date_format: DateFormat::default(),
+            sort: None,
+            backend: Backend::default(),
+            protected: vec![],
         }
     }
 }
@@ -168,6 +191,7 @@ mod test {
         let expected_conf = Config {
             theme: "my_theme".to_string(),
             themes: vec![expected_theme, ConsoleTheme::default()],
+            ..Default::default()
         };
         assert_eq!(conf, expected_conf);
     }
@@ -273,4 +297,56 @@ mod test {
 
         assert_eq!(config.theme(), ConsoleTheme::default());
     }
+
+    #[test]
+    fn columns_and_date_format_read_from_json() {
+        let json = r#"{
+            "theme": "default",
+            "themes": [],
+            "columns": ["sha", "subject"],
+            "date_format": "iso"
+        }"#;
+
+        let config = Config::from_json(&mut json.as_bytes()).unwrap();
+
+        assert_eq!(config.columns, vec![Column::Sha, Column::Subject]);
+        assert_eq!(config.date_format, DateFormat::Iso);
+    }
+
+    #[test]
+    fn columns_and_date_format_default_given_not_in_json() {
+        let json = r#"{"theme": "default", "themes": []}"#;
+
+        let config = Config::from_json(&mut json.as_bytes()).unwrap();
+
+        assert_eq!(config.columns, default_columns());
+        assert_eq!(config.date_format, DateFormat::Relative);
+    }
+
+    #[test]
+    fn sort_read_from_json() {
+        let json = r#"{"theme": "default", "themes": [], "sort": "-committerdate"}"#;
+
+        let config = Config::from_json(&mut json.as_bytes()).unwrap();
+
+        assert_eq!(config.sort, Some("-committerdate".parse().unwrap()));
+    }
+
+    #[test]
+    fn from_json_returns_err_given_invalid_sort_key() {
+        let json = r#"{"theme": "default", "themes": [], "sort": "size"}"#;
+
+        let result = Config::from_json(&mut json.as_bytes());
+
+        assert!(result.unwrap_err().to_string().contains("unknown sort key"));
+    }
+
+    #[test]
+    fn backend_read_from_json() {
+        let json = r#"{"theme": "default", "themes": [], "backend": "git"}"#;
+
+        let config = Config::from_json(&mut json.as_bytes()).unwrap();
+
+        assert_eq!(config.backend, Backend::Git);
+    }
 }
@@ -0,0 +1,1003 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use crate::git::object::{Commit, ObjectId, Signature};
+use crate::git::{
+    assign_remotes, recent_checkouts, AheadBehind, Branch, BranchGetter, BranchType, ConfigGetter,
+    RecentGetter, StashGetter,
+};
+use crate::Error;
+
+const GIT_EXE: &str = "git";
+const FIELD_SEPARATOR: u8 = b'\0';
+const LOCAL_REF_PREFIX: &str = "refs/heads/";
+const REMOTE_REF_PREFIX: &str = "refs/remotes/";
+const TAG_REF_PREFIX: &str = "refs/tags/";
+
+/// The fields `for-each-ref` prints for each ref, separated by NUL bytes. The
+/// commit fields are those of the commit an annotated tag points to, if the
+/// ref is one.
+const FOR_EACH_REF_FIELDS: [&str; 14] = [
+    "%(refname)",
+    "%(objectname)",
+    "%(*objectname)",
+    "%(symref)",
+    "%(HEAD)",
+    "%(worktreepath)",
+    "%(upstream)",
+    "%(upstream:track)",
+    "%(if)%(*objectname)%(then)%(*objecttype)%(else)%(objecttype)%(end)",
+    "%(if)%(*objectname)%(then)%(*tree)%(else)%(tree)%(end)",
+    "%(if)%(*objectname)%(then)%(*parent)%(else)%(parent)%(end)",
+    "%(if)%(*objectname)%(then)%(*author)%(else)%(author)%(end)",
+    "%(if)%(*objectname)%(then)%(*committer)%(else)%(committer)%(end)",
+    "%(if)%(*objectname)%(then)%(*contents:subject)%(else)%(contents:subject)%(end)",
+];
+
+/// The fields `stash list` prints for each stash entry, separated by NUL
+/// bytes: the entry's commit, its reflog message and the commit's details.
+/// Dates are printed raw, e.g., `1700000000 +0100`.
+const STASH_LIST_FIELDS: [&str; 7] = [
+    "%H",
+    "%gs",
+    "%T",
+    "%P",
+    "%an <%ae> %ad",
+    "%cn <%ce> %cd",
+    "%s",
+];
+
+/// Gets branches by running the `git` command, so supports everything the
+/// installed version of git does (e.g., reftables and ref namespaces).
+pub struct GitCliBranchGetter {
+    pub repo_dir: PathBuf,
+    /// Environment variables to set for the git commands it runs, on top of
+    /// those of this process.
+    pub env: Vec<(OsString, OsString)>,
+}
+
+impl BranchGetter for GitCliBranchGetter {
+    fn branches(&self, warnings: &mut Vec<String>) -> Result<Vec<Branch>, Error> {
+        let format = FOR_EACH_REF_FIELDS.join("%00");
+        let (output, stderr) = self.run_with_stderr(
+            &[
+                "for-each-ref",
+                &format!("--format={format}"),
+                "refs/heads",
+                "refs/remotes",
+                "refs/tags",
+            ],
+            None,
+        )?;
+        // pass on git's warnings, e.g., about broken refs that it skipped
+        warnings.extend(
+            String::from_utf8_lossy(&stderr)
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(|line| line.strip_prefix("warning: ").unwrap_or(line).to_string()),
+        );
+        let mut branches: Vec<Branch> = output
+            .split(|b| *b == b'\n')
+            .filter_map(parse_branch)
+            .collect();
+        let remotes = self.run(&["remote"], None)?;
+        assign_remotes(&mut branches, &remotes.lines().collect::<Vec<_>>());
+        let mut descriptions = self.branch_descriptions()?;
+        for branch in branches
+            .iter_mut()
+            .filter(|b| b.branch_type == BranchType::Local)
+        {
+            branch.description = descriptions.remove(&branch.name);
+        }
+        Ok(branches)
+    }
+
+    fn resolve_rev(&self, rev: &str) -> Result<ObjectId, Error> {
+        let output = self
+            .run(
+                &["rev-parse", "--verify", &format!("{rev}^{{commit}}")],
+                None,
+            )
+            .map_err(|_| Error::Git(format!("could not resolve '{rev}'")))?;
+        ObjectId::from_hex(output.trim())
+            .ok_or_else(|| Error::Git(format!("could not resolve '{rev}'")))
+    }
+
+    fn reachable(&self, from: &ObjectId, commits: &[ObjectId]) -> Result<Vec<bool>, Error> {
+        if commits.is_empty() {
+            return Ok(vec![]);
+        }
+        // list the commits that can only be reached from the given commits;
+        // the rest can be reached from 'from'
+        let mut input: String = commits.iter().map(|c| format!("{c}\n")).collect();
+        input.push_str(&format!("^{from}\n"));
+        let output = self.run(&["rev-list", "--stdin"], Some(&input))?;
+        let unreachable: std::collections::HashSet<&str> = output.lines().collect();
+        Ok(commits
+            .iter()
+            .map(|c| !unreachable.contains(c.to_hex().as_str()))
+            .collect())
+    }
+
+    fn ahead_behind(&self, pairs: &[(ObjectId, ObjectId)]) -> Result<Vec<AheadBehind>, Error> {
+        // count the branches sharing a base (e.g., '--base') against it with
+        // one command, rather than one per branch
+        let mut pairs_per_base: HashMap<&ObjectId, usize> = HashMap::new();
+        for (_, base) in pairs {
+            *pairs_per_base.entry(base).or_insert(0) += 1;
+        }
+        let mut counts: HashMap<(&ObjectId, &ObjectId), AheadBehind> = HashMap::new();
+        for (base, _) in pairs_per_base.into_iter().filter(|(_, n)| *n > 1) {
+            // git before 2.41 has no '%(ahead-behind)', so count each pair
+            let Ok(branch_counts) = self.branches_ahead_behind(base) else {
+                break;
+            };
+            for (commit, _) in pairs.iter().filter(|(_, b)| b == base) {
+                if let Some(c) = branch_counts.get(commit) {
+                    counts.insert((commit, base), *c);
+                }
+            }
+        }
+        pairs
+            .iter()
+            .map(|(commit, base)| match counts.get(&(commit, base)) {
+                Some(c) => Ok(*c),
+                None => self.rev_list_ahead_behind(commit, base),
+            })
+            .collect()
+    }
+}
+
+impl StashGetter for GitCliBranchGetter {
+    fn stashes(&self) -> Result<Vec<Branch>, Error> {
+        let format = STASH_LIST_FIELDS.join("%x00");
+        let output = self.run(
+            &["stash", "list", "--date=raw", &format!("--format={format}")],
+            None,
+        )?;
+        // '%gd' would give 'stash@{<date>}' with '--date', so count entries
+        // instead
+        Ok(output
+            .lines()
+            .enumerate()
+            .filter_map(|(i, line)| parse_stash(i, line))
+            .collect())
+    }
+}
+
+impl ConfigGetter for GitCliBranchGetter {
+    fn config_values(&self, section: &str, key: &str) -> Result<Vec<String>, Error> {
+        let name = format!("{section}.{key}").to_lowercase();
+        Ok(self
+            .config_entries()?
+            .into_iter()
+            .filter(|(k, _)| *k == name)
+            .map(|(_, value)| value)
+            .collect())
+    }
+}
+
+impl RecentGetter for GitCliBranchGetter {
+    fn recent_branches(&self) -> Result<Vec<String>, Error> {
+        let output = self.run(&["reflog", "show", "--format=%gs", "HEAD"], None)?;
+        Ok(recent_checkouts(output.lines()))
+    }
+}
+
+impl GitCliBranchGetter {
+    /// Count the commits each local branch is ahead of and behind `base`,
+    /// keyed by the branch's commit. This needs git 2.41 or later.
+    fn branches_ahead_behind(
+        &self,
+        base: &ObjectId,
+    ) -> Result<HashMap<ObjectId, AheadBehind>, Error> {
+        let output = self.run(
+            &[
+                "for-each-ref",
+                &format!("--format=%(objectname) %(ahead-behind:{base})"),
+                "refs/heads",
+            ],
+            None,
+        )?;
+        Ok(output
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split(' ');
+                let commit = ObjectId::from_hex(fields.next()?)?;
+                let ahead = fields.next()?.parse().ok()?;
+                let behind = fields.next()?.parse().ok()?;
+                Some((commit, AheadBehind { ahead, behind }))
+            })
+            .collect())
+    }
+
+    /// Count the commits `commit` is ahead of and behind `base`.
+    fn rev_list_ahead_behind(
+        &self,
+        commit: &ObjectId,
+        base: &ObjectId,
+    ) -> Result<AheadBehind, Error> {
+        let range = format!("{commit}...{base}");
+        let output = self.run(&["rev-list", "--left-right", "--count", &range], None)?;
+        let counts = output
+            .split_whitespace()
+            .map(|n| n.parse::<usize>())
+            .collect::<Result<Vec<_>, _>>();
+        match counts.as_deref() {
+            Ok([ahead, behind]) => Ok(AheadBehind {
+                ahead: *ahead,
+                behind: *behind,
+            }),
+            _ => Err(Error::Git(format!(
+                "unexpected output from 'git rev-list': {output}"
+            ))),
+        }
+    }
+
+    /// Read the descriptions of local branches from the `branch.<name>.description`
+    /// config keys, keyed by branch name.
+    fn branch_descriptions(&self) -> Result<HashMap<String, String>, Error> {
+        Ok(self
+            .config_entries()?
+            .into_iter()
+            .filter_map(|(key, value)| {
+                let name = key.strip_prefix("branch.")?.strip_suffix(".description")?;
+                let value = value.trim_end();
+                (!value.is_empty()).then(|| (name.to_string(), value.to_string()))
+            })
+            .collect())
+    }
+
+    /// List the keys and values of the entries of the repository's config,
+    /// and the files it includes, in the order git reads them. As in the fs
+    /// backend, system and global config are not read. Section and key names
+    /// are lower case, as git prints them.
+    fn config_entries(&self) -> Result<Vec<(String, String)>, Error> {
+        // with '-z', each entry is its key and value separated by a newline,
+        // so values may themselves span lines
+        let output = self.run(&["config", "--local", "--includes", "-z", "--list"], None)?;
+        Ok(output
+            .split('\0')
+            .filter_map(|entry| entry.split_once('\n'))
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect())
+    }
+
+    /// Run a git command in the repository, returning its standard output.
+    fn run(&self, args: &[&str], stdin: Option<&str>) -> Result<String, Error> {
+        let output = self.run_bytes(args, stdin)?;
+        Ok(String::from_utf8_lossy(&output).to_string())
+    }
+
+    /// Run a git command in the repository, returning its raw standard
+    /// output, e.g., to keep ref names that are not valid UTF-8.
+    fn run_bytes(&self, args: &[&str], stdin: Option<&str>) -> Result<Vec<u8>, Error> {
+        Ok(self.run_with_stderr(args, stdin)?.0)
+    }
+
+    /// Run a git command in the repository, returning its raw standard output
+    /// and standard error.
+    fn run_with_stderr(
+        &self,
+        args: &[&str],
+        stdin: Option<&str>,
+    ) -> Result<(Vec<u8>, Vec<u8>), Error> {
+        let mut child = Command::new(GIT_EXE)
+            .arg("-C")
+            .arg(&self.repo_dir)
+            .args(args)
+            .envs(self.env.iter().map(|(key, value)| (key, value)))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| Error::Git(format!("could not run {GIT_EXE}: {e}")))?;
+        if let Some(input) = stdin {
+            // take the handle so it is closed once written
+            if let Some(mut child_stdin) = child.stdin.take() {
+                child_stdin
+                    .write_all(input.as_bytes())
+                    .map_err(|e| Error::Git(format!("could not write to {GIT_EXE}: {e}")))?;
+            }
+        }
+        let output = child
+            .wait_with_output()
+            .map_err(|e| Error::Git(format!("could not run {GIT_EXE}: {e}")))?;
+        if !output.status.success() {
+            return Err(Error::Git(format!(
+                "'{GIT_EXE} {}' failed: {}",
+                args[0],
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok((output.stdout, output.stderr))
+    }
+}
+
+/// Parse a line of `for-each-ref` output in the format given by
+/// `FOR_EACH_REF_FIELDS`. The name of the branch is taken from the raw bytes
+/// of the line, as it need not be valid UTF-8.
+fn parse_branch(line: &[u8]) -> Option<Branch> {
+    let raw_ref_name = line.split(|b| *b == FIELD_SEPARATOR).next()?;
+    let text = String::from_utf8_lossy(line);
+    let fields: Vec<&str> = text.split(char::from(FIELD_SEPARATOR)).collect();
+    let [ref_name, object_name, peeled, symref, head, worktree_path, upstream, track, object_type, tree, parents, author, committer, subject] =
+        fields.as_slice()
+    else {
+        return None;
+    };
+    let (prefix, branch_type) = [
+        (LOCAL_REF_PREFIX, BranchType::Local),
+        (REMOTE_REF_PREFIX, BranchType::Remote),
+        (TAG_REF_PREFIX, BranchType::Tag),
+    ]
+    .into_iter()
+    .find(|(prefix, _)| ref_name.starts_with(prefix))?;
+    let target = ObjectId::from_hex(object_name);
+    let peeled = ObjectId::from_hex(peeled);
+    let commit = match (*object_type, peeled.as_ref().or(target.as_ref())) {
+        ("commit", Some(id)) => {
+            parse_commit(id.clone(), [tree, parents, author, committer, subject])
+        }
+        _ => None,
+    };
+    let is_head = *head == "*";
+    let mut branch = Branch {
+        branch_type,
+        symref: if symref.is_empty() {
+            None
+        } else {
+            Some(symref.to_string())
+        },
+        is_head,
+        // only branches checked out in other worktrees are marked
+        worktree: if is_head || worktree_path.is_empty() {
+            None
+        } else {
+            Some(PathBuf::from(worktree_path))
+        },
+        upstream: if upstream.is_empty() {
+            None
+        } else {
+            Some(upstream.to_string())
+        },
+        upstream_gone: *track == "[gone]",
+        target,
+        peeled,
+        commit,
+        ..Default::default()
+    };
+    branch.set_name(&raw_ref_name[prefix.len()..]);
+    Some(branch)
+}
+
+/// Parse the line of `stash list` output for `stash@{index}` in the format
+/// given by `STASH_LIST_FIELDS`.
+fn parse_stash(index: usize, line: &str) -> Option<Branch> {
+    let fields: Vec<&str> = line.split(char::from(FIELD_SEPARATOR)).collect();
+    let [object_name, message, tree, parents, author, committer, subject] = fields.as_slice()
+    else {
+        return None;
+    };
+    let id = ObjectId::from_hex(object_name)?;
+    Some(Branch {
+        name: format!("stash@{{{index}}}"),
+        branch_type: BranchType::Stash,
+        target: Some(id.clone()),
+        commit: parse_commit(id, [tree, parents, author, committer, subject]),
+        message: Some(message.to_string()),
+        ..Default::default()
+    })
+}
+
+/// Build a commit from its tree, parents, author, committer and subject
+/// fields. A commit whose fields cannot be parsed, e.g., as it has an odd
+/// ident or date, gives `None`, so the ref is still listed without it, as in
+/// the fs backend.
+fn parse_commit(id: ObjectId, fields: [&str; 5]) -> Option<Commit> {
+    let [tree, parents, author, committer, subject] = fields;
+    Some(Commit {
+        id,
+        tree: ObjectId::from_hex(tree)?,
+        parents: parents
+            .split_whitespace()
+            .filter_map(ObjectId::from_hex)
+            .collect(),
+        author: Signature::parse(author)?,
+        committer: Signature::parse(committer)?,
+        subject: subject.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::git::fs::{FsBranchGetter, GitEnv};
+
+    /// Run git in `dir` with a fixed identity and no user or system config.
+    fn git(dir: &Path, args: &[&str]) -> String {
+        let output = Command::new(GIT_EXE)
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .env("GIT_CONFIG_NOSYSTEM", "1")
+            .env("GIT_CONFIG_GLOBAL", "/dev/null")
+            .env("GIT_AUTHOR_NAME", "A U Thor")
+            .env("GIT_AUTHOR_EMAIL", "author@example.com")
+            .env("GIT_AUTHOR_DATE", "1700000000 +0100")
+            .env("GIT_COMMITTER_NAME", "C O Mitter")
+            .env("GIT_COMMITTER_EMAIL", "committer@example.com")
+            .env("GIT_COMMITTER_DATE", "1700000100 +0000")
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "git {args:?} failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).unwrap().trim().to_string()
+    }
+
+    /// Make a repository with the history:
+    ///   main:    initial <- second
+    ///   feature: initial <- feature work
+    /// remote-tracking branches `origin/main` (at `initial`) and
+    /// `origin/HEAD`, a lightweight tag `v0.1` (at `initial`) and an annotated
+    /// tag `v1.0` (at `second`). `main` tracks `origin/main`, and `feature`
+    /// tracks `origin/feature`, which does not exist.
+    fn make_test_repo() -> tempfile::TempDir {
+        make_test_repo_with_format("sha1")
+    }
+
+    /// Make the repository of `make_test_repo`, naming objects with the given
+    /// hash function (`sha1` or `sha256`).
+    fn make_test_repo_with_format(object_format: &str) -> tempfile::TempDir {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        git(
+            dir,
+            &[
+                "init",
+                "-q",
+                "-b",
+                "main",
+                &format!("--object-format={object_format}"),
+            ],
+        );
+        git(dir, &["commit", "-q", "--allow-empty", "-m", "initial"]);
+        git(dir, &["update-ref", "refs/remotes/origin/main", "HEAD"]);
+        git(dir, &["tag", "v0.1"]);
+        git(
+            dir,
+            &[
+                "symbolic-ref",
+                "refs/remotes/origin/HEAD",
+                "refs/remotes/origin/main",
+            ],
+        );
+        git(dir, &["branch", "feature"]);
+        git(dir, &["commit", "-q", "--allow-empty", "-m", "second"]);
+        git(dir, &["tag", "-a", "v1.0", "-m", "Release 1.0"]);
+        git(dir, &["checkout", "-q", "feature"]);
+        git(
+            dir,
+            &["commit", "-q", "--allow-empty", "-m", "feature work"],
+        );
+        git(dir, &["checkout", "-q", "main"]);
+        git(
+            dir,
+            &["remote", "add", "origin", "https://example.com/repo.git"],
+        );
+        for (branch, merge) in [
+            ("main", "refs/heads/main"),
+            ("feature", "refs/heads/feature"),
+        ] {
+            git(
+                dir,
+                &["config", &format!("branch.{branch}.remote"), "origin"],
+            );
+            git(dir, &["config", &format!("branch.{branch}.merge"), merge]);
+        }
+        temp_dir
+    }
+
+    /// Getters of both backends for the repository in `dir`, to check that
+    /// they agree.
+    fn getters(dir: &Path) -> (GitCliBranchGetter, FsBranchGetter) {
+        let git_getter = GitCliBranchGetter {
+            repo_dir: dir.to_path_buf(),
+            env: vec![],
+        };
+        let fs_getter = FsBranchGetter {
+            repo_dir: dir.to_path_buf(),
+            env: GitEnv::default(),
+        };
+        (git_getter, fs_getter)
+    }
+
+    fn branch<'a>(branches: &'a [Branch], name: &str) -> &'a Branch {
+        branches.iter().find(|b| b.name == name).unwrap()
+    }
+
+    #[test]
+    fn branches_lists_branches_and_tags() {
+        let temp_dir = make_test_repo();
+        let getter = GitCliBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: vec![],
+        };
+
+        let branches = getter.branches(&mut vec![]).unwrap();
+
+        let names: Vec<_> = branches
+            .iter()
+            .map(|b| (b.name.as_str(), b.branch_type))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("feature", BranchType::Local),
+                ("main", BranchType::Local),
+                ("origin/HEAD", BranchType::Remote),
+                ("origin/main", BranchType::Remote),
+                ("v0.1", BranchType::Tag),
+                ("v1.0", BranchType::Tag)
+            ]
+        );
+    }
+
+    #[test]
+    fn branches_reads_peeled_commit_of_annotated_tag() {
+        let temp_dir = make_test_repo();
+        let getter = GitCliBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: vec![],
+        };
+
+        let branches = getter.branches(&mut vec![]).unwrap();
+
+        let tag = branch(&branches, "v1.0");
+        let main = git(temp_dir.path(), &["rev-parse", "main"]);
+        assert_eq!(
+            tag.target.as_ref().unwrap().to_string(),
+            git(temp_dir.path(), &["rev-parse", "v1.0"])
+        );
+        assert_eq!(tag.peeled.as_ref().unwrap().to_string(), main);
+        assert_eq!(tag.commit.as_ref().unwrap().subject, "second");
+        assert_eq!(branch(&branches, "v0.1").peeled, None);
+    }
+
+    #[test]
+    fn branches_reads_head_upstream_and_tip_commit() {
+        let temp_dir = make_test_repo();
+        let getter = GitCliBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: vec![],
+        };
+
+        let branches = getter.branches(&mut vec![]).unwrap();
+
+        let main = branch(&branches, "main");
+        assert!(main.is_head);
+        assert_eq!(main.upstream.as_deref(), Some("refs/remotes/origin/main"));
+        assert!(!main.upstream_gone);
+        assert_eq!(
+            main.target.as_ref().unwrap().to_string(),
+            git(temp_dir.path(), &["rev-parse", "main"])
+        );
+        let commit = main.commit.as_ref().unwrap();
+        assert_eq!(commit.subject, "second");
+        assert_eq!(commit.author.name, "A U Thor");
+        assert_eq!(commit.author.tz_offset, 60);
+        assert_eq!(commit.committer.time, 1700000100);
+        assert_eq!(commit.parents.len(), 1);
+        let feature = branch(&branches, "feature");
+        assert!(!feature.is_head);
+        assert!(feature.upstream_gone);
+    }
+
+    #[test]
+    fn branches_sets_worktree_of_branch_checked_out_in_other_worktree() {
+        let temp_dir = make_test_repo();
+        let worktree = temp_dir.path().join("wt");
+        git(
+            temp_dir.path(),
+            &[
+                "worktree",
+                "add",
+                "-q",
+                worktree.to_str().unwrap(),
+                "feature",
+            ],
+        );
+        let getter = GitCliBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: vec![],
+        };
+
+        let branches = getter.branches(&mut vec![]).unwrap();
+
+        let feature_worktree = branch(&branches, "feature").worktree.as_ref().unwrap();
+        assert!(same_file::is_same_file(feature_worktree, &worktree).unwrap());
+        assert_eq!(branch(&branches, "main").worktree, None);
+    }
+
+    #[test]
+    fn branches_reads_descriptions_like_fs_branch_getter() {
+        let temp_dir = make_test_repo();
+        let dir = temp_dir.path();
+        git(
+            dir,
+            &[
+                "config",
+                "branch.feature.description",
+                "Add a feature\nin two lines\n",
+            ],
+        );
+        git(dir, &["config", "branch.gone.description", "Deleted\n"]);
+        let (git_getter, fs_getter) = getters(dir);
+
+        let branches = git_getter.branches(&mut vec![]).unwrap();
+
+        assert_eq!(
+            branch(&branches, "feature").description.as_deref(),
+            Some("Add a feature\nin two lines")
+        );
+        assert_eq!(branch(&branches, "main").description, None);
+        assert_eq!(branches, fs_getter.branches(&mut vec![]).unwrap());
+    }
+
+    #[test]
+    fn config_values_lists_values_of_key_like_fs_branch_getter() {
+        let temp_dir = make_test_repo();
+        let dir = temp_dir.path();
+        git(dir, &["config", "--add", "bselect.protected", "main"]);
+        git(dir, &["config", "--add", "bselect.protected", "release/*"]);
+        let (git_getter, fs_getter) = getters(dir);
+
+        let values = git_getter.config_values("bselect", "protected").unwrap();
+
+        assert_eq!(values, vec!["main", "release/*"]);
+        assert_eq!(
+            values,
+            fs_getter.config_values("bselect", "protected").unwrap()
+        );
+        assert!(git_getter
+            .config_values("bselect", "other")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn config_values_ignores_global_config_like_fs_branch_getter() {
+        let temp_dir = make_test_repo();
+        let dir = temp_dir.path();
+        let global_config = dir.join("global.gitconfig");
+        std::fs::write(&global_config, "[bselect]\n\tprotected = global\n").unwrap();
+        git(dir, &["config", "--add", "bselect.protected", "main"]);
+        let (mut git_getter, fs_getter) = getters(dir);
+        git_getter
+            .env
+            .push(("GIT_CONFIG_GLOBAL".into(), global_config.into()));
+
+        let values = git_getter.config_values("bselect", "protected");
+
+        assert_eq!(values.unwrap(), vec!["main"]);
+        assert_eq!(
+            fs_getter.config_values("bselect", "protected").unwrap(),
+            vec!["main"]
+        );
+    }
+
+    #[test]
+    fn branches_ignores_descriptions_in_global_config_like_fs_branch_getter() {
+        let temp_dir = make_test_repo();
+        let dir = temp_dir.path();
+        let global_config = dir.join("global.gitconfig");
+        std::fs::write(
+            &global_config,
+            "[branch \"main\"]\n\tdescription = global\n",
+        )
+        .unwrap();
+        let (mut git_getter, fs_getter) = getters(dir);
+        git_getter
+            .env
+            .push(("GIT_CONFIG_GLOBAL".into(), global_config.into()));
+
+        let branches = git_getter.branches(&mut vec![]).unwrap();
+
+        assert_eq!(branch(&branches, "main").description, None);
+        assert_eq!(branches, fs_getter.branches(&mut vec![]).unwrap());
+    }
+
+    #[test]
+    fn branches_matches_fs_branch_getter() {
+        let temp_dir = make_test_repo();
+        git(temp_dir.path(), &["pack-refs", "--all"]);
+        git(temp_dir.path(), &["branch", "loose"]);
+        let (git_getter, fs_getter) = getters(temp_dir.path());
+
+        let branches = git_getter.branches(&mut vec![]).unwrap();
+
+        assert_eq!(branches, fs_getter.branches(&mut vec![]).unwrap());
+    }
+
+    #[test]
+    fn branches_matches_fs_branch_getter_given_sha256_repo() {
+        let temp_dir = make_test_repo_with_format("sha256");
+        let (git_getter, fs_getter) = getters(temp_dir.path());
+
+        let loose = git_getter.branches(&mut vec![]).unwrap();
+        // pack both the refs and the objects
+        git(temp_dir.path(), &["gc", "-q"]);
+        let packed = git_getter.branches(&mut vec![]).unwrap();
+
+        assert_eq!(
+            branch(&loose, "main")
+                .target
+                .as_ref()
+                .unwrap()
+                .as_bytes()
+                .len(),
+            32
+        );
+        assert!(loose.iter().all(|b| b.commit.is_some()));
+        assert_eq!(loose, packed);
+        assert_eq!(packed, fs_getter.branches(&mut vec![]).unwrap());
+        assert_eq!(
+            fs_getter.resolve_rev("v1.0").unwrap(),
+            git_getter.resolve_rev("v1.0").unwrap()
+        );
+    }
+
+    #[test]
+    fn stashes_lists_stash_entries_like_fs_branch_getter() {
+        let temp_dir = make_test_repo();
+        let dir = temp_dir.path();
+        std::fs::write(dir.join("file.txt"), "first\n").unwrap();
+        git(dir, &["add", "file.txt"]);
+        git(dir, &["stash", "push", "-q", "-m", "try something"]);
+        std::fs::write(dir.join("file.txt"), "second\n").unwrap();
+        git(dir, &["add", "file.txt"]);
+        git(dir, &["stash", "push", "-q"]);
+        let (git_getter, fs_getter) = getters(dir);
+
+        let stashes = git_getter.stashes().unwrap();
+
+        let entries: Vec<_> = stashes
+            .iter()
+            .map(|s| (s.name.as_str(), s.message.as_deref().unwrap()))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                ("stash@{0}", "WIP on main: e20d9d9 second"),
+                ("stash@{1}", "On main: try something"),
+            ]
+        );
+        assert!(stashes.iter().all(|s| s.commit.is_some()));
+        assert_eq!(stashes, fs_getter.stashes().unwrap());
+    }
+
+    #[test]
+    fn recent_branches_lists_checked_out_branches_like_fs_branch_getter() {
+        let temp_dir = make_test_repo();
+        let dir = temp_dir.path();
+        let commit = git(dir, &["rev-parse", "feature"]);
+        git(dir, &["checkout", "-q", "feature"]);
+        git(dir, &["checkout", "-q", "--detach", &commit]);
+        git(dir, &["checkout", "-q", "main"]);
+        git(dir, &["checkout", "-q", "feature"]);
+        let (git_getter, fs_getter) = getters(dir);
+
+        let recent = git_getter.recent_branches().unwrap();
+
+        assert_eq!(recent, vec!["feature", "main", &commit]);
+        assert_eq!(recent, fs_getter.recent_branches().unwrap());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn branches_keeps_raw_bytes_of_non_utf8_names() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let temp_dir = make_test_repo();
+        let head = git(temp_dir.path(), &["rev-parse", "HEAD"]);
+        let heads_dir = temp_dir.path().join(".git").join("refs").join("heads");
+        std::fs::write(
+            heads_dir.join(std::ffi::OsStr::from_bytes(b"caf\xe9")),
+            format!("{head}\n"),
+        )
+        .unwrap();
+        let (git_getter, fs_getter) = getters(temp_dir.path());
+
+        let branches = git_getter.branches(&mut vec![]).unwrap();
+
+        let branch = branch(&branches, "caf\u{fffd}");
+        assert_eq!(branch.raw_name, Some(b"caf\xe9".to_vec()));
+        assert_eq!(branches, fs_getter.branches(&mut vec![]).unwrap());
+    }
+
+    #[test]
+    fn parse_branch_lists_branch_without_commit_given_malformed_author() {
+        let id = "e2bf29060f42743538be07c164820cdeca0d9d2b";
+        let line = [
+            "refs/heads/main",
+            id,
+            "",
+            "",
+            "*",
+            "",
+            "",
+            "",
+            "commit",
+            "4b825dc642cb6eb9a060e54bf8d69288fbee4904",
+            "",
+            "A U Thor <author@example.com> not-a-date",
+            "C O Mitter <committer@example.com> 1700000100 +0000",
+            "initial",
+        ]
+        .join("\0");
+
+        let branch = parse_branch(line.as_bytes()).unwrap();
+
+        assert_eq!(branch.name, "main");
+        assert_eq!(branch.target, ObjectId::from_hex(id));
+        assert!(branch.is_head);
+        assert_eq!(branch.commit, None);
+    }
+
+    #[test]
+    fn parse_stash_lists_entry_without_commit_given_malformed_author() {
+        let id = "e2bf29060f42743538be07c164820cdeca0d9d2b";
+        let line = [
+            id,
+            "On main: try something",
+            "4b825dc642cb6eb9a060e54bf8d69288fbee4904",
+            "",
+            "A U Thor <author@example.com> not-a-date",
+            "C O Mitter <committer@example.com> 1700000100 +0000",
+            "On main: try something",
+        ]
+        .join("\0");
+
+        let stash = parse_stash(0, &line).unwrap();
+
+        assert_eq!(stash.name, "stash@{0}");
+        assert_eq!(stash.message.as_deref(), Some("On main: try something"));
+        assert_eq!(stash.commit, None);
+    }
+
+    #[test]
+    fn branches_returns_err_given_not_a_repo() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let getter = GitCliBranchGetter {
+            repo_dir: temp_dir.path().join("does_not_exist"),
+            env: vec![],
+        };
+
+        let result = getter.branches(&mut vec![]);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn resolve_rev_returns_commit_given_rev() {
+        let temp_dir = make_test_repo();
+        let getter = GitCliBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: vec![],
+        };
+
+        let id = getter.resolve_rev("origin/main").unwrap();
+
+        assert_eq!(
+            id.to_string(),
+            git(temp_dir.path(), &["rev-parse", "main~1"])
+        );
+    }
+
+    #[test]
+    fn resolve_rev_returns_err_given_unknown_rev() {
+        let temp_dir = make_test_repo();
+        let getter = GitCliBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: vec![],
+        };
+
+        let result = getter.resolve_rev("not_a_branch");
+
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("could not resolve 'not_a_branch'"));
+    }
+
+    #[test]
+    fn resolve_rev_resolves_abbreviated_ids_like_fs_branch_getter() {
+        let temp_dir = make_test_repo();
+        let dir = temp_dir.path();
+        let head = git(dir, &["rev-parse", "HEAD"]);
+        let (git_getter, fs_getter) = getters(dir);
+
+        for rev in [&head[..8], &head[..7]] {
+            assert_eq!(git_getter.resolve_rev(rev).unwrap().to_string(), head);
+            assert_eq!(fs_getter.resolve_rev(rev).unwrap().to_string(), head);
+        }
+        // the repository's object IDs are fixed, and none start with 'beef'
+        assert!(git_getter.resolve_rev("beef").is_err());
+        assert!(fs_getter.resolve_rev("beef").is_err());
+    }
+
+    #[test]
+    fn reachable_returns_whether_commits_are_ancestors() {
+        let temp_dir = make_test_repo();
+        let getter = GitCliBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: vec![],
+        };
+        let id = |rev| getter.resolve_rev(rev).unwrap();
+
+        let reachable = getter
+            .reachable(&id("main"), &[id("origin/main"), id("feature"), id("main")])
+            .unwrap();
+
+        assert_eq!(reachable, vec![true, false, true]);
+    }
+
+    #[test]
+    fn ahead_behind_counts_branches_against_shared_base_like_fs_branch_getter() {
+        let temp_dir = make_test_repo();
+        let (git_getter, fs_getter) = getters(temp_dir.path());
+        let id = |rev| git_getter.resolve_rev(rev).unwrap();
+        let pairs = [
+            (id("main"), id("v0.1")),
+            (id("feature"), id("v0.1")),
+            (id("v1.0"), id("v0.1")),
+        ];
+
+        let counts = git_getter.ahead_behind(&pairs).unwrap();
+
+        assert_eq!(counts, fs_getter.ahead_behind(&pairs).unwrap());
+        assert_eq!(
+            counts[1],
+            AheadBehind {
+                ahead: 1,
+                behind: 0
+            }
+        );
+    }
+
+    #[test]
+    fn ahead_behind_counts_commits_unique_to_each_side() {
+        let temp_dir = make_test_repo();
+        let getter = GitCliBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: vec![],
+        };
+        let id = |rev| getter.resolve_rev(rev).unwrap();
+
+        let counts = getter
+            .ahead_behind(&[(id("main"), id("origin/main")), (id("feature"), id("main"))])
+            .unwrap();
+
+        assert_eq!(
+            counts,
+            vec![
+                AheadBehind {
+                    ahead: 1,
+                    behind: 0
+                },
+                AheadBehind {
+                    ahead: 1,
+                    behind: 1
+                }
+            ]
+        );
+    }
+}
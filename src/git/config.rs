@@ -0,0 +1,1020 @@
+use std::path::{Path, PathBuf};
+
+use crate::git::object::ObjectFormat;
+use crate::Error;
+
+const MAX_INCLUDE_DEPTH: usize = 10;
+
+/// A single `key = value` entry from a git config file.
+///
+/// Section and key names are stored lowercase, as they are case-insensitive.
+/// Subsection names are case-sensitive and stored as written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigEntry {
+    pub section: String,
+    pub subsection: Option<String>,
+    pub key: String,
+    /// `None` for a key given without `=`, which git treats as boolean true.
+    pub value: Option<String>,
+}
+
+/// The details of a repository that `includeIf` conditions are tested against.
+pub struct IncludeContext<'a> {
+    pub git_dir: &'a Path,
+    /// The short name of the branch checked out, if any.
+    pub branch: Option<&'a str>,
+}
+
+/// The entries of a git config file and any files it includes, in the order
+/// git would read them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GitConfig {
+    pub entries: Vec<ConfigEntry>,
+}
+
+impl GitConfig {
+    /// Read a config file, following `include` and `includeIf` directives.
+    ///
+    /// A missing file gives an empty config, as git tolerates missing config
+    /// (and included) files.
+    pub fn from_file(path: &Path, context: &IncludeContext) -> Result<GitConfig, Error> {
+        let mut config = GitConfig::default();
+        if path.is_file() {
+            config.read_file(path, context, 0)?;
+        }
+        Ok(config)
+    }
+
+    /// The last value set for the key, as later values override earlier ones.
+    pub fn get(&self, section: &str, subsection: Option<&str>, key: &str) -> Option<&str> {
+        self.matching(section, subsection, key)
+            .last()
+            .and_then(|e| e.value.as_deref())
+    }
+
+    /// All values set for a multi-valued key, e.g., `remote.<name>.fetch`.
+    pub fn get_all(&self, section: &str, subsection: Option<&str>, key: &str) -> Vec<&str> {
+        self.matching(section, subsection, key)
+            .into_iter()
+            .filter_map(|e| e.value.as_deref())
+            .collect()
+    }
+
+    /// The last value set for the key interpreted as a boolean, using git's
+    /// rules for `true`/`yes`/`on`/`1` and friends.
+    pub fn get_bool(&self, section: &str, subsection: Option<&str>, key: &str) -> Option<bool> {
+        let entry = *self.matching(section, subsection, key).last()?;
+        match entry.value.as_deref() {
+            None => Some(true),
+            Some(v) => parse_bool(v),
+        }
+    }
+
+    /// The names of the configured remotes, in the order they first appear.
+    pub fn remotes(&self) -> Vec<&str> {
+        let mut remotes: Vec<&str> = vec![];
+        let remote_entries = self
+            .entries
+            .iter()
+            .filter(|e| e.section.eq_ignore_ascii_case("remote"));
+        for name in remote_entries.filter_map(|e| e.subsection.as_deref()) {
+            if !remotes.contains(&name) {
+                remotes.push(name);
+            }
+        }
+        remotes
+    }
+
+    /// The hash function the repository names objects with, from
+    /// `extensions.objectFormat`. Repositories without it use SHA-1.
+    pub fn object_format(&self) -> Result<ObjectFormat, Error> {
+        match self.get("extensions", None, "objectformat") {
+            None => Ok(ObjectFormat::Sha1),
+            Some(name) => ObjectFormat::from_name(name)
+                .ok_or_else(|| Error::Git(format!("unknown object format '{name}'"))),
+        }
+    }
+
+    /// The full name of the ref that a local branch's upstream is fetched to,
+    /// e.g., `refs/remotes/origin/main`, from `branch.<name>.remote` and
+    /// `branch.<name>.merge`.
+    ///
+    /// The remote's `fetch` refspecs map the upstream branch to its
+    /// remote-tracking ref. If the remote has no refspecs, git's default of
+    /// `refs/heads/*:refs/remotes/<remote>/*` is assumed.
+    pub fn upstream(&self, branch: &str) -> Option<String> {
+        let remote = self.get("branch", Some(branch), "remote")?;
+        let merge = self.get("branch", Some(branch), "merge")?;
+        let merge = if merge.starts_with("refs/") {
+            merge.to_string()
+        } else {
+            format!("refs/heads/{merge}")
+        };
+        if remote == "." {
+            // the upstream is another local branch
+            return Some(merge);
+        }
+        let default_refspec = format!("refs/heads/*:refs/remotes/{remote}/*");
+        let mut refspecs = self.get_all("remote", Some(remote), "fetch");
+        if refspecs.is_empty() {
+            refspecs.push(&default_refspec);
+        }
+        refspecs
+            .iter()
+            .find_map(|refspec| map_refspec(refspec, &merge))
+    }
+
+    /// The description of a local branch, from `branch.<name>.description`.
+    /// `git branch --edit-description` ends it with a newline, which is
+    /// removed.
+    pub fn branch_description(&self, branch: &str) -> Option<String> {
+        let description = self.get("branch", Some(branch), "description")?.trim_end();
+        (!description.is_empty()).then(|| description.to_string())
+    }
+
+    fn matching(&self, section: &str, subsection: Option<&str>, key: &str) -> Vec<&ConfigEntry> {
+        self.entries
+            .iter()
+            .filter(|e| {
+                e.section.eq_ignore_ascii_case(section)
+                    && e.subsection.as_deref() == subsection
+                    && e.key.eq_ignore_ascii_case(key)
+            })
+            .collect()
+    }
+
+    fn read_file(
+        &mut self,
+        path: &Path,
+        context: &IncludeContext,
+        depth: usize,
+    ) -> Result<(), Error> {
+        if depth > MAX_INCLUDE_DEPTH {
+            return Err(Error::Git(format!(
+                "exceeded maximum include depth ({MAX_INCLUDE_DEPTH}) reading '{}'",
+                path.to_string_lossy()
+            )));
+        }
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            Error::Git(format!(
+                "could not read git config '{}': {e}",
+                path.to_string_lossy()
+            ))
+        })?;
+        let entries = parse(&text).map_err(|e| {
+            Error::Git(format!(
+                "could not parse git config '{}': {e}",
+                path.to_string_lossy()
+            ))
+        })?;
+        let base_dir = path.parent().unwrap_or_else(|| Path::new("."));
+        for entry in entries {
+            let include = include_path(&entry, base_dir, context);
+            self.entries.push(entry);
+            if let Some(include) = include {
+                if include.is_file() {
+                    self.read_file(&include, context, depth + 1)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for GitConfig {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<GitConfig, Error> {
+        let entries =
+            parse(text).map_err(|e| Error::Git(format!("could not parse git config: {e}")))?;
+        Ok(GitConfig { entries })
+    }
+}
+
+/// Map a ref through a fetch refspec such as `+refs/heads/*:refs/remotes/o/*`,
+/// returning `None` if the refspec does not apply to the ref.
+fn map_refspec(refspec: &str, ref_name: &str) -> Option<String> {
+    if refspec.starts_with('^') {
+        // negative refspecs only exclude refs, they never map them
+        return None;
+    }
+    let (src, dst) = refspec.trim_start_matches('+').split_once(':')?;
+    match (src.split_once('*'), dst.split_once('*')) {
+        (Some((src_prefix, src_suffix)), Some((dst_prefix, dst_suffix))) => {
+            let matched = ref_name
+                .strip_prefix(src_prefix)?
+                .strip_suffix(src_suffix)?;
+            Some(format!("{dst_prefix}{matched}{dst_suffix}"))
+        }
+        (None, None) if src == ref_name && !dst.is_empty() => Some(dst.to_string()),
+        _ => None,
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "yes" | "on" => Some(true),
+        "false" | "no" | "off" | "" => Some(false),
+        v => v.parse::<i64>().ok().map(|i| i != 0),
+    }
+}
+
+/// The file to include, if the entry is an `include.path`, or an
+/// `includeIf.<condition>.path` whose condition holds.
+fn include_path(entry: &ConfigEntry, base_dir: &Path, context: &IncludeContext) -> Option<PathBuf> {
+    if entry.key != "path" {
+        return None;
+    }
+    let applies = match (entry.section.as_str(), entry.subsection.as_deref()) {
+        ("include", None) => true,
+        ("includeif", Some(condition)) => include_condition_holds(condition, base_dir, context),
+        _ => false,
+    };
+    if !applies {
+        return None;
+    }
+    let path = expand_home(entry.value.as_deref()?)?;
+    // relative include paths are relative to the including file
+    Some(base_dir.join(path))
+}
+
+fn include_condition_holds(condition: &str, base_dir: &Path, context: &IncludeContext) -> bool {
+    if let Some(pattern) = condition.strip_prefix("gitdir:") {
+        return gitdir_matches(pattern, base_dir, context.git_dir, false);
+    } else if let Some(pattern) = condition.strip_prefix("gitdir/i:") {
+        return gitdir_matches(pattern, base_dir, context.git_dir, true);
+    } else if let Some(pattern) = condition.strip_prefix("onbranch:") {
+        let mut pattern = pattern.to_string();
+        if pattern.ends_with('/') {
+            pattern.push_str("**");
+        }
+        return match context.branch {
+            Some(branch) => glob_matches(&pattern, branch, false),
+            None => false,
+        };
+    }
+    // unknown conditions never hold, as in git
+    false
+}
+
+fn gitdir_matches(pattern: &str, base_dir: &Path, git_dir: &Path, icase: bool) -> bool {
+    let mut pattern = if let Some(rest) = pattern.strip_prefix("./") {
+        base_dir.join(rest).to_string_lossy().to_string()
+    } else {
+        match expand_home(pattern) {
+            Some(p) => p.to_string_lossy().to_string(),
+            None => return false,
+        }
+    };
+    if !Path::new(&pattern).is_absolute() {
+        pattern = format!("**/{pattern}");
+    }
+    if pattern.ends_with('/') {
+        pattern.push_str("**");
+    }
+    glob_matches(&pattern, &git_dir.to_string_lossy(), icase)
+}
+
+fn expand_home(path: &str) -> Option<PathBuf> {
+    match path.strip_prefix("~/") {
+        Some(rest) => Some(directories::BaseDirs::new()?.home_dir().join(rest)),
+        None => Some(PathBuf::from(path)),
+    }
+}
+
+/// Match text against a wildcard pattern, where `*` and `?` do not match `/`
+/// but `**` matches across directories.
+fn glob_matches(pattern: &str, text: &str, icase: bool) -> bool {
+    let (pattern, text) = if icase {
+        (pattern.to_lowercase(), text.to_lowercase())
+    } else {
+        (pattern.to_string(), text.to_string())
+    };
+    match regex::Regex::new(&glob_to_regex(&pattern)) {
+        Ok(re) => re.is_match(&text),
+        Err(_) => false,
+    }
+}
+
+fn glob_to_regex(pattern: &str) -> String {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut re = String::from("^");
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                i += 1;
+                if chars.get(i + 1) == Some(&'/') {
+                    i += 1;
+                    re.push_str("(?:.*/)?");
+                } else {
+                    re.push_str(".*");
+                }
+            }
+            '*' => re.push_str("[^/]*"),
+            '?' => re.push_str("[^/]"),
+            '[' => match chars[i + 1..].iter().position(|c| *c == ']') {
+                Some(len) if len > 0 => {
+                    let class: String = chars[i + 1..i + 1 + len].iter().collect();
+                    let class = match class.strip_prefix('!') {
+                        Some(negated) => format!("^{negated}"),
+                        None => class,
+                    };
+                    re.push_str(&format!("[{}]", class.replace('\\', "\\\\")));
+                    i += len + 1;
+                }
+                _ => re.push_str(&regex::escape("[")),
+            },
+            '\\' if i + 1 < chars.len() => {
+                i += 1;
+                re.push_str(&regex::escape(&chars[i].to_string()));
+            }
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+        i += 1;
+    }
+    re.push('$');
+    re
+}
+
+/// Parse the text of a git config file into its entries.
+fn parse(text: &str) -> Result<Vec<ConfigEntry>, String> {
+    let mut parser = Parser {
+        chars: text.chars().collect(),
+        pos: 0,
+        line: 1,
+    };
+    let mut entries = vec![];
+    let mut section: Option<(String, Option<String>)> = None;
+    loop {
+        parser.skip_whitespace();
+        match parser.peek() {
+            None => break,
+            Some('\n') => {
+                parser.next();
+            }
+            Some('#') | Some(';') => parser.skip_line(),
+            Some('[') => {
+                parser.next();
+                section = Some(parser.section_header()?);
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                let (name, subsection) = section
+                    .clone()
+                    .ok_or_else(|| format!("key outside of a section on line {}", parser.line))?;
+                let key = parser.key();
+                parser.skip_whitespace();
+                let value = match parser.peek() {
+                    Some('=') => {
+                        parser.next();
+                        Some(parser.value()?)
+                    }
+                    None | Some('\n') | Some('#') | Some(';') => {
+                        parser.skip_line();
+                        None
+                    }
+                    Some(c) => return Err(format!("unexpected '{c}' on line {}", parser.line)),
+                };
+                entries.push(ConfigEntry {
+                    section: name,
+                    subsection,
+                    key,
+                    value,
+                });
+            }
+            Some(c) => return Err(format!("unexpected '{c}' on line {}", parser.line)),
+        }
+    }
+    Ok(entries)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(c) if c != '\n' && c.is_whitespace()) {
+            self.next();
+        }
+    }
+
+    fn skip_line(&mut self) {
+        while let Some(c) = self.next() {
+            if c == '\n' {
+                break;
+            }
+        }
+    }
+
+    /// Parse a section header after its opening `[`, supporting both
+    /// `[section "subsection"]` and the deprecated `[section.subsection]`.
+    fn section_header(&mut self) -> Result<(String, Option<String>), String> {
+        let mut name = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+                name.push(c.to_ascii_lowercase());
+                self.next();
+            } else {
+                break;
+            }
+        }
+        if name.is_empty() {
+            return Err(format!("empty section name on line {}", self.line));
+        }
+        self.skip_whitespace();
+        match self.next() {
+            Some(']') => match name.split_once('.') {
+                Some((section, subsection)) => {
+                    Ok((section.to_string(), Some(subsection.to_string())))
+                }
+                None => Ok((name, None)),
+            },
+            Some('"') => {
+                let mut subsection = String::new();
+                loop {
+                    match self.next() {
+                        Some('"') => break,
+                        Some('\\') => match self.next() {
+                            Some('\n') | None => {
+                                return Err(format!(
+                                    "unterminated subsection on line {}",
+                                    self.line
+                                ))
+                            }
+                            Some(c) => subsection.push(c),
+                        },
+                        Some('\n') | None => {
+                            return Err(format!("unterminated subsection on line {}", self.line))
+                        }
+                        Some(c) => subsection.push(c),
+                    }
+                }
+                if self.next() != Some(']') {
+                    return Err(format!("bad section header on line {}", self.line));
+                }
+                Ok((name, Some(subsection)))
+            }
+            _ => Err(format!("bad section header on line {}", self.line)),
+        }
+    }
+
+    fn key(&mut self) -> String {
+        let mut key = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                key.push(c.to_ascii_lowercase());
+                self.next();
+            } else {
+                break;
+            }
+        }
+        key
+    }
+
+    /// Parse a value after its `=`, handling quoting, escape sequences, line
+    /// continuations and trailing comments.
+    fn value(&mut self) -> Result<String, String> {
+        let mut value = String::new();
+        // length of the value excluding unquoted trailing whitespace
+        let mut trimmed_len = 0;
+        let mut in_quotes = false;
+        self.skip_whitespace();
+        loop {
+            let c = match self.next() {
+                None => break,
+                Some(c) => c,
+            };
+            match c {
+                '\n' if in_quotes => {
+                    return Err(format!("unterminated quote on line {}", self.line - 1))
+                }
+                '\n' => break,
+                '#' | ';' if !in_quotes => {
+                    self.skip_line();
+                    break;
+                }
+                '"' => {
+                    in_quotes = !in_quotes;
+                    trimmed_len = value.len();
+                }
+                '\\' => {
+                    match self.next() {
+                        Some('\n') => continue,
+                        Some('n') => value.push('\n'),
+                        Some('t') => value.push('\t'),
+                        Some('b') => {
+                            value.pop();
+                        }
+                        Some(c @ ('\\' | '"')) => value.push(c),
+                        _ => return Err(format!("bad escape sequence on line {}", self.line)),
+                    }
+                    trimmed_len = value.len();
+                }
+                c => {
+                    value.push(c);
+                    if in_quotes || !c.is_whitespace() {
+                        trimmed_len = value.len();
+                    }
+                }
+            }
+        }
+        if in_quotes {
+            return Err(format!("unterminated quote on line {}", self.line));
+        }
+        value.truncate(trimmed_len);
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    fn entry(
+        section: &str,
+        subsection: Option<&str>,
+        key: &str,
+        value: Option<&str>,
+    ) -> ConfigEntry {
+        ConfigEntry {
+            section: section.to_string(),
+            subsection: subsection.map(|s| s.to_string()),
+            key: key.to_string(),
+            value: value.map(|v| v.to_string()),
+        }
+    }
+
+    fn context(git_dir: &Path) -> IncludeContext<'_> {
+        IncludeContext {
+            git_dir,
+            branch: None,
+        }
+    }
+
+    mod parse {
+        use super::super::*;
+        use super::entry;
+
+        #[test]
+        fn reads_sections_subsections_and_keys() {
+            let text = r#"
+[core]
+    bare = false
+    filemode
+[remote "origin"]
+    url = git@github.com:user/repo.git
+    fetch = +refs/heads/*:refs/remotes/origin/*
+[branch "Feature/X"]
+    remote = origin
+"#;
+
+            let entries = parse(text).unwrap();
+
+            assert_eq!(
+                entries,
+                vec![
+                    entry("core", None, "bare", Some("false")),
+                    entry("core", None, "filemode", None),
+                    entry(
+                        "remote",
+                        Some("origin"),
+                        "url",
+                        Some("git@github.com:user/repo.git")
+                    ),
+                    entry(
+                        "remote",
+                        Some("origin"),
+                        "fetch",
+                        Some("+refs/heads/*:refs/remotes/origin/*")
+                    ),
+                    entry("branch", Some("Feature/X"), "remote", Some("origin")),
+                ]
+            );
+        }
+
+        #[test]
+        fn lowercases_section_and_key_names() {
+            let entries = parse("[Core]\n\tIgnoreCase = true\n").unwrap();
+
+            assert_eq!(
+                entries,
+                vec![entry("core", None, "ignorecase", Some("true"))]
+            );
+        }
+
+        #[test]
+        fn reads_deprecated_subsection_syntax() {
+            let entries = parse("[branch.main]\nremote = origin\n").unwrap();
+
+            assert_eq!(
+                entries,
+                vec![entry("branch", Some("main"), "remote", Some("origin"))]
+            );
+        }
+
+        #[test]
+        fn unescapes_subsection_names() {
+            let entries = parse("[branch \"a\\\"b\\\\c\"]\nremote = origin\n").unwrap();
+
+            assert_eq!(entries[0].subsection.as_deref(), Some("a\"b\\c"));
+        }
+
+        #[test]
+        fn handles_quotes_escapes_and_comments_in_values() {
+            let text = "[a]\n\
+                x = \"  quoted ; not a comment \"  # comment\n\
+                y = one\\ttwo\\n\n\
+                z = trailing space   ; comment\n\
+                w = \"\"\n";
+
+            let entries = parse(text).unwrap();
+
+            assert_eq!(
+                entries[0].value.as_deref(),
+                Some("  quoted ; not a comment ")
+            );
+            assert_eq!(entries[1].value.as_deref(), Some("one\ttwo\n"));
+            assert_eq!(entries[2].value.as_deref(), Some("trailing space"));
+            assert_eq!(entries[3].value.as_deref(), Some(""));
+        }
+
+        #[test]
+        fn joins_continued_lines() {
+            let entries = parse("[a]\nx = one \\\ntwo\n").unwrap();
+
+            assert_eq!(entries[0].value.as_deref(), Some("one two"));
+        }
+
+        #[test]
+        fn reads_key_on_section_header_line() {
+            let entries = parse("[a] x = 1\n").unwrap();
+
+            assert_eq!(entries, vec![entry("a", None, "x", Some("1"))]);
+        }
+
+        #[test]
+        fn skips_comment_lines() {
+            let entries = parse("# comment\n; another\n[a]\n  # x = 1\n").unwrap();
+
+            assert!(entries.is_empty());
+        }
+
+        #[test]
+        fn returns_err_given_key_outside_section() {
+            let err = parse("x = 1\n").unwrap_err();
+
+            assert!(err.contains("line 1"));
+        }
+
+        #[test]
+        fn returns_err_given_unterminated_quote() {
+            assert!(parse("[a]\nx = \"abc\n").is_err());
+        }
+
+        #[test]
+        fn returns_err_given_unterminated_section_header() {
+            assert!(parse("[branch \"main\n").is_err());
+        }
+    }
+
+    #[test]
+    fn get_returns_last_value_set() {
+        let config = "[a]\nx = 1\n[A]\nX = 2\n".parse::<GitConfig>().unwrap();
+
+        assert_eq!(config.get("a", None, "x"), Some("2"));
+    }
+
+    #[test]
+    fn get_matches_subsection_case_sensitively() {
+        let config = "[branch \"Main\"]\nremote = origin\n"
+            .parse::<GitConfig>()
+            .unwrap();
+
+        assert_eq!(config.get("branch", Some("Main"), "remote"), Some("origin"));
+        assert_eq!(config.get("branch", Some("main"), "remote"), None);
+    }
+
+    #[test]
+    fn get_all_returns_all_values_in_order() {
+        let config = "[remote \"origin\"]\nfetch = +refs/heads/*:refs/remotes/origin/*\nfetch = +refs/tags/*:refs/tags/*\n".parse::<GitConfig>()
+        .unwrap();
+
+        assert_eq!(
+            config.get_all("remote", Some("origin"), "fetch"),
+            vec![
+                "+refs/heads/*:refs/remotes/origin/*",
+                "+refs/tags/*:refs/tags/*"
+            ]
+        );
+    }
+
+    #[test]
+    fn get_bool_interprets_git_booleans() {
+        let config = "[a]\nt1\nt2 = yes\nt3 = 2\nf1 = off\nf2 = 0\nf3 =\nbad = maybe\n"
+            .parse::<GitConfig>()
+            .unwrap();
+
+        assert_eq!(config.get_bool("a", None, "t1"), Some(true));
+        assert_eq!(config.get_bool("a", None, "t2"), Some(true));
+        assert_eq!(config.get_bool("a", None, "t3"), Some(true));
+        assert_eq!(config.get_bool("a", None, "f1"), Some(false));
+        assert_eq!(config.get_bool("a", None, "f2"), Some(false));
+        assert_eq!(config.get_bool("a", None, "f3"), Some(false));
+        assert_eq!(config.get_bool("a", None, "bad"), None);
+        assert_eq!(config.get_bool("a", None, "missing"), None);
+    }
+
+    #[test]
+    fn upstream_maps_merge_ref_through_fetch_refspec() {
+        let config = "[remote \"origin\"]\n\
+             fetch = +refs/heads/*:refs/remotes/origin/*\n\
+             [branch \"feature\"]\n\
+             remote = origin\n\
+             merge = refs/heads/feature/upstream\n"
+            .parse::<GitConfig>()
+            .unwrap();
+
+        assert_eq!(
+            config.upstream("feature").as_deref(),
+            Some("refs/remotes/origin/feature/upstream")
+        );
+    }
+
+    #[test]
+    fn upstream_uses_first_matching_fetch_refspec() {
+        let config = "[remote \"up\"]\n\
+             fetch = ^refs/heads/main\n\
+             fetch = +refs/heads/main:refs/remotes/up/trunk\n\
+             fetch = +refs/heads/*:refs/remotes/up/*\n\
+             [branch \"main\"]\n\
+             remote = up\n\
+             merge = refs/heads/main\n"
+            .parse::<GitConfig>()
+            .unwrap();
+
+        assert_eq!(
+            config.upstream("main").as_deref(),
+            Some("refs/remotes/up/trunk")
+        );
+    }
+
+    #[test]
+    fn upstream_assumes_default_refspec_given_remote_has_none() {
+        let config = "[branch \"main\"]\nremote = origin\nmerge = main\n"
+            .parse::<GitConfig>()
+            .unwrap();
+
+        assert_eq!(
+            config.upstream("main").as_deref(),
+            Some("refs/remotes/origin/main")
+        );
+    }
+
+    #[test]
+    fn upstream_returns_local_ref_given_dot_remote() {
+        let config = "[branch \"topic\"]\nremote = .\nmerge = refs/heads/main\n"
+            .parse::<GitConfig>()
+            .unwrap();
+
+        assert_eq!(config.upstream("topic").as_deref(), Some("refs/heads/main"));
+    }
+
+    #[test]
+    fn upstream_returns_none_given_no_tracking_config() {
+        let config = "[branch \"main\"]\nremote = origin\n"
+            .parse::<GitConfig>()
+            .unwrap();
+
+        assert_eq!(config.upstream("main"), None);
+        assert_eq!(config.upstream("other"), None);
+    }
+
+    #[test]
+    fn branch_description_strips_trailing_newline() {
+        let config = "[branch \"main\"]\n\
+             description = Add a feature\\nin two lines\\n\n\
+             [branch \"empty\"]\n\
+             description = \n"
+            .parse::<GitConfig>()
+            .unwrap();
+
+        assert_eq!(
+            config.branch_description("main").as_deref(),
+            Some("Add a feature\nin two lines")
+        );
+        assert_eq!(config.branch_description("empty"), None);
+        assert_eq!(config.branch_description("other"), None);
+    }
+
+    #[test]
+    fn remotes_returns_each_remote_once() {
+        let config = "[remote \"origin\"]\n\
+             url = https://example.com/repo.git\n\
+             [remote \"fork/alice\"]\n\
+             url = https://example.com/alice.git\n\
+             [branch \"main\"]\n\
+             remote = origin\n\
+             [remote \"origin\"]\n\
+             fetch = +refs/heads/*:refs/remotes/origin/*\n"
+            .parse::<GitConfig>()
+            .unwrap();
+
+        assert_eq!(config.remotes(), vec!["origin", "fork/alice"]);
+    }
+
+    #[test]
+    fn object_format_reads_extensions_object_format() {
+        let parse = |text: &str| text.parse::<GitConfig>().unwrap().object_format();
+
+        assert_eq!(parse(""), Ok(ObjectFormat::Sha1));
+        assert_eq!(
+            parse("[extensions]\n\tobjectFormat = sha256\n"),
+            Ok(ObjectFormat::Sha256)
+        );
+        assert_eq!(
+            parse("[extensions]\n\tobjectformat = SHA1\n"),
+            Ok(ObjectFormat::Sha1)
+        );
+        assert!(matches!(
+            parse("[extensions]\n\tobjectFormat = md5\n"),
+            Err(Error::Git(e)) if e.contains("unknown object format 'md5'")
+        ));
+    }
+
+    #[test]
+    fn from_file_returns_empty_config_given_missing_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let config =
+            GitConfig::from_file(&temp_dir.path().join("config"), &context(temp_dir.path()))
+                .unwrap();
+
+        assert_eq!(config, GitConfig::default());
+    }
+
+    #[test]
+    fn from_file_follows_relative_include_path() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config_path = temp_dir.path().join("config");
+        write!(
+            std::fs::File::create(&config_path).unwrap(),
+            "[a]\nx = 1\n[include]\npath = extra/included\n[a]\ny = 3\n"
+        )
+        .unwrap();
+        std::fs::create_dir(temp_dir.path().join("extra")).unwrap();
+        write!(
+            std::fs::File::create(temp_dir.path().join("extra").join("included")).unwrap(),
+            "[a]\nx = 2\ny = 2\n"
+        )
+        .unwrap();
+
+        let config = GitConfig::from_file(&config_path, &context(temp_dir.path())).unwrap();
+
+        // included values override earlier values but not later ones
+        assert_eq!(config.get("a", None, "x"), Some("2"));
+        assert_eq!(config.get("a", None, "y"), Some("3"));
+    }
+
+    #[test]
+    fn from_file_ignores_missing_include() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config_path = temp_dir.path().join("config");
+        write!(
+            std::fs::File::create(&config_path).unwrap(),
+            "[include]\npath = not_a_file\n[a]\nx = 1\n"
+        )
+        .unwrap();
+
+        let config = GitConfig::from_file(&config_path, &context(temp_dir.path())).unwrap();
+
+        assert_eq!(config.get("a", None, "x"), Some("1"));
+    }
+
+    #[test]
+    fn from_file_returns_err_given_recursive_include() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config_path = temp_dir.path().join("config");
+        write!(
+            std::fs::File::create(&config_path).unwrap(),
+            "[include]\npath = config\n"
+        )
+        .unwrap();
+
+        let result = GitConfig::from_file(&config_path, &context(temp_dir.path()));
+
+        assert!(result.unwrap_err().to_string().contains("include depth"));
+    }
+
+    #[test]
+    fn from_file_follows_include_if_gitdir_matches() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let git_dir = temp_dir.path().join("work").join("repo").join(".git");
+        let config_path = temp_dir.path().join("config");
+        write!(
+            std::fs::File::create(&config_path).unwrap(),
+            "[includeIf \"gitdir:{}/work/\"]\npath = work_config\n\
+             [includeIf \"gitdir:{}/personal/\"]\npath = personal_config\n",
+            temp_dir.path().to_string_lossy(),
+            temp_dir.path().to_string_lossy(),
+        )
+        .unwrap();
+        write!(
+            std::fs::File::create(temp_dir.path().join("work_config")).unwrap(),
+            "[a]\nx = work\n"
+        )
+        .unwrap();
+        write!(
+            std::fs::File::create(temp_dir.path().join("personal_config")).unwrap(),
+            "[a]\ny = personal\n"
+        )
+        .unwrap();
+
+        let config = GitConfig::from_file(&config_path, &context(&git_dir)).unwrap();
+
+        assert_eq!(config.get("a", None, "x"), Some("work"));
+        assert_eq!(config.get("a", None, "y"), None);
+    }
+
+    #[test]
+    fn from_file_follows_include_if_onbranch_matches() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config_path = temp_dir.path().join("config");
+        write!(
+            std::fs::File::create(&config_path).unwrap(),
+            "[includeIf \"onbranch:feature/\"]\npath = feature_config\n"
+        )
+        .unwrap();
+        write!(
+            std::fs::File::create(temp_dir.path().join("feature_config")).unwrap(),
+            "[a]\nx = 1\n"
+        )
+        .unwrap();
+        let on_feature = IncludeContext {
+            git_dir: temp_dir.path(),
+            branch: Some("feature/abc"),
+        };
+        let on_main = IncludeContext {
+            git_dir: temp_dir.path(),
+            branch: Some("main"),
+        };
+
+        let feature_config = GitConfig::from_file(&config_path, &on_feature).unwrap();
+        let main_config = GitConfig::from_file(&config_path, &on_main).unwrap();
+
+        assert_eq!(feature_config.get("a", None, "x"), Some("1"));
+        assert_eq!(main_config.get("a", None, "x"), None);
+    }
+
+    mod glob_matches {
+        use super::super::glob_matches;
+
+        #[test]
+        fn single_star_does_not_match_slash() {
+            assert!(glob_matches("/a/*/c", "/a/b/c", false));
+            assert!(!glob_matches("/a/*/c", "/a/b/x/c", false));
+        }
+
+        #[test]
+        fn double_star_matches_across_directories() {
+            assert!(glob_matches("**/repo/.git", "/home/user/repo/.git", false));
+            assert!(glob_matches("/home/**", "/home/user/repo/.git", false));
+            assert!(glob_matches("/home/**/.git", "/home/.git", false));
+        }
+
+        #[test]
+        fn matches_character_classes() {
+            assert!(glob_matches("v[0-9]", "v1", false));
+            assert!(!glob_matches("v[!0-9]", "v1", false));
+        }
+
+        #[test]
+        fn escapes_regex_characters() {
+            assert!(glob_matches("a.b+c", "a.b+c", false));
+            assert!(!glob_matches("a.b", "axb", false));
+        }
+
+        #[test]
+        fn matches_case_insensitively_if_requested() {
+            assert!(glob_matches("/Work/**", "/work/repo", true));
+            assert!(!glob_matches("/Work/**", "/work/repo", false));
+        }
+    }
+}
@@ -0,0 +1,2559 @@
+mod odb;
+mod reftable;
+
+use crate::git::config::{GitConfig, IncludeContext};
+use crate::git::object::ObjectId;
+use crate::git::{
+    assign_remotes, recent_checkouts, AheadBehind, Branch, BranchGetter, BranchType, ConfigGetter,
+    RecentGetter, StashGetter,
+};
+use crate::Error;
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+const GIT_DIR: &str = ".git";
+const GIT_FILE_PREFIX: &str = "gitdir:";
+const COMMON_DIR_FILE: &str = "commondir";
+const HEAD_FILE: &str = "HEAD";
+const SYMREF_PREFIX: &str = "ref:";
+const LOCAL_REF_PREFIX: &str = "refs/heads/";
+const REMOTE_REF_PREFIX: &str = "refs/remotes/";
+const TAG_REF_PREFIX: &str = "refs/tags/";
+const PEELED_PREFIX: u8 = b'^';
+const PACKED_REFS_FILE: &str = "packed-refs";
+const STASH_REF: &str = "refs/stash";
+const LOGS_DIR: &str = "logs";
+const MAX_SYMREF_DEPTH: usize = 5;
+const WORKTREES_DIR: &str = "worktrees";
+const WORKTREE_GITDIR_FILE: &str = "gitdir";
+const CONFIG_FILE: &str = "config";
+const GIT_DIR_ENV: &str = "GIT_DIR";
+const GIT_COMMON_DIR_ENV: &str = "GIT_COMMON_DIR";
+const GIT_WORK_TREE_ENV: &str = "GIT_WORK_TREE";
+const GIT_CEILING_DIRECTORIES_ENV: &str = "GIT_CEILING_DIRECTORIES";
+
+/// The value of a ref: an object ID or, for a symbolic ref, the full name of
+/// the ref it points to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RefValue {
+    /// The ref points to an object. For an annotated tag, `peeled` is the
+    /// object the tag points to, if known.
+    Object {
+        target: ObjectId,
+        peeled: Option<ObjectId>,
+    },
+    /// The ref is a symbolic ref pointing to another ref.
+    Symref(String),
+}
+
+/// Refs keyed by their full name, e.g., `refs/heads/main`. Names are kept as
+/// bytes, as git does not require them to be valid UTF-8.
+pub type Refs = BTreeMap<Vec<u8>, RefValue>;
+
+pub struct FsBranchGetter {
+    pub repo_dir: PathBuf,
+    pub env: GitEnv,
+}
+
+/// The git environment variables that affect repository discovery.
+///
+/// Relative paths are resolved against the directory discovery starts from,
+/// in the same way git resolves them against its working directory.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GitEnv {
+    /// `GIT_DIR`: use this git directory instead of searching for one.
+    pub git_dir: Option<PathBuf>,
+    /// `GIT_COMMON_DIR`: take shared refs from here, ignoring `commondir`.
+    pub common_dir: Option<PathBuf>,
+    /// `GIT_WORK_TREE`: the root of the working tree. This has no bearing on
+    /// which refs are read, but must exist if given.
+    pub work_tree: Option<PathBuf>,
+    /// `GIT_CEILING_DIRECTORIES`: directories the upward search for a
+    /// repository must not move into.
+    pub ceiling_dirs: Vec<PathBuf>,
+}
+
+impl GitEnv {
+    pub fn from_env() -> GitEnv {
+        GitEnv {
+            git_dir: env_path(GIT_DIR_ENV),
+            common_dir: env_path(GIT_COMMON_DIR_ENV),
+            work_tree: env_path(GIT_WORK_TREE_ENV),
+            ceiling_dirs: match std::env::var_os(GIT_CEILING_DIRECTORIES_ENV) {
+                Some(dirs) => std::env::split_paths(&dirs)
+                    .filter(|p| !p.as_os_str().is_empty())
+                    .collect(),
+                None => vec![],
+            },
+        }
+    }
+}
+
+fn env_path(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name)
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+}
+
+/// The directories making up a repository's git metadata.
+///
+/// For a normal checkout both paths are the `.git` directory. For a linked
+/// worktree, `git_dir` is the worktree's private directory (containing its
+/// `HEAD`) and `common_dir` is the main repository's `.git` directory, which
+/// holds the refs shared between all worktrees.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Repo {
+    pub git_dir: PathBuf,
+    pub common_dir: PathBuf,
+}
+
+impl BranchGetter for FsBranchGetter {
+    fn branches(&self, warnings: &mut Vec<String>) -> Result<Vec<Branch>, Error> {
+        let repo = discover_repo(&self.repo_dir, &self.env)?;
+        let config = read_repo_config(&repo)?;
+        let hash_len = config.object_format()?.hash_len();
+        let git_dir = &repo.common_dir;
+        let refs = match reftable::read_stack(git_dir)? {
+            Some(refs) => refs,
+            None => read_file_refs(git_dir, warnings)?,
+        };
+        let mut branches = vec![];
+        for (ref_name, value) in &refs {
+            if !is_valid_ref_name(ref_name) {
+                warnings.push(format!(
+                    "ignoring ref with broken name '{}'",
+                    String::from_utf8_lossy(ref_name)
+                ));
+                continue;
+            }
+            if matches!(value, RefValue::Object { target, .. } if target.as_bytes().len() != hash_len)
+            {
+                warnings.push(format!(
+                    "ignoring malformed ref '{}'",
+                    String::from_utf8_lossy(ref_name)
+                ));
+                continue;
+            }
+            branches.extend(make_branch(ref_name, value.clone()));
+        }
+        resolve_symrefs(&mut branches, &repo, warnings);
+        assign_remotes(&mut branches, &config.remotes());
+        mark_checked_out_branches(&mut branches, &repo, &config)?;
+        for branch in branches
+            .iter_mut()
+            .filter(|b| b.branch_type == BranchType::Local)
+        {
+            branch.upstream = config.upstream(&branch.name);
+            branch.description = config.branch_description(&branch.name);
+        }
+        mark_gone_upstreams(&mut branches, &refs, git_dir);
+        read_tip_commits(&mut branches, &open_odb(&repo, &config)?)?;
+        // branches are made in order of their ref names, like 'git branch'
+        Ok(branches)
+    }
+
+    fn resolve_rev(&self, rev: &str) -> Result<ObjectId, Error> {
+        let repo = discover_repo(&self.repo_dir, &self.env)?;
+        let odb = open_odb(&repo, &read_repo_config(&repo)?)?;
+        // like git, refs take precedence over (abbreviated) object IDs
+        let id = match resolve_ref(&repo, rev)? {
+            Some(id) => id,
+            None => odb
+                .resolve_abbrev(rev)?
+                .ok_or_else(|| Error::Git(format!("could not resolve '{rev}'")))?,
+        };
+        odb.peel_to_commit(&id)
+    }
+
+    fn reachable(&self, from: &ObjectId, commits: &[ObjectId]) -> Result<Vec<bool>, Error> {
+        let repo = discover_repo(&self.repo_dir, &self.env)?;
+        open_odb(&repo, &read_repo_config(&repo)?)?.reachable(from, commits)
+    }
+
+    fn ahead_behind(&self, pairs: &[(ObjectId, ObjectId)]) -> Result<Vec<AheadBehind>, Error> {
+        let repo = discover_repo(&self.repo_dir, &self.env)?;
+        let odb = open_odb(&repo, &read_repo_config(&repo)?)?;
+        pairs
+            .iter()
+            .map(|(commit, base)| odb.ahead_behind(commit, base))
+            .collect()
+    }
+}
+
+impl StashGetter for FsBranchGetter {
+    fn stashes(&self) -> Result<Vec<Branch>, Error> {
+        let repo = discover_repo(&self.repo_dir, &self.env)?;
+        if read_ref(&repo, STASH_REF, 0)?.is_none() {
+            return Ok(vec![]);
+        }
+        // the stash's entries are only recorded in its reflog, which is kept
+        // in log blocks in a reftable repository
+        if reftable::read_stack(&repo.common_dir)?.is_some() {
+            return Err(Error::Git(
+                "cannot read the stash of a reftable repository, try '--backend git'".to_string(),
+            ));
+        }
+        let odb = open_odb(&repo, &read_repo_config(&repo)?)?;
+        let reflog = read_reflog(&repo.common_dir.join(LOGS_DIR).join(STASH_REF))?;
+        // the newest entry, 'stash@{0}', is at the end of the reflog
+        reflog
+            .into_iter()
+            .rev()
+            .enumerate()
+            .map(|(i, (target, message))| {
+                Ok(Branch {
+                    name: format!("stash@{{{i}}}"),
+                    branch_type: BranchType::Stash,
+                    commit: odb.find_commit(&target)?,
+                    target: Some(target),
+                    message: Some(message),
+                    ..Default::default()
+                })
+            })
+            .collect()
+    }
+}
+
+impl ConfigGetter for FsBranchGetter {
+    fn config_values(&self, section: &str, key: &str) -> Result<Vec<String>, Error> {
+        let repo = discover_repo(&self.repo_dir, &self.env)?;
+        let config = read_repo_config(&repo)?;
+        Ok(config
+            .get_all(section, None, key)
+            .into_iter()
+            .map(str::to_string)
+            .collect())
+    }
+}
+
+impl RecentGetter for FsBranchGetter {
+    fn recent_branches(&self) -> Result<Vec<String>, Error> {
+        let repo = discover_repo(&self.repo_dir, &self.env)?;
+        if reftable::read_stack(&repo.common_dir)?.is_some() {
+            return Err(Error::Git(
+                "cannot read the reflog of a reftable repository, try '--backend git'".to_string(),
+            ));
+        }
+        // each worktree has its own HEAD, and so its own HEAD reflog
+        let reflog = read_reflog(&repo.git_dir.join(LOGS_DIR).join(HEAD_FILE))?;
+        Ok(recent_checkouts(
+            reflog.iter().rev().map(|(_, message)| message.as_str()),
+        ))
+    }
+}
+
+/// Read the entries of a reflog, oldest first, returning the object ID each
+/// entry set the ref to and its message. Each line of a reflog is
+/// `<old ID> <new ID> <committer> <time> <tz>\t<message>`. Malformed lines
+/// are skipped, as git does.
+fn read_reflog(log_file: &Path) -> Result<Vec<(ObjectId, String)>, Error> {
+    if !log_file.is_file() {
+        return Ok(vec![]);
+    }
+    let content = std::fs::read(log_file).map_err(|e| {
+        Error::Git(format!(
+            "could not read '{}': {e}",
+            log_file.to_string_lossy()
+        ))
+    })?;
+    Ok(String::from_utf8_lossy(&content)
+        .lines()
+        .filter_map(|line| {
+            let (entry, message) = line.split_once('\t').unwrap_or((line, ""));
+            let target = ObjectId::from_full_hex(entry.split(' ').nth(1)?)?;
+            Some((target, message.to_string()))
+        })
+        .collect())
+}
+
+/// Read the refs stored in the packed refs file and as loose refs under
+/// `refs/heads/`, `refs/remotes/` and `refs/tags/`. As in git, a loose ref
+/// takes precedence over a packed ref with the same name, as the packed ref is
+/// left behind when the ref is updated. Refs that cannot be parsed are
+/// skipped, with a warning.
+fn read_file_refs(git_dir: &Path, warnings: &mut Vec<String>) -> Result<Refs, Error> {
+    let mut refs = parse_packed_refs(&git_dir.join(PACKED_REFS_FILE), warnings)?;
+    for prefix in [LOCAL_REF_PREFIX, REMOTE_REF_PREFIX, TAG_REF_PREFIX] {
+        refs.extend(read_loose_refs(git_dir, prefix, warnings)?);
+    }
+    Ok(refs)
+}
+
+/// Make a branch (or tag) from a ref. The target of a symbolic ref is left
+/// unset, to be filled in by `resolve_symrefs`. Returns `None` for refs that
+/// are neither branches nor tags.
+fn make_branch(ref_name: &[u8], value: RefValue) -> Option<Branch> {
+    let (name, branch_type) = split_ref_name(ref_name)?;
+    let (target, peeled, symref) = match value {
+        RefValue::Object { target, peeled } => (Some(target), peeled, None),
+        RefValue::Symref(symref) => (None, None, Some(symref)),
+    };
+    let mut branch = Branch {
+        branch_type,
+        symref,
+        target,
+        peeled,
+        ..Default::default()
+    };
+    branch.set_name(name);
+    Some(branch)
+}
+
+/// Set the target of each symbolic ref to that of the ref it points to. As
+/// with `git for-each-ref`, symbolic refs that point to missing refs are
+/// skipped, with a warning.
+fn resolve_symrefs(branches: &mut Vec<Branch>, repo: &Repo, warnings: &mut Vec<String>) {
+    branches.retain_mut(|branch| {
+        let Some(symref) = &branch.symref else {
+            return true;
+        };
+        match read_ref(repo, symref, 1) {
+            Ok(Some(target)) => {
+                branch.target = Some(target);
+                true
+            }
+            _ => {
+                warnings.push(format!(
+                    "ignoring broken symbolic ref '{}'",
+                    branch.ref_name()
+                ));
+                false
+            }
+        }
+    });
+}
+
+/// Split a full ref name into a branch's (or tag's) short name and type.
+/// Returns `None` for refs that are neither branches nor tags.
+fn split_ref_name(ref_name: &[u8]) -> Option<(&[u8], BranchType)> {
+    if let Some(name) = ref_name.strip_prefix(LOCAL_REF_PREFIX.as_bytes()) {
+        Some((name, BranchType::Local))
+    } else if let Some(name) = ref_name.strip_prefix(REMOTE_REF_PREFIX.as_bytes()) {
+        Some((name, BranchType::Remote))
+    } else {
+        Some((
+            ref_name.strip_prefix(TAG_REF_PREFIX.as_bytes())?,
+            BranchType::Tag,
+        ))
+    }
+}
+
+/// Whether a full ref name follows git's rules for ref names (see
+/// `git check-ref-format`). Bytes outside of ASCII are allowed, so the name
+/// need not be valid UTF-8.
+fn is_valid_ref_name(ref_name: &[u8]) -> bool {
+    if ref_name == b"@"
+        || ref_name.ends_with(b".")
+        || ref_name.windows(2).any(|w| w == b".." || w == b"@{")
+        || ref_name
+            .iter()
+            .any(|b| b.is_ascii_control() || b" ~^:?*[\\".contains(b))
+    {
+        return false;
+    }
+    // this also rejects empty names and names with leading, trailing or
+    // repeated slashes
+    ref_name.split(|b| *b == b'/').all(|component| {
+        !component.is_empty() && !component.starts_with(b".") && !component.ends_with(b".lock")
+    })
+}
+
+/// Find the ref a short name refers to, using the same precedence as git
+/// (e.g., a tag shadows a branch with the same name), and read its target.
+fn resolve_ref(repo: &Repo, name: &str) -> Result<Option<ObjectId>, Error> {
+    let mut candidates = vec![];
+    // only pseudo-refs like 'HEAD' and 'ORIG_HEAD' are read from the top of
+    // the git dir
+    if name.starts_with("refs/") || name.chars().all(|c| c.is_ascii_uppercase() || c == '_') {
+        candidates.push(name.to_string());
+    }
+    candidates.extend([
+        format!("refs/{name}"),
+        format!("refs/tags/{name}"),
+        format!("{LOCAL_REF_PREFIX}{name}"),
+        format!("refs/remotes/{name}"),
+        format!("refs/remotes/{name}/{HEAD_FILE}"),
+    ]);
+    for candidate in candidates {
+        if let Some(id) = read_ref(repo, &candidate, 0)? {
+            return Ok(Some(id));
+        }
+    }
+    Ok(None)
+}
+
+/// Read the object ID a ref points to, following symbolic refs. Returns
+/// `None` if the ref does not exist.
+fn read_ref(repo: &Repo, name: &str, depth: usize) -> Result<Option<ObjectId>, Error> {
+    if depth > MAX_SYMREF_DEPTH {
+        return Err(Error::Git(format!(
+            "too many levels of symbolic refs at '{name}'"
+        )));
+    }
+    // refs outside of 'refs/' (e.g., 'HEAD') belong to the current worktree
+    let dir = if name.starts_with("refs/") {
+        &repo.common_dir
+    } else {
+        &repo.git_dir
+    };
+    if let Some(refs) = reftable::read_stack(dir)? {
+        return match refs.get(name.as_bytes()) {
+            Some(RefValue::Symref(target)) => read_ref(repo, target, depth + 1),
+            Some(RefValue::Object { target, .. }) => Ok(Some(target.clone())),
+            None => Ok(None),
+        };
+    }
+    let ref_file = dir.join(name);
+    if ref_file.is_file() {
+        let content = std::fs::read_to_string(&ref_file).map_err(|e| {
+            Error::Git(format!(
+                "could not read '{}': {e}",
+                ref_file.to_string_lossy()
+            ))
+        })?;
+        return match parse_ref_value(&content) {
+            Some(RefValue::Symref(target)) => read_ref(repo, &target, depth + 1),
+            Some(RefValue::Object { target, .. }) => Ok(Some(target)),
+            None => Err(Error::Git(format!("invalid ref '{name}'"))),
+        };
+    }
+    find_packed_ref(&repo.common_dir.join(PACKED_REFS_FILE), name)
+}
+
+fn find_packed_ref(packed_file: &PathBuf, name: &str) -> Result<Option<ObjectId>, Error> {
+    // malformed lines are warned about when listing branches
+    let refs = parse_packed_refs(packed_file, &mut vec![])?;
+    Ok(match refs.get(name.as_bytes()) {
+        Some(RefValue::Object { target, .. }) => Some(target.clone()),
+        _ => None,
+    })
+}
+
+/// Read the commit each branch points to. Branches whose commit is missing
+/// (e.g., in a shallow or partial clone) are left without one, but objects
+/// that exist and cannot be read are errors.
+fn read_tip_commits(branches: &mut [Branch], odb: &odb::ObjectDb) -> Result<(), Error> {
+    for branch in branches.iter_mut() {
+        // only packed refs record what a tag peels to, so peel loose tags here
+        if branch.branch_type == BranchType::Tag && branch.peeled.is_none() {
+            if let Some(target) = &branch.target {
+                branch.peeled = odb.find_peeled_commit(target)?.filter(|id| id != target);
+            }
+        }
+        branch.commit = match branch.tip() {
+            Some(id) => odb.find_commit(id)?,
+            None => None,
+        };
+    }
+    Ok(())
+}
+
+/// Set `upstream_gone` on branches whose upstream ref no longer exists, e.g.,
+/// because the remote branch was deleted and then pruned.
+fn mark_gone_upstreams(branches: &mut [Branch], refs: &Refs, common_dir: &Path) {
+    for branch in branches.iter_mut() {
+        if let Some(upstream) = &branch.upstream {
+            // loose refs are only read from 'refs/heads', 'refs/remotes' and
+            // 'refs/tags', but custom refspecs can map upstreams elsewhere
+            branch.upstream_gone =
+                !refs.contains_key(upstream.as_bytes()) && !common_dir.join(upstream).is_file();
+        }
+    }
+}
+
+/// Open the repository's object database, using the object format set in its
+/// config.
+fn open_odb(repo: &Repo, config: &GitConfig) -> Result<odb::ObjectDb, Error> {
+    odb::ObjectDb::open(&repo.common_dir.join("objects"), config.object_format()?)
+}
+
+fn read_repo_config(repo: &Repo) -> Result<GitConfig, Error> {
+    let head = read_head(&repo.git_dir)?;
+    let context = IncludeContext {
+        git_dir: &repo.git_dir,
+        branch: head
+            .as_deref()
+            .and_then(|h| h.strip_prefix(LOCAL_REF_PREFIX)),
+    };
+    GitConfig::from_file(&repo.common_dir.join(CONFIG_FILE), &context)
+}
+
+/// A working tree attached to a repository.
+struct Worktree {
+    /// The directory holding the worktree's private metadata, e.g., `HEAD`.
+    git_dir: PathBuf,
+    /// The root of the worktree's checkout.
+    path: PathBuf,
+    /// Whether this is the main worktree of a bare repository, which has no
+    /// checkout.
+    is_bare: bool,
+}
+
+/// Set `is_head` on the branch checked out in the current worktree, and
+/// `worktree` on branches checked out in any other worktree.
+fn mark_checked_out_branches(
+    branches: &mut [Branch],
+    repo: &Repo,
+    config: &GitConfig,
+) -> Result<(), Error> {
+    for worktree in list_worktrees(&repo.common_dir, config)? {
+        let head = read_head(&worktree.git_dir)?;
+        let head_branch = match head
+            .as_deref()
+            .and_then(|h| h.strip_prefix(LOCAL_REF_PREFIX))
+        {
+            Some(b) => b,
+            None => continue,
+        };
+        let is_current = worktree.git_dir == repo.git_dir;
+        if worktree.is_bare && !is_current {
+            // like git, a bare repository's HEAD does not count as checked out
+            continue;
+        }
+        for branch in branches
+            .iter_mut()
+            .filter(|b| b.branch_type == BranchType::Local && b.name == head_branch)
+        {
+            if is_current {
+                branch.is_head = true;
+            } else {
+                branch.worktree = Some(worktree.path.clone());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// List the main worktree and any linked worktrees of the repository.
+///
+/// If `core.bare` is not set, a repository whose git directory is not named
+/// `.git` is assumed to be bare. The main worktree of a bare repository has
+/// the repository itself as its path.
+fn list_worktrees(common_dir: &Path, config: &GitConfig) -> Result<Vec<Worktree>, Error> {
+    let is_dot_git = common_dir.file_name().is_some_and(|name| name == GIT_DIR);
+    let is_bare = config.get_bool("core", None, "bare").unwrap_or(!is_dot_git);
+    let main_path = match common_dir.parent() {
+        Some(parent) if !is_bare => parent,
+        _ => common_dir,
+    };
+    let mut worktrees = vec![Worktree {
+        git_dir: common_dir.to_path_buf(),
+        path: main_path.to_path_buf(),
+        is_bare,
+    }];
+    let worktrees_dir = common_dir.join(WORKTREES_DIR);
+    if !worktrees_dir.is_dir() {
+        return Ok(worktrees);
+    }
+    let entries = std::fs::read_dir(&worktrees_dir)
+        .map_err(|e| Error::Git(format!("could not list worktrees: {e}")))?;
+    for entry in entries {
+        let wt_git_dir = entry
+            .map_err(|e| Error::Git(format!("could not list worktrees: {e}")))?
+            .path();
+        if !wt_git_dir.is_dir() {
+            continue;
+        }
+        // 'gitdir' holds the path to the worktree's '.git' file; if it's
+        // missing, the worktree is stale, but we still report its branch
+        let path = std::fs::read_to_string(wt_git_dir.join(WORKTREE_GITDIR_FILE))
+            .ok()
+            .map(|p| PathBuf::from(p.trim()))
+            .and_then(|p| p.parent().map(|p| p.to_path_buf()))
+            .unwrap_or_else(|| wt_git_dir.clone());
+        worktrees.push(Worktree {
+            git_dir: wt_git_dir,
+            path,
+            is_bare: false,
+        });
+    }
+    Ok(worktrees)
+}
+
+/// Read the ref `HEAD` points to, returning `None` if `HEAD` is detached
+/// (i.e., contains an object ID) or does not exist.
+fn read_head(git_dir: &Path) -> Result<Option<String>, Error> {
+    // in a reftable repository, the 'HEAD' file is only a placeholder for
+    // older versions of git
+    if let Some(refs) = reftable::read_stack(git_dir)? {
+        return Ok(match refs.get(HEAD_FILE.as_bytes()) {
+            Some(RefValue::Symref(target)) => Some(target.clone()),
+            _ => None,
+        });
+    }
+    let head_file = git_dir.join(HEAD_FILE);
+    if !head_file.is_file() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(&head_file).map_err(|e| {
+        Error::Git(format!(
+            "could not read '{}': {e}",
+            head_file.to_string_lossy()
+        ))
+    })?;
+    Ok(match parse_ref_value(&content) {
+        Some(RefValue::Symref(target)) => Some(target),
+        _ => None,
+    })
+}
+
+/// Parse the contents of a loose ref file: a full object ID or, for a symbolic
+/// ref, `ref:` followed by the name of the ref it points to. Returns `None` if
+/// the contents are malformed.
+fn parse_ref_value(content: &str) -> Option<RefValue> {
+    let content = content.trim();
+    if let Some(target) = content.strip_prefix(SYMREF_PREFIX) {
+        let target = target.trim_start();
+        return (!target.is_empty()).then(|| RefValue::Symref(target.to_string()));
+    }
+    ObjectId::from_full_hex(content).map(|target| RefValue::Object {
+        target,
+        peeled: None,
+    })
+}
+
+/// Read the loose refs whose names start with `prefix` (e.g., `refs/heads/`),
+/// returning each ref's full name and value. Refs that cannot be parsed are
+/// skipped, with a warning.
+fn read_loose_refs(
+    git_dir: &Path,
+    prefix: &str,
+    warnings: &mut Vec<String>,
+) -> Result<Refs, Error> {
+    let dir = git_dir.join(prefix);
+    if !dir.is_dir() {
+        return Ok(Refs::new());
+    }
+    read_loose_refs_inner(&dir, prefix.as_bytes(), warnings)
+        .map_err(|e| Error::Git(format!("could not parse refs: {e}")))
+}
+
+fn read_loose_refs_inner(
+    dir: &Path,
+    prefix: &[u8],
+    warnings: &mut Vec<String>,
+) -> Result<Refs, Box<dyn std::error::Error>> {
+    let mut refs = Refs::new();
+    for res_item in std::fs::read_dir(dir)? {
+        let item = res_item?;
+        let ref_name = [prefix, &file_name_bytes(&item.file_name())].concat();
+        if item.file_type()?.is_file() {
+            let content = std::fs::read(item.path())?;
+            match std::str::from_utf8(&content).ok().and_then(parse_ref_value) {
+                Some(value) => {
+                    refs.insert(ref_name, value);
+                }
+                None => warnings.push(format!(
+                    "ignoring malformed ref '{}'",
+                    String::from_utf8_lossy(&ref_name)
+                )),
+            }
+        } else if item.file_type()?.is_dir() {
+            refs.extend(read_loose_refs_inner(
+                &item.path(),
+                &[&ref_name[..], b"/"].concat(),
+                warnings,
+            )?);
+        }
+    }
+    Ok(refs)
+}
+
+/// The raw bytes of a file name, which on Unix need not be valid UTF-8.
+#[cfg(unix)]
+fn file_name_bytes(file_name: &std::ffi::OsStr) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    file_name.as_bytes().to_vec()
+}
+
+/// The raw bytes of a file name. File names on this platform are Unicode, so
+/// are always valid UTF-8.
+#[cfg(not(unix))]
+fn file_name_bytes(file_name: &std::ffi::OsStr) -> Vec<u8> {
+    file_name.to_string_lossy().as_bytes().to_vec()
+}
+
+fn parse_packed_refs(packed_file: &PathBuf, warnings: &mut Vec<String>) -> Result<Refs, Error> {
+    if !packed_file.is_file() {
+        return Ok(Refs::new());
+    }
+    parse_packed_refs_inner(packed_file, warnings)
+        .map_err(|e| Error::Git(format!("could not parse packed refs: {e}")))
+}
+
+fn parse_packed_refs_inner(
+    packed_file: &PathBuf,
+    warnings: &mut Vec<String>,
+) -> Result<Refs, Box<dyn std::error::Error>> {
+    let mut refs = Refs::new();
+    // the ref on the previous line, which a following peeled line belongs to
+    let mut previous: Option<Vec<u8>> = None;
+    // ref names need not be valid UTF-8, so read the file as bytes
+    for line in std::fs::read(packed_file)?.split(|b| *b == b'\n') {
+        let line = line.trim_ascii();
+        if line.is_empty() || line.starts_with(b"#") {
+            continue;
+        }
+        if let Some(peeled) = line.strip_prefix(&[PEELED_PREFIX]) {
+            if let Some(RefValue::Object { peeled: value, .. }) =
+                previous.as_ref().and_then(|name| refs.get_mut(name))
+            {
+                *value = std::str::from_utf8(peeled)
+                    .ok()
+                    .and_then(ObjectId::from_full_hex);
+            }
+            continue;
+        }
+        previous = None;
+        let Some(space) = line.iter().position(|b| *b == b' ') else {
+            warnings.push(format!(
+                "ignoring malformed packed ref line '{}'",
+                String::from_utf8_lossy(line)
+            ));
+            continue;
+        };
+        let (target, ref_name) = (&line[..space], &line[space + 1..]);
+        let Some(target) = std::str::from_utf8(target)
+            .ok()
+            .and_then(ObjectId::from_full_hex)
+        else {
+            warnings.push(format!(
+                "ignoring malformed ref '{}'",
+                String::from_utf8_lossy(ref_name)
+            ));
+            continue;
+        };
+        refs.insert(
+            ref_name.to_vec(),
+            RefValue::Object {
+                target,
+                peeled: None,
+            },
+        );
+        previous = Some(ref_name.to_vec());
+    }
+    Ok(refs)
+}
+
+fn discover_repo(dir: &PathBuf, env: &GitEnv) -> Result<Repo, Error> {
+    let start_dir = std::fs::canonicalize(dir)
+        .map_err(|_| Error::Git(format!("'{}' not a directory", dir.to_string_lossy())))?;
+    if let Some(work_tree) = &env.work_tree {
+        if !start_dir.join(work_tree).is_dir() {
+            return Err(Error::Git(format!(
+                "invalid work tree '{}'",
+                work_tree.to_string_lossy()
+            )));
+        }
+    }
+    let git_dir = match &env.git_dir {
+        Some(git_dir) => resolve_git_dir(&start_dir.join(git_dir))?,
+        None => find_git_dir(&start_dir, &env.ceiling_dirs)?.ok_or_else(|| {
+            Error::Git(format!(
+                "not a git repository (or any of its parents): '{}'",
+                dir.to_string_lossy()
+            ))
+        })?,
+    };
+    let common_dir = match &env.common_dir {
+        Some(common_dir) => std::fs::canonicalize(start_dir.join(common_dir)).map_err(|_| {
+            Error::Git(format!(
+                "invalid common dir '{}'",
+                common_dir.to_string_lossy()
+            ))
+        })?,
+        None => read_common_dir(&git_dir)?,
+    };
+    Ok(Repo {
+        git_dir,
+        common_dir,
+    })
+}
+
+fn find_git_dir(start_dir: &Path, ceiling_dirs: &[PathBuf]) -> Result<Option<PathBuf>, Error> {
+    // like git, only ceilings strictly above the start directory apply, and
+    // the ceiling directory itself is never searched
+    let ceiling = ceiling_dirs
+        .iter()
+        .filter(|c| c.is_absolute())
+        .filter_map(|c| std::fs::canonicalize(c).ok())
+        .filter(|c| start_dir.starts_with(c) && start_dir != c)
+        .max_by_key(|c| c.components().count());
+    let mut current_dir = start_dir.to_path_buf();
+    loop {
+        let dot_git = current_dir.join(GIT_DIR);
+        if dot_git.is_dir() {
+            return Ok(Some(dot_git));
+        } else if dot_git.is_file() {
+            // worktrees and submodules have a '.git' file pointing elsewhere
+            return read_git_file(&dot_git).map(Some);
+        } else if is_bare_git_dir(&current_dir) {
+            return Ok(Some(current_dir));
+        }
+        current_dir = match current_dir.parent() {
+            Some(p) if Some(p) != ceiling.as_deref() => p.to_path_buf(),
+            _ => return Ok(None),
+        }
+    }
+}
+
+/// Whether the directory looks like a bare repository, using the same check as
+/// git: it must contain a `HEAD` file and `objects` and `refs` directories.
+fn is_bare_git_dir(dir: &Path) -> bool {
+    dir.join(HEAD_FILE).is_file() && dir.join("objects").is_dir() && dir.join("refs").is_dir()
+}
+
+fn resolve_git_dir(path: &Path) -> Result<PathBuf, Error> {
+    if path.is_dir() {
+        return std::fs::canonicalize(path).map_err(|e| {
+            Error::Git(format!(
+                "could not resolve git dir '{}': {e}",
+                path.to_string_lossy()
+            ))
+        });
+    } else if path.is_file() {
+        return read_git_file(path);
+    }
+    Err(Error::Git(format!(
+        "not a git repository: '{}'",
+        path.to_string_lossy()
+    )))
+}
+
+fn read_git_file(git_file: &Path) -> Result<PathBuf, Error> {
+    let content = std::fs::read_to_string(git_file).map_err(|e| {
+        Error::Git(format!(
+            "could not read git file '{}': {e}",
+            git_file.to_string_lossy()
+        ))
+    })?;
+    let target = content
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix(GIT_FILE_PREFIX))
+        .map(|path| path.trim())
+        .filter(|path| !path.is_empty())
+        .ok_or_else(|| {
+            Error::Git(format!(
+                "invalid gitfile format: '{}'",
+                git_file.to_string_lossy()
+            ))
+        })?;
+    // relative paths in a git file are relative to the file's directory
+    let base_dir = git_file.parent().unwrap_or_else(|| Path::new("."));
+    std::fs::canonicalize(base_dir.join(target)).map_err(|_| {
+        Error::Git(format!(
+            "not a git repository: '{}' (from '{}')",
+            target,
+            git_file.to_string_lossy()
+        ))
+    })
+}
+
+fn read_common_dir(git_dir: &Path) -> Result<PathBuf, Error> {
+    let common_dir_file = git_dir.join(COMMON_DIR_FILE);
+    if !common_dir_file.is_file() {
+        return Ok(git_dir.to_path_buf());
+    }
+    let content = std::fs::read_to_string(&common_dir_file).map_err(|e| {
+        Error::Git(format!(
+            "could not read '{}': {e}",
+            common_dir_file.to_string_lossy()
+        ))
+    })?;
+    // 'commondir' may hold an absolute path or one relative to the git dir
+    std::fs::canonicalize(git_dir.join(content.trim())).map_err(|_| {
+        Error::Git(format!(
+            "invalid common dir '{}' in '{}'",
+            content.trim(),
+            common_dir_file.to_string_lossy()
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use same_file::is_same_file;
+
+    use super::*;
+
+    fn make_test_git_dir() -> Result<tempfile::TempDir, Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        populate_git_dir(&temp_dir.path().join(GIT_DIR))?;
+        Ok(temp_dir)
+    }
+
+    fn make_bare_test_repo(
+        parent_dir: &Path,
+        name: &str,
+    ) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let git_dir = parent_dir.join(name);
+        populate_git_dir(&git_dir)?;
+        std::fs::create_dir_all(git_dir.join("objects"))?;
+        std::fs::File::create(git_dir.join("HEAD"))?
+            .write_all("ref: refs/heads/main\n".as_bytes())?;
+        Ok(git_dir)
+    }
+
+    fn populate_git_dir(git_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        // make local branches
+        let heads_dir = git_dir.join("refs").join("heads");
+        std::fs::create_dir_all(&heads_dir).unwrap();
+        std::fs::File::create(heads_dir.join("main"))?
+            .write_all("e2bf29060f42743538be07c164820cdeca0d9d2b".as_bytes())?;
+        std::fs::File::create(heads_dir.join("other_branch"))?
+            .write_all("a9c68440003151dd3cf7ffa4eaedd425d221d268".as_bytes())?;
+        std::fs::create_dir(heads_dir.join("user")).unwrap();
+        std::fs::File::create(heads_dir.join("user").join("some_dev_branch"))?
+            .write_all("da7d6bf0955fa4d511067c00551fee04c613079d".as_bytes())?;
+
+        // make remote branches in 'origin'
+        let origin_dir = git_dir.join("refs").join("remotes").join("origin");
+        std::fs::create_dir_all(&origin_dir)?;
+        std::fs::File::create(origin_dir.join("main"))?
+            .write_all("e2bf29060f42743538be07c164820cdeca0d9d2b".as_bytes())?;
+        std::fs::File::create(origin_dir.join("remote_branch"))?
+            .write_all("e2bf29060f42743538be07c164820cdeca0d9d2b".as_bytes())?;
+        // git writes 'origin/HEAD' as a symbolic ref, which resolving the rev
+        // 'origin' follows, so it must have the 'ref: ' prefix to be read
+        std::fs::File::create(origin_dir.join("HEAD"))?
+            .write_all("ref: refs/remotes/origin/main\n".as_bytes())?;
+
+        // make remote branches in 'upstream'
+        let upstream_dir = git_dir.join("refs").join("remotes").join("upstream");
+        std::fs::create_dir_all(&upstream_dir)?;
+        std::fs::File::create(upstream_dir.join("main"))?
+            .write_all("707a178071655bed661318a5344557fe3e9a6ce1".as_bytes())?;
+
+        // make packed refs
+        let packed_refs = git_dir.join("packed-refs");
+        std::fs::File::create(packed_refs)?.write_all(
+            [
+                "# pack-refs with: peeled fully-peeled sorted",
+                "6e442625fe1a269a068c369e58ce11295dcdeb0c refs/remotes/origin/packed",
+                "6e442625fe1a269a068c369e58ce11295dcdeb0c refs/tags/v0.1.0",
+                "^a513aa4efda3f06f316feb6b5df23d32480c435e",
+                "12344f25fe1a269a068c369e58ce11295dcdeb0c refs/heads/upstream/packed_2",
+                "",
+            ]
+            .join("\n")
+            .as_bytes(),
+        )?;
+        Ok(())
+    }
+
+    fn make_linked_worktree(
+        main_dir: &Path,
+        name: &str,
+    ) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let wt_git_dir = main_dir.join(GIT_DIR).join("worktrees").join(name);
+        std::fs::create_dir_all(&wt_git_dir)?;
+        std::fs::File::create(wt_git_dir.join("HEAD"))?
+            .write_all("ref: refs/heads/other_branch\n".as_bytes())?;
+        std::fs::File::create(wt_git_dir.join(COMMON_DIR_FILE))?.write_all("../..\n".as_bytes())?;
+
+        let worktree = main_dir.join(format!("{name}_checkout"));
+        std::fs::create_dir_all(&worktree)?;
+        std::fs::File::create(wt_git_dir.join(WORKTREE_GITDIR_FILE))?
+            .write_all(format!("{}\n", worktree.join(GIT_DIR).to_string_lossy()).as_bytes())?;
+        std::fs::File::create(worktree.join(GIT_DIR))?
+            .write_all(format!("gitdir: {}\n", wt_git_dir.to_string_lossy()).as_bytes())?;
+        Ok(worktree)
+    }
+
+    fn expected_branches() -> Vec<Branch> {
+        // sort these alphabetically for easier comparison in tests
+        vec![
+            Branch {
+                name: "main".to_string(),
+                branch_type: BranchType::Local,
+                target: ObjectId::from_hex("e2bf29060f42743538be07c164820cdeca0d9d2b"),
+                ..Default::default()
+            },
+            Branch {
+                name: "origin/HEAD".to_string(),
+                branch_type: BranchType::Remote,
+                remote: Some("origin".to_string()),
+                symref: Some("refs/remotes/origin/main".to_string()),
+                target: ObjectId::from_hex("e2bf29060f42743538be07c164820cdeca0d9d2b"),
+                ..Default::default()
+            },
+            Branch {
+                name: "origin/main".to_string(),
+                branch_type: BranchType::Remote,
+                remote: Some("origin".to_string()),
+                target: ObjectId::from_hex("e2bf29060f42743538be07c164820cdeca0d9d2b"),
+                ..Default::default()
+            },
+            Branch {
+                name: "origin/packed".to_string(),
+                branch_type: BranchType::Remote,
+                remote: Some("origin".to_string()),
+                target: ObjectId::from_hex("6e442625fe1a269a068c369e58ce11295dcdeb0c"),
+                ..Default::default()
+            },
+            Branch {
+                name: "origin/remote_branch".to_string(),
+                branch_type: BranchType::Remote,
+                remote: Some("origin".to_string()),
+                target: ObjectId::from_hex("e2bf29060f42743538be07c164820cdeca0d9d2b"),
+                ..Default::default()
+            },
+            Branch {
+                name: "other_branch".to_string(),
+                branch_type: BranchType::Local,
+                target: ObjectId::from_hex("a9c68440003151dd3cf7ffa4eaedd425d221d268"),
+                ..Default::default()
+            },
+            Branch {
+                name: "upstream/main".to_string(),
+                branch_type: BranchType::Remote,
+                remote: Some("upstream".to_string()),
+                target: ObjectId::from_hex("707a178071655bed661318a5344557fe3e9a6ce1"),
+                ..Default::default()
+            },
+            Branch {
+                name: "upstream/packed_2".to_string(),
+                branch_type: BranchType::Local,
+                target: ObjectId::from_hex("12344f25fe1a269a068c369e58ce11295dcdeb0c"),
+                ..Default::default()
+            },
+            Branch {
+                name: "user/some_dev_branch".to_string(),
+                branch_type: BranchType::Local,
+                target: ObjectId::from_hex("da7d6bf0955fa4d511067c00551fee04c613079d"),
+                ..Default::default()
+            },
+            Branch {
+                name: "v0.1.0".to_string(),
+                branch_type: BranchType::Tag,
+                target: ObjectId::from_hex("6e442625fe1a269a068c369e58ce11295dcdeb0c"),
+                peeled: ObjectId::from_hex("a513aa4efda3f06f316feb6b5df23d32480c435e"),
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn fs_branch_getter_retrieves_all_branches() {
+        let temp_dir = make_test_git_dir().unwrap();
+
+        let getter = FsBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: GitEnv::default(),
+        };
+        let mut branches = getter.branches(&mut vec![]).unwrap();
+
+        branches.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(branches, expected_branches());
+    }
+
+    #[test]
+    fn fs_branch_getter_returns_branches_ordered_by_ref_name() {
+        let temp_dir = make_test_git_dir().unwrap();
+
+        let getter = FsBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: GitEnv::default(),
+        };
+        let branches = getter.branches(&mut vec![]).unwrap();
+
+        let ref_names: Vec<String> = branches.iter().map(|b| b.ref_name()).collect();
+        assert_eq!(
+            ref_names,
+            vec![
+                "refs/heads/main",
+                "refs/heads/other_branch",
+                "refs/heads/upstream/packed_2",
+                "refs/heads/user/some_dev_branch",
+                "refs/remotes/origin/HEAD",
+                "refs/remotes/origin/main",
+                "refs/remotes/origin/packed",
+                "refs/remotes/origin/remote_branch",
+                "refs/remotes/upstream/main",
+                "refs/tags/v0.1.0",
+            ]
+        );
+    }
+
+    #[test]
+    fn fs_branch_getter_returns_empty_vec_given_no_branches() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let git_dir = temp_dir.path().join(GIT_DIR);
+        std::fs::create_dir_all(git_dir).unwrap();
+
+        let getter = FsBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: GitEnv::default(),
+        };
+        let branches = getter.branches(&mut vec![]).unwrap();
+
+        assert!(branches.is_empty());
+    }
+
+    #[test]
+    fn discover_repo_returns_git_dir_if_in_repo_root() {
+        let temp_dir = make_test_git_dir().unwrap();
+        let git_path = temp_dir.path().to_path_buf();
+
+        let repo = discover_repo(&git_path, &GitEnv::default()).unwrap();
+
+        assert!(is_same_file(&repo.git_dir, temp_dir.path().join(".git")).unwrap());
+        assert!(is_same_file(&repo.common_dir, temp_dir.path().join(".git")).unwrap());
+    }
+
+    #[test]
+    fn discover_repo_returns_git_dir_if_path_not_in_repo_root() {
+        let temp_dir = make_test_git_dir().unwrap();
+        let src_dir = tempfile::tempdir_in(temp_dir.path()).unwrap();
+
+        let repo = discover_repo(&PathBuf::from(src_dir.path()), &GitEnv::default()).unwrap();
+
+        assert!(is_same_file(repo.git_dir, temp_dir.path().join(".git")).unwrap());
+    }
+
+    #[test]
+    fn discover_repo_returns_err_given_not_in_git_dir() {
+        let not_git_dir = tempfile::tempdir().unwrap();
+
+        let git_dir = discover_repo(&PathBuf::from(not_git_dir.path()), &GitEnv::default());
+
+        assert!(git_dir.is_err());
+        assert!(git_dir
+            .unwrap_err()
+            .to_string()
+            .contains("not a git repository"));
+    }
+
+    #[test]
+    fn discover_repo_follows_git_file_of_linked_worktree() {
+        let temp_dir = make_test_git_dir().unwrap();
+        let worktree = make_linked_worktree(temp_dir.path(), "wt").unwrap();
+
+        let repo = discover_repo(&worktree, &GitEnv::default()).unwrap();
+
+        let wt_git_dir = temp_dir.path().join(GIT_DIR).join("worktrees").join("wt");
+        assert!(is_same_file(repo.git_dir, wt_git_dir).unwrap());
+        assert!(is_same_file(repo.common_dir, temp_dir.path().join(GIT_DIR)).unwrap());
+    }
+
+    #[test]
+    fn discover_repo_follows_relative_git_file_of_submodule() {
+        let temp_dir = make_test_git_dir().unwrap();
+        let module_dir = temp_dir.path().join(GIT_DIR).join("modules").join("sub");
+        std::fs::create_dir_all(&module_dir).unwrap();
+        let sub_dir = temp_dir.path().join("sub");
+        std::fs::create_dir_all(&sub_dir).unwrap();
+        std::fs::File::create(sub_dir.join(GIT_DIR))
+            .unwrap()
+            .write_all("gitdir: ../.git/modules/sub\n".as_bytes())
+            .unwrap();
+
+        let repo = discover_repo(&sub_dir, &GitEnv::default()).unwrap();
+
+        assert!(is_same_file(&repo.git_dir, &module_dir).unwrap());
+        assert!(is_same_file(&repo.common_dir, &module_dir).unwrap());
+    }
+
+    #[test]
+    fn discover_repo_returns_err_given_invalid_git_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::File::create(temp_dir.path().join(GIT_DIR))
+            .unwrap()
+            .write_all("not a git file".as_bytes())
+            .unwrap();
+
+        let repo = discover_repo(&temp_dir.path().to_path_buf(), &GitEnv::default());
+
+        assert!(repo.unwrap_err().to_string().contains("invalid gitfile"));
+    }
+
+    #[test]
+    fn fs_branch_getter_retrieves_shared_branches_from_linked_worktree() {
+        let temp_dir = make_test_git_dir().unwrap();
+        let worktree = make_linked_worktree(temp_dir.path(), "wt").unwrap();
+
+        let getter = FsBranchGetter {
+            repo_dir: worktree.join("some_dir"),
+            env: GitEnv::default(),
+        };
+        std::fs::create_dir_all(&getter.repo_dir).unwrap();
+        let mut branches = getter.branches(&mut vec![]).unwrap();
+
+        branches.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut expected = expected_branches();
+        // 'other_branch' is checked out in the worktree
+        for branch in expected.iter_mut().filter(|b| b.name == "other_branch") {
+            branch.is_head = true;
+        }
+        assert_eq!(branches, expected);
+    }
+
+    #[test]
+    fn discover_repo_returns_err_given_path_does_not_exist() {
+        let git_dir = discover_repo(&PathBuf::from("/not/a/dir"), &GitEnv::default());
+
+        assert!(git_dir.is_err());
+        assert!(git_dir.unwrap_err().to_string().contains("not a directory"));
+    }
+
+    #[test]
+    fn discover_repo_uses_git_dir_env_instead_of_searching() {
+        let temp_dir = make_test_git_dir().unwrap();
+        let other_dir = tempfile::tempdir().unwrap();
+        let env = GitEnv {
+            git_dir: Some(temp_dir.path().join(GIT_DIR)),
+            ..Default::default()
+        };
+
+        let repo = discover_repo(&other_dir.path().to_path_buf(), &env).unwrap();
+
+        assert!(is_same_file(&repo.git_dir, temp_dir.path().join(GIT_DIR)).unwrap());
+        assert!(is_same_file(&repo.common_dir, temp_dir.path().join(GIT_DIR)).unwrap());
+    }
+
+    #[test]
+    fn discover_repo_resolves_relative_git_dir_env_against_start_dir() {
+        let temp_dir = make_test_git_dir().unwrap();
+        std::fs::rename(
+            temp_dir.path().join(GIT_DIR),
+            temp_dir.path().join("mirror.git"),
+        )
+        .unwrap();
+        let env = GitEnv {
+            git_dir: Some(PathBuf::from("mirror.git")),
+            ..Default::default()
+        };
+
+        let getter = FsBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env,
+        };
+        let mut branches = getter.branches(&mut vec![]).unwrap();
+
+        branches.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(branches, expected_branches());
+    }
+
+    #[test]
+    fn discover_repo_returns_err_given_git_dir_env_is_not_a_repo() {
+        let temp_dir = make_test_git_dir().unwrap();
+        let env = GitEnv {
+            git_dir: Some(PathBuf::from("not_a_dir")),
+            ..Default::default()
+        };
+
+        let repo = discover_repo(&temp_dir.path().to_path_buf(), &env);
+
+        assert!(repo
+            .unwrap_err()
+            .to_string()
+            .contains("not a git repository"));
+    }
+
+    #[test]
+    fn discover_repo_follows_git_file_given_as_git_dir_env() {
+        let temp_dir = make_test_git_dir().unwrap();
+        let worktree = make_linked_worktree(temp_dir.path(), "wt").unwrap();
+        let other_dir = tempfile::tempdir().unwrap();
+        let env = GitEnv {
+            git_dir: Some(worktree.join(GIT_DIR)),
+            ..Default::default()
+        };
+
+        let repo = discover_repo(&other_dir.path().to_path_buf(), &env).unwrap();
+
+        assert!(is_same_file(&repo.common_dir, temp_dir.path().join(GIT_DIR)).unwrap());
+    }
+
+    #[test]
+    fn discover_repo_common_dir_env_overrides_commondir_file() {
+        let temp_dir = make_test_git_dir().unwrap();
+        let worktree = make_linked_worktree(temp_dir.path(), "wt").unwrap();
+        let other_common = tempfile::tempdir().unwrap();
+        let env = GitEnv {
+            common_dir: Some(other_common.path().to_path_buf()),
+            ..Default::default()
+        };
+
+        let repo = discover_repo(&worktree, &env).unwrap();
+
+        let wt_git_dir = temp_dir.path().join(GIT_DIR).join("worktrees").join("wt");
+        assert!(is_same_file(&repo.git_dir, wt_git_dir).unwrap());
+        assert!(is_same_file(&repo.common_dir, other_common.path()).unwrap());
+    }
+
+    #[test]
+    fn discover_repo_returns_err_given_common_dir_env_does_not_exist() {
+        let temp_dir = make_test_git_dir().unwrap();
+        let env = GitEnv {
+            common_dir: Some(PathBuf::from("/not/a/dir")),
+            ..Default::default()
+        };
+
+        let repo = discover_repo(&temp_dir.path().to_path_buf(), &env);
+
+        assert!(repo.unwrap_err().to_string().contains("invalid common dir"));
+    }
+
+    #[test]
+    fn discover_repo_does_not_search_ceiling_dir_or_above() {
+        let temp_dir = make_test_git_dir().unwrap();
+        let sub_dir = temp_dir.path().join("a").join("b");
+        std::fs::create_dir_all(&sub_dir).unwrap();
+        let env = GitEnv {
+            ceiling_dirs: vec![temp_dir.path().to_path_buf()],
+            ..Default::default()
+        };
+
+        let repo = discover_repo(&sub_dir, &env);
+
+        assert!(repo
+            .unwrap_err()
+            .to_string()
+            .contains("not a git repository"));
+    }
+
+    #[test]
+    fn discover_repo_searches_start_dir_given_it_is_a_ceiling_dir() {
+        let temp_dir = make_test_git_dir().unwrap();
+        let env = GitEnv {
+            ceiling_dirs: vec![temp_dir.path().to_path_buf()],
+            ..Default::default()
+        };
+
+        let repo = discover_repo(&temp_dir.path().to_path_buf(), &env).unwrap();
+
+        assert!(is_same_file(&repo.git_dir, temp_dir.path().join(GIT_DIR)).unwrap());
+    }
+
+    #[test]
+    fn discover_repo_ignores_relative_ceiling_dirs() {
+        let temp_dir = make_test_git_dir().unwrap();
+        let sub_dir = temp_dir.path().join("a");
+        std::fs::create_dir_all(&sub_dir).unwrap();
+        let env = GitEnv {
+            ceiling_dirs: vec![PathBuf::from("..")],
+            ..Default::default()
+        };
+
+        let repo = discover_repo(&sub_dir, &env).unwrap();
+
+        assert!(is_same_file(&repo.git_dir, temp_dir.path().join(GIT_DIR)).unwrap());
+    }
+
+    #[test]
+    fn discover_repo_returns_err_given_work_tree_env_does_not_exist() {
+        let temp_dir = make_test_git_dir().unwrap();
+        let env = GitEnv {
+            work_tree: Some(PathBuf::from("not_a_dir")),
+            ..Default::default()
+        };
+
+        let repo = discover_repo(&temp_dir.path().to_path_buf(), &env);
+
+        assert!(repo.unwrap_err().to_string().contains("invalid work tree"));
+    }
+
+    #[test]
+    fn discover_repo_returns_bare_repo_given_path_to_bare_repo() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let bare_dir = make_bare_test_repo(temp_dir.path(), "mirror.git").unwrap();
+
+        let repo = discover_repo(&bare_dir, &GitEnv::default()).unwrap();
+
+        assert!(is_same_file(&repo.git_dir, &bare_dir).unwrap());
+        assert!(is_same_file(&repo.common_dir, &bare_dir).unwrap());
+    }
+
+    #[test]
+    fn discover_repo_returns_bare_repo_given_path_inside_bare_repo() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let bare_dir = make_bare_test_repo(temp_dir.path(), "mirror.git").unwrap();
+
+        let repo = discover_repo(&bare_dir.join("refs").join("heads"), &GitEnv::default());
+
+        assert!(is_same_file(repo.unwrap().git_dir, &bare_dir).unwrap());
+    }
+
+    #[test]
+    fn discover_repo_returns_err_given_dir_with_refs_but_no_objects() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let bare_dir = make_bare_test_repo(temp_dir.path(), "mirror.git").unwrap();
+        std::fs::remove_dir(bare_dir.join("objects")).unwrap();
+
+        let repo = discover_repo(&bare_dir, &GitEnv::default());
+
+        assert!(repo
+            .unwrap_err()
+            .to_string()
+            .contains("not a git repository"));
+    }
+
+    #[test]
+    fn fs_branch_getter_retrieves_all_branches_from_bare_repo() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let bare_dir = make_bare_test_repo(temp_dir.path(), "mirror.git").unwrap();
+
+        let getter = FsBranchGetter {
+            repo_dir: bare_dir,
+            env: GitEnv::default(),
+        };
+        let mut branches = getter.branches(&mut vec![]).unwrap();
+
+        branches.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut expected = expected_branches();
+        expected[0].is_head = true; // the bare repo's HEAD points to 'main'
+        assert_eq!(branches, expected);
+    }
+
+    #[test]
+    fn fs_branch_getter_marks_branch_pointed_to_by_head() {
+        let temp_dir = make_test_git_dir().unwrap();
+        std::fs::File::create(temp_dir.path().join(GIT_DIR).join(HEAD_FILE))
+            .unwrap()
+            .write_all("ref: refs/heads/user/some_dev_branch\n".as_bytes())
+            .unwrap();
+
+        let getter = FsBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: GitEnv::default(),
+        };
+        let branches = getter.branches(&mut vec![]).unwrap();
+
+        let head_branches: Vec<_> = branches.iter().filter(|b| b.is_head).collect();
+        assert_eq!(head_branches.len(), 1);
+        assert_eq!(head_branches[0].name, "user/some_dev_branch");
+        assert_eq!(head_branches[0].branch_type, BranchType::Local);
+    }
+
+    #[test]
+    fn fs_branch_getter_marks_packed_branch_pointed_to_by_head() {
+        let temp_dir = make_test_git_dir().unwrap();
+        std::fs::File::create(temp_dir.path().join(GIT_DIR).join(HEAD_FILE))
+            .unwrap()
+            .write_all("ref: refs/heads/upstream/packed_2\n".as_bytes())
+            .unwrap();
+
+        let getter = FsBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: GitEnv::default(),
+        };
+        let branches = getter.branches(&mut vec![]).unwrap();
+
+        let head_branches: Vec<_> = branches.iter().filter(|b| b.is_head).collect();
+        assert_eq!(head_branches.len(), 1);
+        assert_eq!(head_branches[0].name, "upstream/packed_2");
+    }
+
+    #[test]
+    fn fs_branch_getter_marks_no_branch_given_detached_head() {
+        let temp_dir = make_test_git_dir().unwrap();
+        std::fs::File::create(temp_dir.path().join(GIT_DIR).join(HEAD_FILE))
+            .unwrap()
+            .write_all("e2bf29060f42743538be07c164820cdeca0d9d2b\n".as_bytes())
+            .unwrap();
+
+        let getter = FsBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: GitEnv::default(),
+        };
+        let branches = getter.branches(&mut vec![]).unwrap();
+
+        assert!(!branches.iter().any(|b| b.is_head));
+    }
+
+    #[test]
+    fn fs_branch_getter_uses_head_of_linked_worktree() {
+        let temp_dir = make_test_git_dir().unwrap();
+        std::fs::File::create(temp_dir.path().join(GIT_DIR).join(HEAD_FILE))
+            .unwrap()
+            .write_all("ref: refs/heads/main\n".as_bytes())
+            .unwrap();
+        let worktree = make_linked_worktree(temp_dir.path(), "wt").unwrap();
+
+        let getter = FsBranchGetter {
+            repo_dir: worktree,
+            env: GitEnv::default(),
+        };
+        let branches = getter.branches(&mut vec![]).unwrap();
+
+        let head_branches: Vec<_> = branches.iter().filter(|b| b.is_head).collect();
+        assert_eq!(head_branches.len(), 1);
+        assert_eq!(head_branches[0].name, "other_branch");
+    }
+
+    #[test]
+    fn fs_branch_getter_sets_worktree_of_branch_checked_out_in_linked_worktree() {
+        let temp_dir = make_test_git_dir().unwrap();
+        std::fs::File::create(temp_dir.path().join(GIT_DIR).join(HEAD_FILE))
+            .unwrap()
+            .write_all("ref: refs/heads/main\n".as_bytes())
+            .unwrap();
+        let worktree = make_linked_worktree(temp_dir.path(), "wt").unwrap();
+
+        let getter = FsBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: GitEnv::default(),
+        };
+        let branches = getter.branches(&mut vec![]).unwrap();
+
+        let other = branches.iter().find(|b| b.name == "other_branch").unwrap();
+        assert!(!other.is_head);
+        assert!(is_same_file(other.worktree.as_ref().unwrap(), &worktree).unwrap());
+        let main = branches.iter().find(|b| b.name == "main").unwrap();
+        assert!(main.is_head);
+        assert_eq!(main.worktree, None);
+        assert_eq!(branches.iter().filter(|b| b.worktree.is_some()).count(), 1);
+    }
+
+    #[test]
+    fn fs_branch_getter_sets_worktree_of_branch_checked_out_in_main_worktree() {
+        let temp_dir = make_test_git_dir().unwrap();
+        std::fs::File::create(temp_dir.path().join(GIT_DIR).join(HEAD_FILE))
+            .unwrap()
+            .write_all("ref: refs/heads/main\n".as_bytes())
+            .unwrap();
+        let worktree = make_linked_worktree(temp_dir.path(), "wt").unwrap();
+
+        let getter = FsBranchGetter {
+            repo_dir: worktree,
+            env: GitEnv::default(),
+        };
+        let branches = getter.branches(&mut vec![]).unwrap();
+
+        let main = branches.iter().find(|b| b.name == "main").unwrap();
+        assert!(is_same_file(main.worktree.as_ref().unwrap(), temp_dir.path()).unwrap());
+        let other = branches.iter().find(|b| b.name == "other_branch").unwrap();
+        assert!(other.is_head);
+        assert_eq!(other.worktree, None);
+    }
+
+    #[test]
+    fn fs_branch_getter_ignores_detached_linked_worktree() {
+        let temp_dir = make_test_git_dir().unwrap();
+        make_linked_worktree(temp_dir.path(), "wt").unwrap();
+        std::fs::File::create(
+            temp_dir
+                .path()
+                .join(GIT_DIR)
+                .join(WORKTREES_DIR)
+                .join("wt")
+                .join(HEAD_FILE),
+        )
+        .unwrap()
+        .write_all("e2bf29060f42743538be07c164820cdeca0d9d2b\n".as_bytes())
+        .unwrap();
+
+        let getter = FsBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: GitEnv::default(),
+        };
+        let branches = getter.branches(&mut vec![]).unwrap();
+
+        assert!(branches.iter().all(|b| b.worktree.is_none()));
+    }
+
+    #[test]
+    fn fs_branch_getter_does_not_set_worktree_of_bare_repo_head_branch() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let bare_dir = make_bare_test_repo(temp_dir.path(), "mirror.git").unwrap();
+        let wt_git_dir = bare_dir.join(WORKTREES_DIR).join("wt");
+        std::fs::create_dir_all(&wt_git_dir).unwrap();
+        std::fs::File::create(wt_git_dir.join(HEAD_FILE))
+            .unwrap()
+            .write_all("ref: refs/heads/other_branch\n".as_bytes())
+            .unwrap();
+        std::fs::File::create(wt_git_dir.join(COMMON_DIR_FILE))
+            .unwrap()
+            .write_all("../..\n".as_bytes())
+            .unwrap();
+
+        let getter = FsBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: GitEnv {
+                git_dir: Some(wt_git_dir),
+                ..Default::default()
+            },
+        };
+        let branches = getter.branches(&mut vec![]).unwrap();
+
+        let main = branches.iter().find(|b| b.name == "main").unwrap();
+        assert!(!main.is_head);
+        assert_eq!(main.worktree, None);
+        let other = branches.iter().find(|b| b.name == "other_branch").unwrap();
+        assert!(other.is_head);
+    }
+
+    #[test]
+    fn fs_branch_getter_sets_upstream_of_tracking_branches() {
+        let temp_dir = make_test_git_dir().unwrap();
+        std::fs::File::create(temp_dir.path().join(GIT_DIR).join(CONFIG_FILE))
+            .unwrap()
+            .write_all(
+                [
+                    "[remote \"origin\"]",
+                    "\tfetch = +refs/heads/*:refs/remotes/origin/*",
+                    "[branch \"main\"]",
+                    "\tremote = origin",
+                    "\tmerge = refs/heads/main",
+                    "[branch \"upstream/packed_2\"]",
+                    "\tremote = .",
+                    "\tmerge = refs/heads/main",
+                    "",
+                ]
+                .join("\n")
+                .as_bytes(),
+            )
+            .unwrap();
+
+        let getter = FsBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: GitEnv::default(),
+        };
+        let branches = getter.branches(&mut vec![]).unwrap();
+
+        let upstream_of = |name: &str, branch_type: BranchType| {
+            branches
+                .iter()
+                .find(|b| b.name == name && b.branch_type == branch_type)
+                .unwrap()
+                .upstream
+                .clone()
+        };
+        assert_eq!(
+            upstream_of("main", BranchType::Local).as_deref(),
+            Some("refs/remotes/origin/main")
+        );
+        assert_eq!(
+            upstream_of("upstream/packed_2", BranchType::Local).as_deref(),
+            Some("refs/heads/main")
+        );
+        assert_eq!(upstream_of("other_branch", BranchType::Local), None);
+        assert_eq!(upstream_of("origin/main", BranchType::Remote), None);
+    }
+
+    #[test]
+    fn fs_branch_getter_uses_core_bare_to_detect_bare_repo() {
+        let temp_dir = make_test_git_dir().unwrap();
+        let git_dir = temp_dir.path().join(GIT_DIR);
+        std::fs::File::create(git_dir.join(CONFIG_FILE))
+            .unwrap()
+            .write_all("[core]\n\tbare = true\n".as_bytes())
+            .unwrap();
+        std::fs::File::create(git_dir.join(HEAD_FILE))
+            .unwrap()
+            .write_all("ref: refs/heads/main\n".as_bytes())
+            .unwrap();
+        let worktree = make_linked_worktree(temp_dir.path(), "wt").unwrap();
+
+        let getter = FsBranchGetter {
+            repo_dir: worktree,
+            env: GitEnv::default(),
+        };
+        let branches = getter.branches(&mut vec![]).unwrap();
+
+        assert!(branches.iter().all(|b| b.worktree.is_none()));
+    }
+
+    #[test]
+    fn fs_branch_getter_marks_branches_whose_upstream_is_gone() {
+        let temp_dir = make_test_git_dir().unwrap();
+        std::fs::File::create(temp_dir.path().join(GIT_DIR).join(CONFIG_FILE))
+            .unwrap()
+            .write_all(
+                [
+                    "[branch \"main\"]",
+                    "\tremote = origin",
+                    "\tmerge = refs/heads/main",
+                    "[branch \"other_branch\"]",
+                    "\tremote = origin",
+                    "\tmerge = refs/heads/deleted_branch",
+                    "[branch \"user/some_dev_branch\"]",
+                    "\tremote = origin",
+                    "\tmerge = refs/heads/packed",
+                    "",
+                ]
+                .join("\n")
+                .as_bytes(),
+            )
+            .unwrap();
+
+        let getter = FsBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: GitEnv::default(),
+        };
+        let branches = getter.branches(&mut vec![]).unwrap();
+
+        let gone: Vec<_> = branches
+            .iter()
+            .filter(|b| b.upstream_gone)
+            .map(|b| b.name.as_str())
+            .collect();
+        assert_eq!(gone, vec!["other_branch"]);
+    }
+
+    #[test]
+    fn read_loose_refs_parses_object_ids_and_symbolic_refs() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let heads_dir = temp_dir.path().join("refs").join("heads");
+        std::fs::create_dir_all(heads_dir.join("user")).unwrap();
+        let sha1 = "e2bf29060f42743538be07c164820cdeca0d9d2b";
+        let sha256 = "6f4e2a1c9d0b8e7f3a5c2d1e0f9b8a7c6d5e4f3a2b1c0d9e8f7a6b5c4d3e2f1a";
+        std::fs::write(heads_dir.join("sha1"), format!("{sha1}\n")).unwrap();
+        std::fs::write(heads_dir.join("user").join("sha256"), sha256).unwrap();
+        std::fs::write(heads_dir.join("alias"), "ref: refs/heads/sha1\n").unwrap();
+        std::fs::write(heads_dir.join("short"), "e2bf2906\n").unwrap();
+        std::fs::write(heads_dir.join("not_hex"), format!("{}zz\n", &sha1[2..])).unwrap();
+        std::fs::write(heads_dir.join("empty_symref"), "ref:\n").unwrap();
+        std::fs::write(heads_dir.join("signed"), "+f".repeat(20)).unwrap();
+        std::fs::write(heads_dir.join("binary"), [0xff, 0xfe, 0x00]).unwrap();
+        let mut warnings = vec![];
+
+        let refs = read_loose_refs(temp_dir.path(), LOCAL_REF_PREFIX, &mut warnings).unwrap();
+
+        let object = |hex| RefValue::Object {
+            target: ObjectId::from_hex(hex).unwrap(),
+            peeled: None,
+        };
+        assert_eq!(
+            refs,
+            Refs::from([
+                (
+                    b"refs/heads/alias".to_vec(),
+                    RefValue::Symref("refs/heads/sha1".to_string())
+                ),
+                (b"refs/heads/sha1".to_vec(), object(sha1)),
+                (b"refs/heads/user/sha256".to_vec(), object(sha256)),
+            ])
+        );
+        warnings.sort();
+        assert_eq!(
+            warnings,
+            vec![
+                "ignoring malformed ref 'refs/heads/binary'",
+                "ignoring malformed ref 'refs/heads/empty_symref'",
+                "ignoring malformed ref 'refs/heads/not_hex'",
+                "ignoring malformed ref 'refs/heads/short'",
+                "ignoring malformed ref 'refs/heads/signed'",
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_packed_refs_warns_about_malformed_lines() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let packed_file = temp_dir.path().join(PACKED_REFS_FILE);
+        std::fs::write(
+            &packed_file,
+            [
+                "# pack-refs with: peeled fully-peeled sorted",
+                "e2bf29060f42743538be07c164820cdeca0d9d2b refs/heads/main",
+                "e2bf2906 refs/heads/short",
+                "^a513aa4efda3f06f316feb6b5df23d32480c435e",
+                "not_a_ref_line",
+                "",
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+        let mut warnings = vec![];
+
+        let refs = parse_packed_refs(&packed_file, &mut warnings).unwrap();
+
+        // the peeled line follows a skipped ref, so must not be given to 'main'
+        assert_eq!(
+            refs,
+            Refs::from([(
+                b"refs/heads/main".to_vec(),
+                RefValue::Object {
+                    target: ObjectId::from_full_hex("e2bf29060f42743538be07c164820cdeca0d9d2b")
+                        .unwrap(),
+                    peeled: None,
+                }
+            )])
+        );
+        assert_eq!(
+            warnings,
+            vec![
+                "ignoring malformed ref 'refs/heads/short'",
+                "ignoring malformed packed ref line 'not_a_ref_line'",
+            ]
+        );
+    }
+
+    #[test]
+    fn fs_branch_getter_resolves_symbolic_refs_and_skips_broken_ones() {
+        let temp_dir = make_test_git_dir().unwrap();
+        let heads_dir = temp_dir.path().join(GIT_DIR).join("refs").join("heads");
+        std::fs::write(
+            heads_dir.join("alias"),
+            "ref: refs/heads/upstream/packed_2\n",
+        )
+        .unwrap();
+        std::fs::write(heads_dir.join("dangling"), "ref: refs/heads/missing\n").unwrap();
+        std::fs::write(heads_dir.join("malformed"), "not an object id\n").unwrap();
+
+        let getter = FsBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: GitEnv::default(),
+        };
+        let mut warnings = vec![];
+        let branches = getter.branches(&mut warnings).unwrap();
+
+        let alias = branches.iter().find(|b| b.name == "alias").unwrap();
+        assert_eq!(alias.symref_name(), Some("upstream/packed_2"));
+        assert_eq!(
+            alias.target,
+            ObjectId::from_hex("12344f25fe1a269a068c369e58ce11295dcdeb0c")
+        );
+        assert!(!branches
+            .iter()
+            .any(|b| b.name == "dangling" || b.name == "malformed"));
+        assert_eq!(
+            warnings,
+            vec![
+                "ignoring malformed ref 'refs/heads/malformed'",
+                "ignoring broken symbolic ref 'refs/heads/dangling'",
+            ]
+        );
+    }
+
+    #[test]
+    fn fs_branch_getter_lists_stash_entries_newest_first() {
+        let temp_dir = make_test_git_dir().unwrap();
+        let git_dir = temp_dir.path().join(GIT_DIR);
+        let (older, newer) = (
+            "a9c68440003151dd3cf7ffa4eaedd425d221d268",
+            "707a178071655bed661318a5344557fe3e9a6ce1",
+        );
+        let null = "0000000000000000000000000000000000000000";
+        std::fs::write(git_dir.join(STASH_REF), format!("{newer}\n")).unwrap();
+        std::fs::create_dir_all(git_dir.join(LOGS_DIR).join("refs")).unwrap();
+        std::fs::write(
+            git_dir.join(LOGS_DIR).join(STASH_REF),
+            [
+                format!("{null} {older} A U Thor <author@example.com> 1700000000 +0100\tOn main: try something"),
+                "not a reflog line".to_string(),
+                format!("{older} {newer} A U Thor <author@example.com> 1700000100 +0100\tWIP on main: e2bf290 Add a feature"),
+                String::new(),
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+        odb::tests::write_loose_object(
+            &git_dir.join("objects"),
+            newer,
+            "commit",
+            odb::tests::COMMIT_DATA.as_bytes(),
+        );
+        let getter = FsBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: GitEnv::default(),
+        };
+
+        let stashes = getter.stashes().unwrap();
+
+        let entries: Vec<_> = stashes
+            .iter()
+            .map(|s| (s.name.as_str(), s.message.as_deref(), s.target.clone()))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                (
+                    "stash@{0}",
+                    Some("WIP on main: e2bf290 Add a feature"),
+                    ObjectId::from_hex(newer)
+                ),
+                (
+                    "stash@{1}",
+                    Some("On main: try something"),
+                    ObjectId::from_hex(older)
+                ),
+            ]
+        );
+        assert!(stashes.iter().all(|s| s.branch_type == BranchType::Stash));
+        assert!(stashes[0].commit.is_some());
+        assert!(stashes[1].commit.is_none());
+    }
+
+    #[test]
+    fn fs_branch_getter_lists_no_stash_entries_given_no_stash_ref() {
+        let temp_dir = make_test_git_dir().unwrap();
+        let getter = FsBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: GitEnv::default(),
+        };
+
+        let stashes = getter.stashes().unwrap();
+
+        assert!(stashes.is_empty());
+    }
+
+    #[test]
+    fn fs_branch_getter_lists_recent_branches_from_head_reflog() {
+        let temp_dir = make_test_git_dir().unwrap();
+        let git_dir = temp_dir.path().join(GIT_DIR);
+        let (first, second) = (
+            "a9c68440003151dd3cf7ffa4eaedd425d221d268",
+            "707a178071655bed661318a5344557fe3e9a6ce1",
+        );
+        std::fs::create_dir_all(git_dir.join(LOGS_DIR)).unwrap();
+        std::fs::write(
+            git_dir.join(LOGS_DIR).join(HEAD_FILE),
+            [
+                format!("{first} {second} A U Thor <author@example.com> 1700000000 +0100\tcheckout: moving from main to feature"),
+                format!("{second} {second} A U Thor <author@example.com> 1700000100 +0100\tcommit: add a feature"),
+                format!("{second} {first} A U Thor <author@example.com> 1700000200 +0100\tcheckout: moving from feature to {first}"),
+                format!("{first} {first} A U Thor <author@example.com> 1700000300 +0100\tcheckout: moving from {first} to main"),
+                String::new(),
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+        let getter = FsBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: GitEnv::default(),
+        };
+
+        let recent = getter.recent_branches().unwrap();
+
+        assert_eq!(recent, vec!["main", first, "feature"]);
+    }
+
+    #[test]
+    fn fs_branch_getter_lists_no_recent_branches_given_no_head_reflog() {
+        let temp_dir = make_test_git_dir().unwrap();
+        let getter = FsBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: GitEnv::default(),
+        };
+
+        let recent = getter.recent_branches().unwrap();
+
+        assert!(recent.is_empty());
+    }
+
+    #[test]
+    fn fs_branch_getter_reads_sha256_repo() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let git_dir = temp_dir.path().join(GIT_DIR);
+        let heads_dir = git_dir.join("refs").join("heads");
+        std::fs::create_dir_all(&heads_dir).unwrap();
+        let loose = "6f4e2a1c9d0b8e7f3a5c2d1e0f9b8a7c6d5e4f3a2b1c0d9e8f7a6b5c4d3e2f1a";
+        let tag = "0c1d2e3f4a5b6c7d8e9f0a1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d6e7f8a9b0c1d";
+        std::fs::write(git_dir.join(HEAD_FILE), "ref: refs/heads/main\n").unwrap();
+        std::fs::write(
+            git_dir.join(CONFIG_FILE),
+            "[core]\n\trepositoryformatversion = 1\n[extensions]\n\tobjectFormat = sha256\n",
+        )
+        .unwrap();
+        std::fs::write(heads_dir.join("main"), format!("{loose}\n")).unwrap();
+        // a SHA-1 length ID cannot be valid in a SHA-256 repository
+        std::fs::write(
+            heads_dir.join("sha1"),
+            "e2bf29060f42743538be07c164820cdeca0d9d2b\n",
+        )
+        .unwrap();
+        std::fs::write(
+            git_dir.join(PACKED_REFS_FILE),
+            format!(
+                "# pack-refs with: peeled fully-peeled sorted\n{tag} refs/tags/v1.0\n^{loose}\n"
+            ),
+        )
+        .unwrap();
+        let objects_dir = git_dir.join("objects");
+        odb::tests::write_loose_object(
+            &objects_dir,
+            loose,
+            "commit",
+            odb::tests::COMMIT_DATA.as_bytes(),
+        );
+
+        let getter = FsBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: GitEnv::default(),
+        };
+        let branches = getter.branches(&mut vec![]).unwrap();
+
+        let names: Vec<_> = branches.iter().map(|b| b.to_string()).collect();
+        assert_eq!(names, vec!["main", "tags/v1.0"]);
+        assert_eq!(branches[0].target, ObjectId::from_hex(loose));
+        assert!(branches[0].is_head);
+        assert_eq!(
+            branches[0].commit.as_ref().map(|c| c.subject.as_str()),
+            Some("Add a feature")
+        );
+        assert_eq!(branches[1].target, ObjectId::from_hex(tag));
+        assert_eq!(branches[1].peeled, ObjectId::from_hex(loose));
+        assert_eq!(
+            getter.resolve_rev("main").unwrap(),
+            ObjectId::from_hex(loose).unwrap()
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn fs_branch_getter_keeps_raw_bytes_of_non_utf8_names() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let temp_dir = make_test_git_dir().unwrap();
+        let git_dir = temp_dir.path().join(GIT_DIR);
+        std::fs::write(
+            git_dir
+                .join("refs")
+                .join("heads")
+                .join(std::ffi::OsStr::from_bytes(b"caf\xe9")),
+            "e2bf29060f42743538be07c164820cdeca0d9d2b\n",
+        )
+        .unwrap();
+        let mut packed_refs = std::fs::read(git_dir.join("packed-refs")).unwrap();
+        packed_refs.extend(b"a9c68440003151dd3cf7ffa4eaedd425d221d268 refs/tags/v\xff\n");
+        std::fs::write(git_dir.join("packed-refs"), packed_refs).unwrap();
+
+        let getter = FsBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: GitEnv::default(),
+        };
+        let branches = getter.branches(&mut vec![]).unwrap();
+
+        let raw_names: Vec<_> = branches
+            .iter()
+            .filter_map(|b| Some((b.name.as_str(), b.raw_name.as_deref()?)))
+            .collect();
+        assert_eq!(
+            raw_names,
+            vec![
+                ("caf\u{fffd}", b"caf\xe9".as_slice()),
+                ("v\u{fffd}", b"v\xff".as_slice())
+            ]
+        );
+    }
+
+    #[test]
+    fn fs_branch_getter_skips_refs_with_broken_names() {
+        let temp_dir = make_test_git_dir().unwrap();
+        let heads_dir = temp_dir.path().join(GIT_DIR).join("refs").join("heads");
+        for name in ["bad..name", "bad.lock", "bad name", ".bad"] {
+            std::fs::write(
+                heads_dir.join(name),
+                "e2bf29060f42743538be07c164820cdeca0d9d2b\n",
+            )
+            .unwrap();
+        }
+
+        let getter = FsBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: GitEnv::default(),
+        };
+        let mut warnings = vec![];
+        let branches = getter.branches(&mut warnings).unwrap();
+
+        assert!(!branches.iter().any(|b| b.name.contains("bad")));
+        assert_eq!(warnings.len(), 4);
+        assert!(warnings
+            .iter()
+            .all(|w| w.starts_with("ignoring ref with broken name")));
+    }
+
+    #[test]
+    fn is_valid_ref_name_follows_git_ref_name_rules() {
+        for name in [
+            b"refs/heads/main".as_slice(),
+            b"refs/heads/feature/x-1",
+            b"refs/heads/caf\xe9",
+            b"refs/tags/v1.0",
+            b"refs/heads/a@b",
+        ] {
+            assert!(is_valid_ref_name(name), "{}", String::from_utf8_lossy(name));
+        }
+        for name in [
+            b"".as_slice(),
+            b"@",
+            b"refs/heads/a..b",
+            b"refs/heads/.hidden",
+            b"refs/heads/main.lock",
+            b"refs/heads/main.",
+            b"refs/heads/a@{b",
+            b"refs/heads//main",
+            b"refs/heads/main/",
+            b"/refs/heads/main",
+            b"refs/heads/a b",
+            b"refs/heads/a~b",
+            b"refs/heads/a^b",
+            b"refs/heads/a:b",
+            b"refs/heads/a?b",
+            b"refs/heads/a*b",
+            b"refs/heads/a[b",
+            b"refs/heads/a\\b",
+            b"refs/heads/a\x7fb",
+            b"refs/heads/a\tb",
+        ] {
+            assert!(
+                !is_valid_ref_name(name),
+                "{}",
+                String::from_utf8_lossy(name)
+            );
+        }
+    }
+
+    #[test]
+    fn fs_branch_getter_prefers_loose_refs_over_packed_refs() {
+        let temp_dir = make_test_git_dir().unwrap();
+        let refs_dir = temp_dir.path().join(GIT_DIR).join("refs");
+        std::fs::write(
+            refs_dir.join("remotes").join("origin").join("packed"),
+            "a9c68440003151dd3cf7ffa4eaedd425d221d268\n",
+        )
+        .unwrap();
+        std::fs::create_dir_all(refs_dir.join("tags")).unwrap();
+        std::fs::write(
+            refs_dir.join("tags").join("v0.1.0"),
+            "da7d6bf0955fa4d511067c00551fee04c613079d\n",
+        )
+        .unwrap();
+
+        let getter = FsBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: GitEnv::default(),
+        };
+        let branches = getter.branches(&mut vec![]).unwrap();
+
+        let packed: Vec<_> = branches
+            .iter()
+            .filter(|b| b.ref_name() == "refs/remotes/origin/packed")
+            .collect();
+        assert_eq!(packed.len(), 1);
+        assert_eq!(
+            packed[0].target,
+            ObjectId::from_hex("a9c68440003151dd3cf7ffa4eaedd425d221d268")
+        );
+        let tags: Vec<_> = branches
+            .iter()
+            .filter(|b| b.branch_type == BranchType::Tag)
+            .collect();
+        assert_eq!(tags.len(), 1);
+        assert_eq!(
+            tags[0].target,
+            ObjectId::from_hex("da7d6bf0955fa4d511067c00551fee04c613079d")
+        );
+        // the peeled object belongs to the stale packed tag
+        assert_eq!(tags[0].peeled, None);
+    }
+
+    #[test]
+    fn fs_branch_getter_lists_refs_in_both_stores_once_in_ref_name_order() {
+        let temp_dir = make_test_git_dir().unwrap();
+        let git_dir = temp_dir.path().join(GIT_DIR);
+        std::fs::write(
+            git_dir.join("packed-refs"),
+            [
+                "# pack-refs with: peeled fully-peeled sorted",
+                "e2bf29060f42743538be07c164820cdeca0d9d2b refs/heads/main",
+                "a9c68440003151dd3cf7ffa4eaedd425d221d268 refs/heads/other_branch",
+                "707a178071655bed661318a5344557fe3e9a6ce1 refs/heads/packed_only",
+                "e2bf29060f42743538be07c164820cdeca0d9d2b refs/remotes/origin/main",
+                "",
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+
+        let getter = FsBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: GitEnv::default(),
+        };
+        let branches = getter.branches(&mut vec![]).unwrap();
+
+        let ref_names: Vec<_> = branches.iter().map(|b| b.ref_name()).collect();
+        assert_eq!(
+            ref_names,
+            vec![
+                "refs/heads/main",
+                "refs/heads/other_branch",
+                "refs/heads/packed_only",
+                "refs/heads/user/some_dev_branch",
+                "refs/remotes/origin/HEAD",
+                "refs/remotes/origin/main",
+                "refs/remotes/origin/remote_branch",
+                "refs/remotes/upstream/main",
+            ]
+        );
+    }
+
+    #[test]
+    fn fs_branch_getter_reads_tip_commit_of_branches() {
+        let temp_dir = make_test_git_dir().unwrap();
+        let objects_dir = temp_dir.path().join(GIT_DIR).join("objects");
+        odb::tests::write_loose_object(
+            &objects_dir,
+            "e2bf29060f42743538be07c164820cdeca0d9d2b",
+            "commit",
+            odb::tests::COMMIT_DATA.as_bytes(),
+        );
+
+        let getter = FsBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: GitEnv::default(),
+        };
+        let branches = getter.branches(&mut vec![]).unwrap();
+
+        let subject_of = |name: &str| {
+            branches
+                .iter()
+                .find(|b| b.name == name)
+                .and_then(|b| b.commit.as_ref())
+                .map(|c| c.subject.clone())
+        };
+        assert_eq!(subject_of("main").as_deref(), Some("Add a feature"));
+        assert_eq!(subject_of("origin/main").as_deref(), Some("Add a feature"));
+        assert_eq!(subject_of("other_branch"), None);
+    }
+
+    #[test]
+    fn fs_branch_getter_peels_loose_annotated_tags() {
+        let temp_dir = make_test_git_dir().unwrap();
+        let git_dir = temp_dir.path().join(GIT_DIR);
+        let objects_dir = git_dir.join("objects");
+        std::fs::create_dir_all(git_dir.join("refs").join("tags")).unwrap();
+        for (tag, target) in [
+            ("annotated", "6e442625fe1a269a068c369e58ce11295dcdeb0c"),
+            ("lightweight", "707a178071655bed661318a5344557fe3e9a6ce1"),
+        ] {
+            std::fs::write(git_dir.join("refs").join("tags").join(tag), target).unwrap();
+        }
+        odb::tests::write_commit(
+            &objects_dir,
+            "707a178071655bed661318a5344557fe3e9a6ce1",
+            &[],
+        );
+        odb::tests::write_loose_object(
+            &objects_dir,
+            "6e442625fe1a269a068c369e58ce11295dcdeb0c",
+            "tag",
+            b"object 707a178071655bed661318a5344557fe3e9a6ce1\ntype commit\n\nmsg\n",
+        );
+
+        let getter = FsBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: GitEnv::default(),
+        };
+        let branches = getter.branches(&mut vec![]).unwrap();
+
+        let tag = |name: &str| {
+            branches
+                .iter()
+                .find(|b| b.branch_type == BranchType::Tag && b.name == name)
+                .unwrap()
+        };
+        let commit = ObjectId::from_hex("707a178071655bed661318a5344557fe3e9a6ce1");
+        assert_eq!(tag("annotated").peeled, commit);
+        assert_eq!(
+            tag("annotated").commit.as_ref().map(|c| &c.id),
+            commit.as_ref()
+        );
+        assert_eq!(tag("lightweight").peeled, None);
+        assert_eq!(
+            tag("lightweight").commit.as_ref().map(|c| &c.id),
+            commit.as_ref()
+        );
+    }
+
+    #[test]
+    fn fs_branch_getter_resolves_revs_to_commits() {
+        let temp_dir = make_test_git_dir().unwrap();
+        let git_dir = temp_dir.path().join(GIT_DIR);
+        let objects_dir = git_dir.join("objects");
+        std::fs::File::create(git_dir.join(HEAD_FILE))
+            .unwrap()
+            .write_all("ref: refs/heads/other_branch\n".as_bytes())
+            .unwrap();
+        for id in [
+            "e2bf29060f42743538be07c164820cdeca0d9d2b",
+            "a9c68440003151dd3cf7ffa4eaedd425d221d268",
+            "12344f25fe1a269a068c369e58ce11295dcdeb0c",
+            "707a178071655bed661318a5344557fe3e9a6ce1",
+        ] {
+            odb::tests::write_commit(&objects_dir, id, &[]);
+        }
+        // 'main' is also a tag, pointing to an annotated tag object
+        std::fs::create_dir_all(git_dir.join("refs").join("tags")).unwrap();
+        std::fs::File::create(git_dir.join("refs").join("tags").join("main"))
+            .unwrap()
+            .write_all("6e442625fe1a269a068c369e58ce11295dcdeb0c\n".as_bytes())
+            .unwrap();
+        odb::tests::write_loose_object(
+            &objects_dir,
+            "6e442625fe1a269a068c369e58ce11295dcdeb0c",
+            "tag",
+            b"object 707a178071655bed661318a5344557fe3e9a6ce1\ntype commit\n\nmsg\n",
+        );
+
+        let getter = FsBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: GitEnv::default(),
+        };
+        let resolve = |rev: &str| getter.resolve_rev(rev).map(|id| id.to_string());
+
+        assert_eq!(
+            resolve("HEAD").unwrap(),
+            "a9c68440003151dd3cf7ffa4eaedd425d221d268"
+        );
+        assert_eq!(
+            resolve("main").unwrap(),
+            "707a178071655bed661318a5344557fe3e9a6ce1"
+        );
+        assert_eq!(
+            resolve("heads/main").unwrap(),
+            "e2bf29060f42743538be07c164820cdeca0d9d2b"
+        );
+        assert_eq!(
+            resolve("origin").unwrap(),
+            "e2bf29060f42743538be07c164820cdeca0d9d2b"
+        );
+        assert_eq!(
+            resolve("upstream/packed_2").unwrap(),
+            "12344f25fe1a269a068c369e58ce11295dcdeb0c"
+        );
+        assert_eq!(
+            resolve("a9c68440003151dd3cf7ffa4eaedd425d221d268").unwrap(),
+            "a9c68440003151dd3cf7ffa4eaedd425d221d268"
+        );
+        assert_eq!(
+            resolve("a9c68440").unwrap(),
+            "a9c68440003151dd3cf7ffa4eaedd425d221d268"
+        );
+        assert_eq!(
+            resolve("a9c6844").unwrap(),
+            "a9c68440003151dd3cf7ffa4eaedd425d221d268"
+        );
+        assert!(resolve("beef")
+            .unwrap_err()
+            .to_string()
+            .contains("could not resolve 'beef'"));
+        assert!(resolve("not_a_branch")
+            .unwrap_err()
+            .to_string()
+            .contains("could not resolve 'not_a_branch'"));
+    }
+
+    #[test]
+    fn fs_branch_getter_finds_reachable_commits() {
+        let temp_dir = make_test_git_dir().unwrap();
+        let objects_dir = temp_dir.path().join(GIT_DIR).join("objects");
+        let main = "e2bf29060f42743538be07c164820cdeca0d9d2b";
+        let other = "a9c68440003151dd3cf7ffa4eaedd425d221d268";
+        let unrelated = "da7d6bf0955fa4d511067c00551fee04c613079d";
+        odb::tests::write_commit(&objects_dir, other, &[]);
+        odb::tests::write_commit(&objects_dir, main, &[other]);
+        odb::tests::write_commit(&objects_dir, unrelated, &[]);
+
+        let getter = FsBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: GitEnv::default(),
+        };
+        let id = |hex| ObjectId::from_hex(hex).unwrap();
+        let reachable = getter
+            .reachable(&id(main), &[id(main), id(other), id(unrelated)])
+            .unwrap();
+
+        assert_eq!(reachable, vec![true, true, false]);
+    }
+
+    #[test]
+    fn fs_branch_getter_counts_commits_ahead_and_behind() {
+        let temp_dir = make_test_git_dir().unwrap();
+        let objects_dir = temp_dir.path().join(GIT_DIR).join("objects");
+        let root = "12344f25fe1a269a068c369e58ce11295dcdeb0c";
+        let main = "e2bf29060f42743538be07c164820cdeca0d9d2b";
+        let other = "a9c68440003151dd3cf7ffa4eaedd425d221d268";
+        odb::tests::write_commit(&objects_dir, root, &[]);
+        odb::tests::write_commit(&objects_dir, main, &[root]);
+        odb::tests::write_commit(&objects_dir, other, &[root]);
+
+        let getter = FsBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: GitEnv::default(),
+        };
+        let id = |hex| ObjectId::from_hex(hex).unwrap();
+        let counts = getter
+            .ahead_behind(&[(id(main), id(other)), (id(main), id(root))])
+            .unwrap();
+
+        assert_eq!(
+            counts,
+            vec![
+                AheadBehind {
+                    ahead: 1,
+                    behind: 1
+                },
+                AheadBehind {
+                    ahead: 1,
+                    behind: 0
+                }
+            ]
+        );
+    }
+
+    fn make_reftable_test_repo() -> tempfile::TempDir {
+        use reftable::tests::{build_table, write_stack, TestRecord};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let git_dir = temp_dir.path().join(GIT_DIR);
+        std::fs::create_dir_all(git_dir.join("objects")).unwrap();
+        // git writes these placeholders so older versions do not mistake the
+        // repository for one using files
+        std::fs::write(git_dir.join(HEAD_FILE), "ref: refs/heads/.invalid\n").unwrap();
+        std::fs::create_dir_all(git_dir.join("refs")).unwrap();
+        std::fs::write(
+            git_dir.join("refs").join("heads"),
+            "this repository uses the reftable format\n",
+        )
+        .unwrap();
+        let old = build_table(
+            &[
+                TestRecord::Symref("HEAD", "refs/heads/main"),
+                TestRecord::Object(
+                    "refs/heads/deleted",
+                    "a9c68440003151dd3cf7ffa4eaedd425d221d268",
+                ),
+                TestRecord::Object(
+                    "refs/heads/main",
+                    "a9c68440003151dd3cf7ffa4eaedd425d221d268",
+                ),
+                TestRecord::Symref("refs/remotes/origin/HEAD", "refs/remotes/origin/main"),
+                TestRecord::Object(
+                    "refs/remotes/origin/main",
+                    "e2bf29060f42743538be07c164820cdeca0d9d2b",
+                ),
+            ],
+            2,
+            256,
+        );
+        let new = build_table(
+            &[
+                TestRecord::Deletion("refs/heads/deleted"),
+                TestRecord::Object(
+                    "refs/heads/main",
+                    "e2bf29060f42743538be07c164820cdeca0d9d2b",
+                ),
+                TestRecord::Object(
+                    "refs/heads/user/feature",
+                    "707a178071655bed661318a5344557fe3e9a6ce1",
+                ),
+                TestRecord::Peeled(
+                    "refs/tags/v1.0",
+                    "6e442625fe1a269a068c369e58ce11295dcdeb0c",
+                    "707a178071655bed661318a5344557fe3e9a6ce1",
+                ),
+            ],
+            16,
+            0,
+        );
+        write_stack(&git_dir, &[old, new]);
+        temp_dir
+    }
+
+    #[test]
+    fn fs_branch_getter_retrieves_branches_from_reftable_repo() {
+        let temp_dir = make_reftable_test_repo();
+        std::fs::write(
+            temp_dir.path().join(GIT_DIR).join(CONFIG_FILE),
+            "[branch \"main\"]\n\tremote = origin\n\tmerge = refs/heads/main\n\
+             [branch \"user/feature\"]\n\tremote = origin\n\tmerge = refs/heads/feature\n",
+        )
+        .unwrap();
+
+        let getter = FsBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: GitEnv::default(),
+        };
+        let branches = getter.branches(&mut vec![]).unwrap();
+
+        assert_eq!(
+            branches,
+            vec![
+                Branch {
+                    name: "main".to_string(),
+                    branch_type: BranchType::Local,
+                    is_head: true,
+                    upstream: Some("refs/remotes/origin/main".to_string()),
+                    target: ObjectId::from_hex("e2bf29060f42743538be07c164820cdeca0d9d2b"),
+                    ..Default::default()
+                },
+                Branch {
+                    name: "user/feature".to_string(),
+                    branch_type: BranchType::Local,
+                    upstream: Some("refs/remotes/origin/feature".to_string()),
+                    upstream_gone: true,
+                    target: ObjectId::from_hex("707a178071655bed661318a5344557fe3e9a6ce1"),
+                    ..Default::default()
+                },
+                Branch {
+                    name: "origin/HEAD".to_string(),
+                    branch_type: BranchType::Remote,
+                    remote: Some("origin".to_string()),
+                    symref: Some("refs/remotes/origin/main".to_string()),
+                    target: ObjectId::from_hex("e2bf29060f42743538be07c164820cdeca0d9d2b"),
+                    ..Default::default()
+                },
+                Branch {
+                    name: "origin/main".to_string(),
+                    branch_type: BranchType::Remote,
+                    remote: Some("origin".to_string()),
+                    target: ObjectId::from_hex("e2bf29060f42743538be07c164820cdeca0d9d2b"),
+                    ..Default::default()
+                },
+                Branch {
+                    name: "v1.0".to_string(),
+                    branch_type: BranchType::Tag,
+                    target: ObjectId::from_hex("6e442625fe1a269a068c369e58ce11295dcdeb0c"),
+                    peeled: ObjectId::from_hex("707a178071655bed661318a5344557fe3e9a6ce1"),
+                    ..Default::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn fs_branch_getter_resolves_revs_in_reftable_repo() {
+        let temp_dir = make_reftable_test_repo();
+        let objects_dir = temp_dir.path().join(GIT_DIR).join("objects");
+        for id in [
+            "e2bf29060f42743538be07c164820cdeca0d9d2b",
+            "707a178071655bed661318a5344557fe3e9a6ce1",
+        ] {
+            odb::tests::write_commit(&objects_dir, id, &[]);
+        }
+        odb::tests::write_loose_object(
+            &objects_dir,
+            "6e442625fe1a269a068c369e58ce11295dcdeb0c",
+            "tag",
+            b"object 707a178071655bed661318a5344557fe3e9a6ce1\ntype commit\n\nmsg\n",
+        );
+
+        let getter = FsBranchGetter {
+            repo_dir: temp_dir.path().to_path_buf(),
+            env: GitEnv::default(),
+        };
+        let resolve = |rev: &str| getter.resolve_rev(rev).map(|id| id.to_string());
+
+        assert_eq!(
+            resolve("HEAD").unwrap(),
+            "e2bf29060f42743538be07c164820cdeca0d9d2b"
+        );
+        assert_eq!(
+            resolve("origin").unwrap(),
+            "e2bf29060f42743538be07c164820cdeca0d9d2b"
+        );
+        assert_eq!(
+            resolve("v1.0").unwrap(),
+            "707a178071655bed661318a5344557fe3e9a6ce1"
+        );
+        assert!(resolve("deleted").is_err());
+    }
+}
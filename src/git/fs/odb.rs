@@ -0,0 +1,1525 @@
+use std::cell::RefCell;
+use std::collections::{BTreeSet, BinaryHeap, HashMap, HashSet};
+use std::io::{BufRead, Read, Seek};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::git::object::{tag_target, Commit, Object, ObjectFormat, ObjectId, ObjectKind};
+use crate::git::AheadBehind;
+use crate::Error;
+
+const PACK_IDX_MAGIC: &[u8] = b"\xfftOc";
+const FANOUT_LEN: usize = 256 * 4;
+const MAX_DELTA_DEPTH: usize = 64;
+const MAX_PEEL_DEPTH: usize = 16;
+// the shortest abbreviated object ID git accepts
+const MIN_ABBREV_LEN: usize = 4;
+// sizes in pack headers are untrusted, so buffers grow from at most this
+const MAX_PREALLOC: usize = 1 << 20;
+const MAX_DELTA_BASE_CACHE_SIZE: usize = 16 << 20;
+
+// flags marking which side of a history walk (e.g., of an ahead/behind count) a
+// commit is reachable from
+const FROM_COMMIT: u8 = 1;
+const FROM_BASE: u8 = 2;
+const FROM_BOTH: u8 = FROM_COMMIT | FROM_BASE;
+
+const PACK_COMMIT: u8 = 1;
+const PACK_TREE: u8 = 2;
+const PACK_BLOB: u8 = 3;
+const PACK_TAG: u8 = 4;
+const PACK_OFS_DELTA: u8 = 6;
+const PACK_REF_DELTA: u8 = 7;
+
+/// Reads objects from a repository's `objects` directory, both loose
+/// (zlib-compressed files) and packed (in pack files, found via their index).
+pub struct ObjectDb {
+    /// The objects directory, followed by any alternates.
+    object_dirs: Vec<PathBuf>,
+    packs: Vec<PackIndex>,
+    hash_len: usize,
+    /// The pack files read from so far, by their index in `packs`, kept open
+    /// for reading further objects.
+    pack_files: RefCell<HashMap<usize, std::io::BufReader<std::fs::File>>>,
+    delta_bases: RefCell<DeltaBaseCache>,
+}
+
+impl ObjectDb {
+    /// Open the object database in `objects_dir`, whose objects are named
+    /// with the given hash function.
+    pub fn open(objects_dir: &Path, format: ObjectFormat) -> Result<ObjectDb, Error> {
+        let hash_len = format.hash_len();
+        let mut object_dirs = vec![objects_dir.to_path_buf()];
+        object_dirs.append(&mut read_alternates(objects_dir));
+        let mut packs = vec![];
+        for dir in &object_dirs {
+            packs.append(&mut read_pack_indexes(&dir.join("pack"), hash_len)?);
+        }
+        Ok(ObjectDb {
+            object_dirs,
+            packs,
+            hash_len,
+            pack_files: RefCell::default(),
+            delta_bases: RefCell::default(),
+        })
+    }
+
+    pub fn read(&self, id: &ObjectId) -> Result<Object, Error> {
+        self.read_at_depth(id, 0)
+    }
+
+    pub fn read_commit(&self, id: &ObjectId) -> Result<Commit, Error> {
+        let object = self.read(id)?;
+        if object.kind != ObjectKind::Commit {
+            return Err(Error::Git(format!("object {id} is not a commit")));
+        }
+        Commit::parse(id.clone(), &object.data)
+    }
+
+    /// Read the commit `id` names, or `None` if the object is missing (e.g.,
+    /// in a shallow or partial clone), is not a commit, or is malformed.
+    /// Objects that exist but cannot be read are still errors.
+    pub fn find_commit(&self, id: &ObjectId) -> Result<Option<Commit>, Error> {
+        if !self.contains(id) {
+            return Ok(None);
+        }
+        let object = self.read(id)?;
+        if object.kind != ObjectKind::Commit {
+            return Ok(None);
+        }
+        Ok(Commit::parse(id.clone(), &object.data).ok())
+    }
+
+    /// Whether the object exists, without reading it.
+    pub fn contains(&self, id: &ObjectId) -> bool {
+        let hex = id.to_hex();
+        self.object_dirs
+            .iter()
+            .any(|dir| dir.join(&hex[..2]).join(&hex[2..]).is_file())
+            || self.packs.iter().any(|pack| pack.find(id).is_some())
+    }
+
+    /// Find the object whose ID starts with the given (case-insensitive)
+    /// hexadecimal digits, as git does for abbreviated IDs. Gives `None` if no
+    /// object does, and an error if more than one does.
+    pub fn resolve_abbrev(&self, hex: &str) -> Result<Option<ObjectId>, Error> {
+        let hex = hex.to_ascii_lowercase();
+        if hex.len() < MIN_ABBREV_LEN
+            || hex.len() > 2 * self.hash_len
+            || !hex.bytes().all(|b| b.is_ascii_hexdigit())
+        {
+            return Ok(None);
+        }
+        let mut ids = BTreeSet::new();
+        for dir in &self.object_dirs {
+            let Ok(entries) = std::fs::read_dir(dir.join(&hex[..2])) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let full_hex = format!("{}{}", &hex[..2], entry.file_name().to_string_lossy());
+                if full_hex.len() == 2 * self.hash_len && full_hex.starts_with(&hex) {
+                    ids.extend(ObjectId::from_hex(&full_hex));
+                }
+            }
+        }
+        for pack in &self.packs {
+            ids.extend(pack.find_prefix(&hex));
+        }
+        if ids.len() > 1 {
+            return Err(Error::Git(format!("short object ID {hex} is ambiguous")));
+        }
+        Ok(ids.into_iter().next())
+    }
+
+    /// Follow (possibly nested) annotated tags to the commit they point to.
+    pub fn peel_to_commit(&self, id: &ObjectId) -> Result<ObjectId, Error> {
+        let mut id = id.clone();
+        for _ in 0..MAX_PEEL_DEPTH {
+            let object = self.read(&id)?;
+            id = match object.kind {
+                ObjectKind::Commit => return Ok(id),
+                ObjectKind::Tag => tag_target(&object.data)
+                    .ok_or_else(|| Error::Git(format!("invalid tag {id}: missing object")))?,
+                _ => return Err(Error::Git(format!("object {id} is not a commit"))),
+            };
+        }
+        Err(Error::Git(format!("too many nested tags at {id}")))
+    }
+
+    /// Like `peel_to_commit`, but gives `None` if an object on the way is
+    /// missing or the tags do not point to a commit.
+    pub fn find_peeled_commit(&self, id: &ObjectId) -> Result<Option<ObjectId>, Error> {
+        let mut id = id.clone();
+        for _ in 0..MAX_PEEL_DEPTH {
+            if !self.contains(&id) {
+                return Ok(None);
+            }
+            let object = self.read(&id)?;
+            id = match object.kind {
+                ObjectKind::Commit => return Ok(Some(id)),
+                ObjectKind::Tag => tag_target(&object.data)
+                    .ok_or_else(|| Error::Git(format!("invalid tag {id}: missing object")))?,
+                _ => return Ok(None),
+            };
+        }
+        Err(Error::Git(format!("too many nested tags at {id}")))
+    }
+
+    /// Which of `commits` are reachable from `from`, i.e., are `from` or one
+    /// of its ancestors.
+    ///
+    /// This walks the histories of `from` and `commits` together, newest
+    /// first, so it can stop at their common ancestors rather than walking
+    /// all of `from`'s history when some of `commits` are not reachable.
+    pub fn reachable(&self, from: &ObjectId, commits: &[ObjectId]) -> Result<Vec<bool>, Error> {
+        let mut unreached: HashSet<&ObjectId> = commits.iter().collect();
+        let mut tips = vec![(from, FROM_BASE)];
+        tips.extend(commits.iter().map(|commit| (commit, FROM_COMMIT)));
+        let flags = self.paint_down(&tips, |id, flag| {
+            if flag & FROM_BASE != 0 {
+                unreached.remove(id);
+            }
+            unreached.is_empty()
+        })?;
+        Ok(commits
+            .iter()
+            .map(|commit| flags.get(commit).is_some_and(|f| f & FROM_BASE != 0))
+            .collect())
+    }
+
+    /// Count the commits reachable from `commit` but not from `base` (ahead),
+    /// and from `base` but not from `commit` (behind).
+    pub fn ahead_behind(&self, commit: &ObjectId, base: &ObjectId) -> Result<AheadBehind, Error> {
+        let flags = self.paint_down(&[(commit, FROM_COMMIT), (base, FROM_BASE)], |_, _| false)?;
+        let count = |flag| flags.values().filter(|f| **f == flag).count();
+        Ok(AheadBehind {
+            ahead: count(FROM_COMMIT),
+            behind: count(FROM_BASE),
+        })
+    }
+
+    /// Flag each of `tips`, and the commits reachable from it, with the side
+    /// it is on, returning the flags of the commits visited.
+    ///
+    /// Like git's `paint_down_to_common`, this walks the histories newest
+    /// first, stopping once every commit left to visit is reachable from both
+    /// sides, or once `painted`, called whenever a commit's flags change,
+    /// returns true.
+    fn paint_down(
+        &self,
+        tips: &[(&ObjectId, u8)],
+        mut painted: impl FnMut(&ObjectId, u8) -> bool,
+    ) -> Result<HashMap<ObjectId, u8>, Error> {
+        let mut nodes: HashMap<ObjectId, (i64, Vec<ObjectId>)> = HashMap::new();
+        let mut flags: HashMap<ObjectId, u8> = HashMap::new();
+        let mut queue = BinaryHeap::new();
+        // how many times each commit is queued, and how many queued entries
+        // are not yet reachable from both sides, so the walk can stop once
+        // only common ancestors are left without scanning the queue
+        let mut queued: HashMap<ObjectId, usize> = HashMap::new();
+        let mut not_both = 0;
+        let mut to_paint: Vec<(ObjectId, u8)> = tips
+            .iter()
+            .map(|(id, flag)| ((*id).clone(), *flag))
+            .collect();
+        loop {
+            for (id, flag) in to_paint.drain(..) {
+                let old_flag = flags.entry(id.clone()).or_insert(0);
+                if *old_flag | flag == *old_flag {
+                    continue;
+                }
+                *old_flag |= flag;
+                let new_flag = *old_flag;
+                let count = queued.entry(id.clone()).or_insert(0);
+                if new_flag == FROM_BOTH {
+                    // entries queued before it became common no longer count
+                    not_both -= *count;
+                } else {
+                    not_both += 1;
+                }
+                *count += 1;
+                if painted(&id, new_flag) {
+                    return Ok(flags);
+                }
+                queue.push((self.commit_node(&id, &mut nodes)?.0, id));
+            }
+            if not_both == 0 {
+                break;
+            }
+            let (_, id) = match queue.pop() {
+                Some(item) => item,
+                None => break,
+            };
+            let flag = flags[&id];
+            *queued.get_mut(&id).unwrap() -= 1;
+            if flag != FROM_BOTH {
+                not_both -= 1;
+            }
+            let parents = self.commit_node(&id, &mut nodes)?.1;
+            to_paint.extend(parents.into_iter().map(|parent| (parent, flag)));
+        }
+        Ok(flags)
+    }
+
+    /// The committer time and parents of a commit, cached in `nodes`. Commits
+    /// that are missing (e.g., beyond a shallow clone's boundary) have no
+    /// parents.
+    fn commit_node(
+        &self,
+        id: &ObjectId,
+        nodes: &mut HashMap<ObjectId, (i64, Vec<ObjectId>)>,
+    ) -> Result<(i64, Vec<ObjectId>), Error> {
+        if let Some(node) = nodes.get(id) {
+            return Ok(node.clone());
+        }
+        let node = if self.contains(id) {
+            let commit = self.read_commit(id)?;
+            (commit.committer.time, commit.parents)
+        } else {
+            (i64::MIN, vec![])
+        };
+        nodes.insert(id.clone(), node.clone());
+        Ok(node)
+    }
+
+    fn read_at_depth(&self, id: &ObjectId, depth: usize) -> Result<Object, Error> {
+        for dir in &self.object_dirs {
+            let hex = id.to_hex();
+            let loose_path = dir.join(&hex[..2]).join(&hex[2..]);
+            if loose_path.is_file() {
+                return read_loose_object(&loose_path)
+                    .map_err(|e| Error::Git(format!("could not read object {id}: {e}")));
+            }
+        }
+        for (i, pack) in self.packs.iter().enumerate() {
+            if let Some(offset) = pack.find(id) {
+                return self
+                    .read_packed_object(i, offset, depth)
+                    .map_err(|e| Error::Git(format!("could not read object {id}: {e}")));
+            }
+        }
+        Err(Error::Git(format!("object {id} not found")))
+    }
+
+    /// Read the object at `offset` in the pack at index `pack` of `packs`.
+    fn read_packed_object(
+        &self,
+        pack: usize,
+        offset: u64,
+        depth: usize,
+    ) -> Result<Object, Box<dyn std::error::Error>> {
+        if depth > MAX_DELTA_DEPTH {
+            return Err("delta chain too long".into());
+        }
+        let (base, delta) = match self.read_stored_entry(pack, offset)? {
+            StoredEntry::Object(object) => return Ok(object),
+            StoredEntry::OfsDelta(base_offset, delta) => {
+                (self.read_delta_base(pack, base_offset, depth + 1)?, delta)
+            }
+            StoredEntry::RefDelta(base_id, delta) => {
+                (Rc::new(self.read_at_depth(&base_id, depth + 1)?), delta)
+            }
+        };
+        Ok(Object {
+            kind: base.kind,
+            data: apply_delta(&base.data, &delta)?,
+        })
+    }
+
+    /// Read the base of an `OFS_DELTA` entry. Bases are often shared by many
+    /// deltas, so are cached.
+    fn read_delta_base(
+        &self,
+        pack: usize,
+        offset: u64,
+        depth: usize,
+    ) -> Result<Rc<Object>, Box<dyn std::error::Error>> {
+        if let Some(base) = self.delta_bases.borrow().get(pack, offset) {
+            return Ok(base);
+        }
+        let base = Rc::new(self.read_packed_object(pack, offset, depth)?);
+        self.delta_bases
+            .borrow_mut()
+            .insert(pack, offset, base.clone());
+        Ok(base)
+    }
+
+    /// Read the entry at `offset` in a pack, without resolving deltas, so the
+    /// pack file is not borrowed while their bases are read.
+    fn read_stored_entry(
+        &self,
+        pack: usize,
+        offset: u64,
+    ) -> Result<StoredEntry, Box<dyn std::error::Error>> {
+        let mut pack_files = self.pack_files.borrow_mut();
+        let file = match pack_files.entry(pack) {
+            std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut(),
+            std::collections::hash_map::Entry::Vacant(entry) => entry.insert(
+                std::io::BufReader::new(std::fs::File::open(&self.packs[pack].pack_path)?),
+            ),
+        };
+        file.seek(std::io::SeekFrom::Start(offset))?;
+        let (pack_type, size) = read_pack_entry_header(file)?;
+        let kind = match pack_type {
+            PACK_COMMIT => ObjectKind::Commit,
+            PACK_TREE => ObjectKind::Tree,
+            PACK_BLOB => ObjectKind::Blob,
+            PACK_TAG => ObjectKind::Tag,
+            PACK_OFS_DELTA => {
+                let base_offset = offset
+                    .checked_sub(read_ofs_delta_offset(file)?)
+                    .ok_or("invalid delta base offset")?;
+                return Ok(StoredEntry::OfsDelta(base_offset, inflate(file, size)?));
+            }
+            PACK_REF_DELTA => {
+                let mut base_id = vec![0; self.hash_len];
+                file.read_exact(&mut base_id)?;
+                let base_id = ObjectId::from_bytes(&base_id);
+                return Ok(StoredEntry::RefDelta(base_id, inflate(file, size)?));
+            }
+            t => return Err(format!("unknown pack object type {t}").into()),
+        };
+        Ok(StoredEntry::Object(Object {
+            kind,
+            data: inflate(file, size)?,
+        }))
+    }
+}
+
+/// A pack entry as it is stored: either a whole object, or a delta against
+/// the object at an offset in the same pack or with an ID.
+enum StoredEntry {
+    Object(Object),
+    OfsDelta(u64, Vec<u8>),
+    RefDelta(ObjectId, Vec<u8>),
+}
+
+/// Recently read delta bases, keyed by the index of their pack and their
+/// offset in it. Once the bases' total size reaches a limit, the cache is
+/// emptied.
+#[derive(Default)]
+struct DeltaBaseCache {
+    objects: HashMap<(usize, u64), Rc<Object>>,
+    size: usize,
+}
+
+impl DeltaBaseCache {
+    fn get(&self, pack: usize, offset: u64) -> Option<Rc<Object>> {
+        self.objects.get(&(pack, offset)).cloned()
+    }
+
+    fn insert(&mut self, pack: usize, offset: u64, object: Rc<Object>) {
+        if object.data.len() > MAX_DELTA_BASE_CACHE_SIZE {
+            return;
+        }
+        if self.size + object.data.len() > MAX_DELTA_BASE_CACHE_SIZE {
+            self.objects.clear();
+            self.size = 0;
+        }
+        self.size += object.data.len();
+        self.objects.insert((pack, offset), object);
+    }
+}
+
+/// The object directories listed in `info/alternates`, which a repository
+/// borrows objects from (e.g., after `git clone --shared`).
+fn read_alternates(objects_dir: &Path) -> Vec<PathBuf> {
+    let content = match std::fs::read_to_string(objects_dir.join("info").join("alternates")) {
+        Ok(c) => c,
+        Err(_) => return vec![],
+    };
+    content
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| objects_dir.join(line))
+        .filter(|dir| dir.is_dir())
+        .collect()
+}
+
+fn read_loose_object(path: &Path) -> Result<Object, Box<dyn std::error::Error>> {
+    let mut decoder = flate2::read::ZlibDecoder::new(std::fs::File::open(path)?);
+    let mut raw = vec![];
+    decoder.read_to_end(&mut raw)?;
+    // loose objects start with a '<type> <size>\0' header
+    let header_end = raw
+        .iter()
+        .position(|b| *b == 0)
+        .ok_or("missing object header")?;
+    let data = raw.split_off(header_end + 1);
+    let header = std::str::from_utf8(&raw[..header_end])?;
+    let (kind, size) = header.split_once(' ').ok_or("invalid object header")?;
+    let kind = ObjectKind::from_name(kind).ok_or("unknown object type")?;
+    if data.len() != size.parse::<usize>()? {
+        return Err("object size does not match header".into());
+    }
+    Ok(Object { kind, data })
+}
+
+/// A pack file's `.idx` file, mapping object IDs to offsets in the pack.
+struct PackIndex {
+    pack_path: PathBuf,
+    data: Vec<u8>,
+    version: u32,
+    count: usize,
+    hash_len: usize,
+}
+
+fn read_pack_indexes(pack_dir: &Path, hash_len: usize) -> Result<Vec<PackIndex>, Error> {
+    if !pack_dir.is_dir() {
+        return Ok(vec![]);
+    }
+    let entries = std::fs::read_dir(pack_dir)
+        .map_err(|e| Error::Git(format!("could not list pack files: {e}")))?;
+    let mut indexes = vec![];
+    for entry in entries {
+        let path = entry
+            .map_err(|e| Error::Git(format!("could not list pack files: {e}")))?
+            .path();
+        if path.extension().is_none_or(|ext| ext != "idx") {
+            continue;
+        }
+        let pack_path = path.with_extension("pack");
+        if !pack_path.is_file() {
+            continue;
+        }
+        let index = PackIndex::read(&path, pack_path, hash_len).map_err(|e| {
+            Error::Git(format!(
+                "could not read pack index '{}': {e}",
+                path.to_string_lossy()
+            ))
+        })?;
+        indexes.push(index);
+    }
+    Ok(indexes)
+}
+
+impl PackIndex {
+    fn read(
+        idx_path: &Path,
+        pack_path: PathBuf,
+        hash_len: usize,
+    ) -> Result<PackIndex, Box<dyn std::error::Error>> {
+        let data = std::fs::read(idx_path)?;
+        let version = if data.starts_with(PACK_IDX_MAGIC) {
+            read_u32(&data, 4).ok_or("truncated header")?
+        } else {
+            1
+        };
+        if version != 1 && version != 2 {
+            return Err(format!("unsupported version {version}").into());
+        }
+        let mut index = PackIndex {
+            pack_path,
+            data,
+            version,
+            count: 0,
+            hash_len,
+        };
+        // each fanout entry counts the objects up to and including its byte,
+        // so the last is the total and none may exceed it
+        let mut count = 0;
+        for byte in 0..256 {
+            let entry = index.fanout(byte).ok_or("truncated fanout table")?;
+            if entry < count {
+                return Err("corrupt fanout table".into());
+            }
+            count = entry;
+        }
+        index.count = count as usize;
+        // the table of 64-bit offsets is optional, but the trailing pack and
+        // index checksums are not
+        let min_len = match version {
+            1 => FANOUT_LEN + index.count * (4 + hash_len),
+            _ => 8 + FANOUT_LEN + index.count * (hash_len + 8),
+        } + 2 * hash_len;
+        if index.data.len() < min_len {
+            return Err("truncated index".into());
+        }
+        Ok(index)
+    }
+
+    /// The number of objects whose first byte is at most `byte`.
+    fn fanout(&self, byte: usize) -> Option<u32> {
+        let start = if self.version == 1 { 0 } else { 8 };
+        read_u32(&self.data, start + byte * 4)
+    }
+
+    fn id_at(&self, i: usize) -> Option<&[u8]> {
+        let start = match self.version {
+            1 => FANOUT_LEN + i * (4 + self.hash_len) + 4,
+            _ => 8 + FANOUT_LEN + i * self.hash_len,
+        };
+        self.data.get(start..start + self.hash_len)
+    }
+
+    fn offset_at(&self, i: usize) -> Option<u64> {
+        if self.version == 1 {
+            return read_u32(&self.data, FANOUT_LEN + i * (4 + self.hash_len)).map(u64::from);
+        }
+        let offsets_start = 8 + FANOUT_LEN + self.count * (self.hash_len + 4);
+        let offset = read_u32(&self.data, offsets_start + i * 4)?;
+        if offset & 0x8000_0000 == 0 {
+            return Some(u64::from(offset));
+        }
+        // the offset is an index into the table of 64-bit offsets
+        let large_start = offsets_start + self.count * 4 + (offset & 0x7fff_ffff) as usize * 8;
+        let bytes = self.data.get(large_start..large_start + 8)?;
+        Some(u64::from_be_bytes(bytes.try_into().ok()?))
+    }
+
+    /// The range of indices of the objects whose first byte is `byte`.
+    fn bucket(&self, byte: usize) -> Option<(usize, usize)> {
+        let lo = if byte == 0 {
+            0
+        } else {
+            self.fanout(byte - 1)? as usize
+        };
+        Some((lo, self.fanout(byte)? as usize))
+    }
+
+    fn find(&self, id: &ObjectId) -> Option<u64> {
+        let id = id.as_bytes();
+        let (mut lo, mut hi) = self.bucket(*id.first()? as usize)?;
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            match self.id_at(mid)?.cmp(id) {
+                std::cmp::Ordering::Less => lo = mid + 1,
+                std::cmp::Ordering::Greater => hi = mid,
+                std::cmp::Ordering::Equal => return self.offset_at(mid),
+            }
+        }
+        None
+    }
+
+    /// The IDs of the objects whose hexadecimal form starts with `prefix`,
+    /// which must be lower case and at least two characters long.
+    fn find_prefix(&self, prefix: &str) -> Vec<ObjectId> {
+        let Some(bytes) = ObjectId::from_hex(&prefix[..prefix.len() / 2 * 2]) else {
+            return vec![];
+        };
+        let bytes = bytes.as_bytes();
+        let Some((mut lo, hi)) = self.bucket(bytes[0] as usize) else {
+            return vec![];
+        };
+        // find the first ID that is not before the prefix
+        let mut end = hi;
+        while lo < end {
+            let mid = lo + (end - lo) / 2;
+            match self.id_at(mid) {
+                Some(id) if id < bytes => lo = mid + 1,
+                _ => end = mid,
+            }
+        }
+        (lo..hi)
+            .map_while(|i| self.id_at(i).filter(|id| id.starts_with(bytes)))
+            .map(ObjectId::from_bytes)
+            .filter(|id| id.to_hex().starts_with(prefix))
+            .collect()
+    }
+}
+
+fn read_u32(data: &[u8], start: usize) -> Option<u32> {
+    let bytes = data.get(start..start + 4)?;
+    Some(u32::from_be_bytes(bytes.try_into().ok()?))
+}
+
+fn read_byte(reader: &mut impl Read) -> Result<u8, std::io::Error> {
+    let mut byte = [0];
+    reader.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+/// Read a pack entry's type and inflated size.
+fn read_pack_entry_header(reader: &mut impl Read) -> Result<(u8, usize), std::io::Error> {
+    let mut byte = read_byte(reader)?;
+    let pack_type = (byte >> 4) & 0x7;
+    let mut size = (byte & 0xf) as usize;
+    let mut shift = 4;
+    while byte & 0x80 != 0 {
+        if shift + 7 > usize::BITS {
+            return Err(invalid_data("pack entry size too large"));
+        }
+        byte = read_byte(reader)?;
+        size |= ((byte & 0x7f) as usize) << shift;
+        shift += 7;
+    }
+    Ok((pack_type, size))
+}
+
+/// Read how far before an `OFS_DELTA` entry its base object is.
+fn read_ofs_delta_offset(reader: &mut impl Read) -> Result<u64, std::io::Error> {
+    let mut byte = read_byte(reader)?;
+    let mut offset = u64::from(byte & 0x7f);
+    while byte & 0x80 != 0 {
+        byte = read_byte(reader)?;
+        offset = offset
+            .checked_add(1)
+            .filter(|offset| offset.leading_zeros() >= 7)
+            .ok_or_else(|| invalid_data("delta base offset too large"))?
+            << 7
+            | u64::from(byte & 0x7f);
+    }
+    Ok(offset)
+}
+
+fn invalid_data(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+fn inflate(reader: &mut impl BufRead, size: usize) -> Result<Vec<u8>, std::io::Error> {
+    let mut data = Vec::with_capacity(size.min(MAX_PREALLOC));
+    flate2::bufread::ZlibDecoder::new(reader)
+        .take(size as u64)
+        .read_to_end(&mut data)?;
+    if data.len() != size {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            "object shorter than expected",
+        ));
+    }
+    Ok(data)
+}
+
+fn read_delta_size(delta: &[u8], pos: &mut usize) -> Result<usize, &'static str> {
+    let mut size = 0;
+    let mut shift = 0;
+    loop {
+        if shift + 7 > usize::BITS {
+            return Err("delta size too large");
+        }
+        let byte = *delta.get(*pos).ok_or("truncated delta")?;
+        *pos += 1;
+        size |= ((byte & 0x7f) as usize) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            return Ok(size);
+        }
+    }
+}
+
+/// Rebuild an object from its base and a delta of copy/insert instructions.
+fn apply_delta(base: &[u8], delta: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut pos = 0;
+    let base_size = read_delta_size(delta, &mut pos)?;
+    if base_size != base.len() {
+        return Err("delta base size mismatch".into());
+    }
+    let result_size = read_delta_size(delta, &mut pos)?;
+    let mut result = Vec::with_capacity(result_size.min(MAX_PREALLOC));
+    while pos < delta.len() {
+        let op = delta[pos];
+        pos += 1;
+        if op & 0x80 != 0 {
+            // copy from the base, with offset and size given by flagged bytes
+            let mut copy_offset = 0;
+            let mut copy_size = 0;
+            for i in 0..4 {
+                if op & (1 << i) != 0 {
+                    copy_offset |= (*delta.get(pos).ok_or("truncated delta")? as usize) << (8 * i);
+                    pos += 1;
+                }
+            }
+            for i in 0..3 {
+                if op & (0x10 << i) != 0 {
+                    copy_size |= (*delta.get(pos).ok_or("truncated delta")? as usize) << (8 * i);
+                    pos += 1;
+                }
+            }
+            if copy_size == 0 {
+                copy_size = 0x10000;
+            }
+            let chunk = base
+                .get(copy_offset..copy_offset + copy_size)
+                .ok_or("delta copy out of range")?;
+            result.extend_from_slice(chunk);
+        } else if op != 0 {
+            // insert the next 'op' bytes of the delta
+            let chunk = delta.get(pos..pos + op as usize).ok_or("truncated delta")?;
+            result.extend_from_slice(chunk);
+            pos += op as usize;
+        } else {
+            return Err("invalid delta instruction".into());
+        }
+    }
+    if result.len() != result_size {
+        return Err("delta result size mismatch".into());
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    pub const COMMIT_DATA: &str = "tree 4b825dc642cb6eb9a060e54bf8d69288fbee4904\n\
+        author A U Thor <author@example.com> 1700000000 +0000\n\
+        committer C O Mitter <committer@example.com> 1700000100 +0000\n\
+        \n\
+        Add a feature\n";
+
+    fn deflate(data: &[u8]) -> Vec<u8> {
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    /// Write a loose object; the ID needn't be the object's real hash.
+    pub fn write_loose_object(objects_dir: &Path, id: &str, kind: &str, data: &[u8]) {
+        let dir = objects_dir.join(&id[..2]);
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut raw = format!("{kind} {}\0", data.len()).into_bytes();
+        raw.extend_from_slice(data);
+        std::fs::write(dir.join(&id[2..]), deflate(&raw)).unwrap();
+    }
+
+    pub enum PackEntry<'a> {
+        Full(u8, &'a [u8]),
+        /// A delta against the entry at the given index in the pack.
+        OfsDelta(usize, Vec<u8>),
+        /// A delta against the object with the given ID.
+        RefDelta(&'a str, Vec<u8>),
+    }
+
+    fn encode_entry_header(pack_type: u8, size: usize) -> Vec<u8> {
+        let mut bytes = vec![];
+        let mut byte = (pack_type << 4) | (size & 0xf) as u8;
+        let mut size = size >> 4;
+        while size != 0 {
+            bytes.push(byte | 0x80);
+            byte = (size & 0x7f) as u8;
+            size >>= 7;
+        }
+        bytes.push(byte);
+        bytes
+    }
+
+    fn encode_ofs_offset(mut offset: u64) -> Vec<u8> {
+        let mut bytes = vec![(offset & 0x7f) as u8];
+        offset >>= 7;
+        while offset != 0 {
+            offset -= 1;
+            bytes.insert(0, 0x80 | (offset & 0x7f) as u8);
+            offset >>= 7;
+        }
+        bytes
+    }
+
+    /// Write a version 2 pack and index holding the entries with the given
+    /// IDs (which needn't be the objects' real hashes). The IDs may be SHA-1
+    /// or SHA-256 length, but not a mix.
+    pub fn write_pack(objects_dir: &Path, name: &str, entries: &[(&str, PackEntry)]) {
+        let hash_len = entries.first().map_or(0, |(id, _)| id.len() / 2);
+        let pack_dir = objects_dir.join("pack");
+        std::fs::create_dir_all(&pack_dir).unwrap();
+        let mut pack = b"PACK".to_vec();
+        pack.extend_from_slice(&2u32.to_be_bytes());
+        pack.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+        let mut offsets = vec![];
+        for (_, entry) in entries {
+            let offset = pack.len() as u64;
+            match entry {
+                PackEntry::Full(pack_type, data) => {
+                    pack.extend(encode_entry_header(*pack_type, data.len()));
+                    pack.extend(deflate(data));
+                }
+                PackEntry::OfsDelta(base, delta) => {
+                    pack.extend(encode_entry_header(PACK_OFS_DELTA, delta.len()));
+                    pack.extend(encode_ofs_offset(offset - offsets[*base]));
+                    pack.extend(deflate(delta));
+                }
+                PackEntry::RefDelta(base, delta) => {
+                    pack.extend(encode_entry_header(PACK_REF_DELTA, delta.len()));
+                    pack.extend(ObjectId::from_hex(base).unwrap().as_bytes());
+                    pack.extend(deflate(delta));
+                }
+            }
+            offsets.push(offset);
+        }
+        pack.extend(vec![0; hash_len]);
+        std::fs::write(pack_dir.join(format!("{name}.pack")), pack).unwrap();
+
+        let mut sorted: Vec<(Vec<u8>, u64)> = entries
+            .iter()
+            .zip(offsets)
+            .map(|((id, _), offset)| (ObjectId::from_hex(id).unwrap().as_bytes().to_vec(), offset))
+            .collect();
+        sorted.sort();
+        let mut idx = PACK_IDX_MAGIC.to_vec();
+        idx.extend_from_slice(&2u32.to_be_bytes());
+        for byte in 0..256 {
+            let count = sorted
+                .iter()
+                .filter(|(id, _)| id[0] as usize <= byte)
+                .count();
+            idx.extend_from_slice(&(count as u32).to_be_bytes());
+        }
+        for (id, _) in &sorted {
+            idx.extend_from_slice(id);
+        }
+        for _ in &sorted {
+            idx.extend_from_slice(&0u32.to_be_bytes());
+        }
+        for (_, offset) in &sorted {
+            idx.extend_from_slice(&(*offset as u32).to_be_bytes());
+        }
+        idx.extend(vec![0; hash_len * 2]);
+        std::fs::write(pack_dir.join(format!("{name}.idx")), idx).unwrap();
+    }
+
+    /// A delta that copies the whole of a base of the given size, then appends
+    /// the given bytes.
+    pub fn append_delta(base_size: usize, append: &[u8]) -> Vec<u8> {
+        let mut delta = vec![];
+        for size in [base_size, base_size + append.len()] {
+            let mut size = size;
+            loop {
+                let byte = (size & 0x7f) as u8;
+                size >>= 7;
+                if size == 0 {
+                    delta.push(byte);
+                    break;
+                }
+                delta.push(byte | 0x80);
+            }
+        }
+        // copy with offset 0 and a two byte size
+        delta.extend([
+            0x80 | 0x10 | 0x20,
+            (base_size & 0xff) as u8,
+            (base_size >> 8) as u8,
+        ]);
+        delta.push(append.len() as u8);
+        delta.extend_from_slice(append);
+        delta
+    }
+
+    #[test]
+    fn read_returns_loose_object() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let id = "e2bf29060f42743538be07c164820cdeca0d9d2b";
+        write_loose_object(temp_dir.path(), id, "blob", b"some content");
+
+        let odb = ObjectDb::open(temp_dir.path(), ObjectFormat::Sha1).unwrap();
+        let object = odb.read(&ObjectId::from_hex(id).unwrap()).unwrap();
+
+        assert_eq!(object.kind, ObjectKind::Blob);
+        assert_eq!(object.data, b"some content");
+    }
+
+    #[test]
+    fn read_commit_parses_loose_commit() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let id = "e2bf29060f42743538be07c164820cdeca0d9d2b";
+        write_loose_object(temp_dir.path(), id, "commit", COMMIT_DATA.as_bytes());
+
+        let odb = ObjectDb::open(temp_dir.path(), ObjectFormat::Sha1).unwrap();
+        let commit = odb.read_commit(&ObjectId::from_hex(id).unwrap()).unwrap();
+
+        assert_eq!(commit.subject, "Add a feature");
+        assert_eq!(commit.author.name, "A U Thor");
+        assert_eq!(commit.committer.time, 1700000100);
+        assert!(commit.parents.is_empty());
+    }
+
+    #[test]
+    fn read_commit_returns_err_given_object_is_not_a_commit() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let id = "e2bf29060f42743538be07c164820cdeca0d9d2b";
+        write_loose_object(temp_dir.path(), id, "blob", b"some content");
+
+        let odb = ObjectDb::open(temp_dir.path(), ObjectFormat::Sha1).unwrap();
+        let result = odb.read_commit(&ObjectId::from_hex(id).unwrap());
+
+        assert!(result.unwrap_err().to_string().contains("not a commit"));
+    }
+
+    #[test]
+    fn read_returns_err_given_object_does_not_exist() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let odb = ObjectDb::open(temp_dir.path(), ObjectFormat::Sha1).unwrap();
+        let result =
+            odb.read(&ObjectId::from_hex("e2bf29060f42743538be07c164820cdeca0d9d2b").unwrap());
+
+        assert!(result.unwrap_err().to_string().contains("not found"));
+    }
+
+    #[test]
+    fn read_returns_packed_objects_including_deltas() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let base = b"base content, long enough to copy";
+        write_pack(
+            temp_dir.path(),
+            "pack-1",
+            &[
+                (
+                    "a9c68440003151dd3cf7ffa4eaedd425d221d268",
+                    PackEntry::Full(PACK_BLOB, base),
+                ),
+                (
+                    "12344f25fe1a269a068c369e58ce11295dcdeb0c",
+                    PackEntry::OfsDelta(0, append_delta(base.len(), b" + ofs")),
+                ),
+                (
+                    "6e442625fe1a269a068c369e58ce11295dcdeb0c",
+                    PackEntry::RefDelta(
+                        "12344f25fe1a269a068c369e58ce11295dcdeb0c",
+                        append_delta(base.len() + 6, b" + ref"),
+                    ),
+                ),
+            ],
+        );
+
+        let odb = ObjectDb::open(temp_dir.path(), ObjectFormat::Sha1).unwrap();
+        let read = |id: &str| odb.read(&ObjectId::from_hex(id).unwrap()).unwrap();
+
+        let full = read("a9c68440003151dd3cf7ffa4eaedd425d221d268");
+        assert_eq!(full.kind, ObjectKind::Blob);
+        assert_eq!(full.data, base);
+        let ofs = read("12344f25fe1a269a068c369e58ce11295dcdeb0c");
+        assert_eq!(ofs.kind, ObjectKind::Blob);
+        assert_eq!(ofs.data, b"base content, long enough to copy + ofs");
+        let refd = read("6e442625fe1a269a068c369e58ce11295dcdeb0c");
+        assert_eq!(refd.data, b"base content, long enough to copy + ofs + ref");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn read_keeps_pack_file_open() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        write_pack(
+            temp_dir.path(),
+            "pack-1",
+            &[
+                (
+                    "707a178071655bed661318a5344557fe3e9a6ce1",
+                    PackEntry::Full(PACK_BLOB, b"x"),
+                ),
+                (
+                    "e2bf29060f42743538be07c164820cdeca0d9d2b",
+                    PackEntry::Full(PACK_BLOB, b"y"),
+                ),
+            ],
+        );
+        let odb = ObjectDb::open(temp_dir.path(), ObjectFormat::Sha1).unwrap();
+        let read = |id: &str| odb.read(&ObjectId::from_hex(id).unwrap()).unwrap();
+        read("707a178071655bed661318a5344557fe3e9a6ce1");
+
+        // an open file can still be read after it is removed
+        std::fs::remove_file(temp_dir.path().join("pack").join("pack-1.pack")).unwrap();
+        let object = read("e2bf29060f42743538be07c164820cdeca0d9d2b");
+
+        assert_eq!(object.data, b"y");
+    }
+
+    #[test]
+    fn read_caches_delta_bases() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let base = b"base content, long enough to copy";
+        let delta_id = "12344f25fe1a269a068c369e58ce11295dcdeb0c";
+        write_pack(
+            temp_dir.path(),
+            "pack-1",
+            &[
+                (
+                    "a9c68440003151dd3cf7ffa4eaedd425d221d268",
+                    PackEntry::Full(PACK_BLOB, base),
+                ),
+                (
+                    delta_id,
+                    PackEntry::OfsDelta(0, append_delta(base.len(), b" + ofs")),
+                ),
+            ],
+        );
+        let odb = ObjectDb::open(temp_dir.path(), ObjectFormat::Sha1).unwrap();
+        let read = |id: &str| odb.read(&ObjectId::from_hex(id).unwrap());
+        read(delta_id).unwrap();
+
+        // break the base's entry, which starts after the pack's 12 byte header
+        let pack_path = temp_dir.path().join("pack").join("pack-1.pack");
+        let mut pack = std::fs::read(&pack_path).unwrap();
+        pack[12] = 0;
+        std::fs::write(&pack_path, pack).unwrap();
+        let object = read(delta_id).unwrap();
+
+        assert_eq!(object.data, b"base content, long enough to copy + ofs");
+        assert!(read("a9c68440003151dd3cf7ffa4eaedd425d221d268").is_err());
+    }
+
+    #[test]
+    fn read_commit_parses_packed_commit() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let id = "707a178071655bed661318a5344557fe3e9a6ce1";
+        write_pack(
+            temp_dir.path(),
+            "pack-1",
+            &[(id, PackEntry::Full(PACK_COMMIT, COMMIT_DATA.as_bytes()))],
+        );
+
+        let odb = ObjectDb::open(temp_dir.path(), ObjectFormat::Sha1).unwrap();
+        let commit = odb.read_commit(&ObjectId::from_hex(id).unwrap()).unwrap();
+
+        assert_eq!(commit.subject, "Add a feature");
+    }
+
+    #[test]
+    fn read_returns_err_given_id_not_in_pack_index() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        write_pack(
+            temp_dir.path(),
+            "pack-1",
+            &[(
+                "707a178071655bed661318a5344557fe3e9a6ce1",
+                PackEntry::Full(PACK_BLOB, b"x"),
+            )],
+        );
+
+        let odb = ObjectDb::open(temp_dir.path(), ObjectFormat::Sha1).unwrap();
+        let result =
+            odb.read(&ObjectId::from_hex("707a178071655bed661318a5344557fe3e9a6ce2").unwrap());
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn read_returns_loose_and_packed_objects_given_sha256_format() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let loose = "6f4e2a1c9d0b8e7f3a5c2d1e0f9b8a7c6d5e4f3a2b1c0d9e8f7a6b5c4d3e2f1a";
+        let base = "0c1d2e3f4a5b6c7d8e9f0a1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d6e7f8a9b0c1d";
+        let delta = "f1e2d3c4b5a69788796a5b4c3d2e1f0a9b8c7d6e5f4a3b2c1d0e9f8a7b6c5d4e";
+        write_loose_object(temp_dir.path(), loose, "blob", b"loose content");
+        write_pack(
+            temp_dir.path(),
+            "pack-1",
+            &[
+                (base, PackEntry::Full(PACK_BLOB, b"base content")),
+                (
+                    delta,
+                    PackEntry::RefDelta(base, append_delta(12, b" + ref")),
+                ),
+            ],
+        );
+
+        let odb = ObjectDb::open(temp_dir.path(), ObjectFormat::Sha256).unwrap();
+        let read = |id: &str| odb.read(&ObjectId::from_hex(id).unwrap()).unwrap();
+
+        assert_eq!(read(loose).data, b"loose content");
+        assert_eq!(read(base).data, b"base content");
+        assert_eq!(read(delta).data, b"base content + ref");
+    }
+
+    #[test]
+    fn read_finds_objects_in_alternates() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let objects_dir = temp_dir.path().join("objects");
+        let alternate_dir = temp_dir.path().join("shared").join("objects");
+        std::fs::create_dir_all(objects_dir.join("info")).unwrap();
+        std::fs::write(
+            objects_dir.join("info").join("alternates"),
+            "../shared/objects\n",
+        )
+        .unwrap();
+        let id = "e2bf29060f42743538be07c164820cdeca0d9d2b";
+        write_loose_object(&alternate_dir, id, "blob", b"shared");
+
+        let odb = ObjectDb::open(&objects_dir, ObjectFormat::Sha1).unwrap();
+        let object = odb.read(&ObjectId::from_hex(id).unwrap()).unwrap();
+
+        assert_eq!(object.data, b"shared");
+    }
+
+    /// Write a commit with the given (fake) ID and parents. Commits whose IDs
+    /// start with larger bytes are made later.
+    pub fn write_commit(objects_dir: &Path, id: &str, parents: &[&str]) {
+        let time = 1700000000 + i64::from(u8::from_str_radix(&id[..2], 16).unwrap());
+        let mut data = "tree 4b825dc642cb6eb9a060e54bf8d69288fbee4904\n".to_string();
+        for parent in parents {
+            data.push_str(&format!("parent {parent}\n"));
+        }
+        data.push_str(&format!(
+            "author A U Thor <author@example.com> {time} +0000\n"
+        ));
+        data.push_str(&format!(
+            "committer C O Mitter <committer@example.com> {time} +0000\n"
+        ));
+        data.push_str(&format!("\nCommit {id}\n"));
+        write_loose_object(objects_dir, id, "commit", data.as_bytes());
+    }
+
+    /// Hex IDs for fake commits, e.g., `commit_id(1)` gives `1111...`.
+    pub fn commit_id(n: u8) -> String {
+        format!("{n:x}").repeat(40)
+    }
+
+    #[test]
+    fn reachable_returns_whether_commits_are_ancestors() {
+        // 1 <- 2 <- 3 <- 5 (merge)
+        //       \<- 4 <-/
+        // 6 is not connected
+        let temp_dir = tempfile::tempdir().unwrap();
+        let odb_dir = temp_dir.path();
+        let id = |n| commit_id(n);
+        write_commit(odb_dir, &id(1), &[]);
+        write_commit(odb_dir, &id(2), &[&id(1)]);
+        write_commit(odb_dir, &id(3), &[&id(2)]);
+        write_commit(odb_dir, &id(4), &[&id(2)]);
+        write_commit(odb_dir, &id(5), &[&id(3), &id(4)]);
+        write_commit(odb_dir, &id(6), &[]);
+        let oid = |n| ObjectId::from_hex(&id(n)).unwrap();
+
+        let odb = ObjectDb::open(odb_dir, ObjectFormat::Sha1).unwrap();
+
+        assert_eq!(
+            odb.reachable(&oid(5), &[oid(1), oid(4), oid(5), oid(6)])
+                .unwrap(),
+            vec![true, true, true, false]
+        );
+        assert_eq!(
+            odb.reachable(&oid(3), &[oid(4), oid(2)]).unwrap(),
+            vec![false, true]
+        );
+    }
+
+    #[test]
+    fn ahead_behind_counts_commits_unique_to_each_side() {
+        // 1 <- 2 <- 3 <- 4 (commit)
+        //       \<- 5 <- 6 <- 7 (base)
+        let temp_dir = tempfile::tempdir().unwrap();
+        let odb_dir = temp_dir.path();
+        let id = |n| commit_id(n);
+        write_commit(odb_dir, &id(1), &[]);
+        write_commit(odb_dir, &id(2), &[&id(1)]);
+        write_commit(odb_dir, &id(3), &[&id(2)]);
+        write_commit(odb_dir, &id(4), &[&id(3)]);
+        write_commit(odb_dir, &id(5), &[&id(2)]);
+        write_commit(odb_dir, &id(6), &[&id(5)]);
+        write_commit(odb_dir, &id(7), &[&id(6)]);
+        let oid = |n| ObjectId::from_hex(&id(n)).unwrap();
+
+        let odb = ObjectDb::open(odb_dir, ObjectFormat::Sha1).unwrap();
+
+        assert_eq!(
+            odb.ahead_behind(&oid(4), &oid(7)).unwrap(),
+            AheadBehind {
+                ahead: 2,
+                behind: 3
+            }
+        );
+        assert_eq!(
+            odb.ahead_behind(&oid(7), &oid(2)).unwrap(),
+            AheadBehind {
+                ahead: 3,
+                behind: 0
+            }
+        );
+        assert_eq!(
+            odb.ahead_behind(&oid(4), &oid(4)).unwrap(),
+            AheadBehind::default()
+        );
+    }
+
+    #[test]
+    fn ahead_behind_counts_merged_commits_once() {
+        // 1 <- 2 <- 4 (merge, commit)
+        //  \<- 3 <-/
+        //        \<- 5 (base)
+        let temp_dir = tempfile::tempdir().unwrap();
+        let odb_dir = temp_dir.path();
+        let id = |n| commit_id(n);
+        write_commit(odb_dir, &id(1), &[]);
+        write_commit(odb_dir, &id(2), &[&id(1)]);
+        write_commit(odb_dir, &id(3), &[&id(1)]);
+        write_commit(odb_dir, &id(4), &[&id(2), &id(3)]);
+        write_commit(odb_dir, &id(5), &[&id(3)]);
+        let oid = |n| ObjectId::from_hex(&id(n)).unwrap();
+
+        let odb = ObjectDb::open(odb_dir, ObjectFormat::Sha1).unwrap();
+
+        assert_eq!(
+            odb.ahead_behind(&oid(4), &oid(5)).unwrap(),
+            AheadBehind {
+                ahead: 2,
+                behind: 1
+            }
+        );
+    }
+
+    #[test]
+    fn reachable_stops_at_common_ancestors() {
+        // 1 (corrupt) <- 2 <- 3 (from)
+        //                 \<- 4
+        let temp_dir = tempfile::tempdir().unwrap();
+        let odb_dir = temp_dir.path();
+        let id = |n| commit_id(n);
+        std::fs::create_dir_all(odb_dir.join(&id(1)[..2])).unwrap();
+        std::fs::write(odb_dir.join(&id(1)[..2]).join(&id(1)[2..]), b"not zlib").unwrap();
+        write_commit(odb_dir, &id(2), &[&id(1)]);
+        write_commit(odb_dir, &id(3), &[&id(2)]);
+        write_commit(odb_dir, &id(4), &[&id(2)]);
+        let oid = |n| ObjectId::from_hex(&id(n)).unwrap();
+
+        let odb = ObjectDb::open(odb_dir, ObjectFormat::Sha1).unwrap();
+
+        assert_eq!(odb.reachable(&oid(3), &[oid(4)]).unwrap(), vec![false]);
+    }
+
+    #[test]
+    fn reachable_stops_at_missing_parents() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        write_commit(temp_dir.path(), &commit_id(2), &[&commit_id(1)]);
+        let oid = |n| ObjectId::from_hex(&commit_id(n)).unwrap();
+
+        let odb = ObjectDb::open(temp_dir.path(), ObjectFormat::Sha1).unwrap();
+
+        assert_eq!(
+            odb.reachable(&oid(2), &[oid(1), oid(3)]).unwrap(),
+            vec![true, false]
+        );
+    }
+
+    #[test]
+    fn open_returns_err_given_truncated_pack_index() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let id = "707a178071655bed661318a5344557fe3e9a6ce1";
+        write_pack(
+            temp_dir.path(),
+            "pack-1",
+            &[(id, PackEntry::Full(PACK_BLOB, b"x"))],
+        );
+        let idx_path = temp_dir.path().join("pack").join("pack-1.idx");
+        let idx = std::fs::read(&idx_path).unwrap();
+        std::fs::write(&idx_path, &idx[..idx.len() - 30]).unwrap();
+
+        let result = ObjectDb::open(temp_dir.path(), ObjectFormat::Sha1);
+
+        assert!(result
+            .err()
+            .unwrap()
+            .to_string()
+            .contains("truncated index"));
+    }
+
+    #[test]
+    fn open_returns_err_given_pack_index_fanout_exceeds_object_count() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let id = "707a178071655bed661318a5344557fe3e9a6ce1";
+        write_pack(
+            temp_dir.path(),
+            "pack-1",
+            &[(id, PackEntry::Full(PACK_BLOB, b"x"))],
+        );
+        let idx_path = temp_dir.path().join("pack").join("pack-1.idx");
+        let mut idx = std::fs::read(&idx_path).unwrap();
+        // claim there are 1000 objects starting with 0x70, but only one in all
+        let entry = 8 + 0x70 * 4;
+        idx[entry..entry + 4].copy_from_slice(&1000u32.to_be_bytes());
+        std::fs::write(&idx_path, idx).unwrap();
+
+        let result = ObjectDb::open(temp_dir.path(), ObjectFormat::Sha1);
+
+        assert!(result
+            .err()
+            .unwrap()
+            .to_string()
+            .contains("corrupt fanout table"));
+    }
+
+    #[test]
+    fn find_commit_returns_none_given_missing_object() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let odb = ObjectDb::open(temp_dir.path(), ObjectFormat::Sha1).unwrap();
+        let commit = odb
+            .find_commit(&ObjectId::from_hex(&commit_id(1)).unwrap())
+            .unwrap();
+
+        assert!(commit.is_none());
+    }
+
+    #[test]
+    fn find_commit_returns_err_given_corrupt_object() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let id = commit_id(1);
+        std::fs::create_dir_all(temp_dir.path().join(&id[..2])).unwrap();
+        std::fs::write(temp_dir.path().join(&id[..2]).join(&id[2..]), b"not zlib").unwrap();
+
+        let odb = ObjectDb::open(temp_dir.path(), ObjectFormat::Sha1).unwrap();
+        let result = odb.find_commit(&ObjectId::from_hex(&id).unwrap());
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn find_peeled_commit_returns_none_given_tag_of_missing_object() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let tag = format!(
+            "object {}\ntype commit\ntag v1\ntagger A <a@b.c> 1 +0000\n\nmsg\n",
+            commit_id(1)
+        );
+        write_loose_object(temp_dir.path(), &commit_id(2), "tag", tag.as_bytes());
+
+        let odb = ObjectDb::open(temp_dir.path(), ObjectFormat::Sha1).unwrap();
+        let peeled = odb
+            .find_peeled_commit(&ObjectId::from_hex(&commit_id(2)).unwrap())
+            .unwrap();
+
+        assert!(peeled.is_none());
+    }
+
+    #[test]
+    fn resolve_abbrev_finds_loose_and_packed_objects() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let loose = "e2bf29060f42743538be07c164820cdeca0d9d2b";
+        let packed = "707a178071655bed661318a5344557fe3e9a6ce1";
+        write_loose_object(temp_dir.path(), loose, "blob", b"x");
+        write_pack(
+            temp_dir.path(),
+            "pack-1",
+            &[(packed, PackEntry::Full(PACK_BLOB, b"y"))],
+        );
+
+        let odb = ObjectDb::open(temp_dir.path(), ObjectFormat::Sha1).unwrap();
+        let resolve = |hex| odb.resolve_abbrev(hex).unwrap().map(|id| id.to_string());
+
+        assert_eq!(resolve("e2bf2906").as_deref(), Some(loose));
+        assert_eq!(resolve("E2BF290").as_deref(), Some(loose));
+        assert_eq!(resolve("707a1").as_deref(), Some(packed));
+        assert_eq!(resolve(packed).as_deref(), Some(packed));
+        assert_eq!(resolve("707a2"), None);
+        assert_eq!(resolve("707"), None);
+        assert_eq!(resolve("+07a"), None);
+    }
+
+    #[test]
+    fn resolve_abbrev_returns_err_given_ambiguous_prefix() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        write_loose_object(
+            temp_dir.path(),
+            "707a178071655bed661318a5344557fe3e9a6ce1",
+            "blob",
+            b"x",
+        );
+        write_pack(
+            temp_dir.path(),
+            "pack-1",
+            &[(
+                "707a278071655bed661318a5344557fe3e9a6ce1",
+                PackEntry::Full(PACK_BLOB, b"y"),
+            )],
+        );
+
+        let odb = ObjectDb::open(temp_dir.path(), ObjectFormat::Sha1).unwrap();
+
+        assert!(odb
+            .resolve_abbrev("707a")
+            .unwrap_err()
+            .to_string()
+            .contains("ambiguous"));
+        assert!(odb.resolve_abbrev("707a1").unwrap().is_some());
+    }
+
+    #[test]
+    fn peel_to_commit_follows_annotated_tags() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        write_commit(temp_dir.path(), &commit_id(1), &[]);
+        let tag = format!(
+            "object {}\ntype commit\ntag v1\ntagger A <a@b.c> 1 +0000\n\nmsg\n",
+            commit_id(1)
+        );
+        write_loose_object(temp_dir.path(), &commit_id(2), "tag", tag.as_bytes());
+
+        let odb = ObjectDb::open(temp_dir.path(), ObjectFormat::Sha1).unwrap();
+        let peeled = odb
+            .peel_to_commit(&ObjectId::from_hex(&commit_id(2)).unwrap())
+            .unwrap();
+
+        assert_eq!(peeled.to_string(), commit_id(1));
+    }
+
+    #[test]
+    fn peel_to_commit_returns_err_given_blob() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        write_loose_object(temp_dir.path(), &commit_id(1), "blob", b"x");
+
+        let odb = ObjectDb::open(temp_dir.path(), ObjectFormat::Sha1).unwrap();
+        let result = odb.peel_to_commit(&ObjectId::from_hex(&commit_id(1)).unwrap());
+
+        assert!(result.unwrap_err().to_string().contains("not a commit"));
+    }
+
+    #[test]
+    fn apply_delta_returns_err_given_base_size_mismatch() {
+        let delta = append_delta(10, b"x");
+
+        assert!(apply_delta(b"short", &delta).is_err());
+    }
+
+    #[test]
+    fn apply_delta_returns_err_given_oversized_result_size() {
+        // a base size of 5, a result size of 2^63 - 1, then insert "x"
+        let mut delta = vec![5];
+        delta.extend([0xff; 8]);
+        delta.extend([0x7f, 1, b'x']);
+
+        let result = apply_delta(b"short", &delta);
+
+        assert!(result.unwrap_err().to_string().contains("size mismatch"));
+    }
+
+    #[test]
+    fn apply_delta_returns_err_given_over_long_size() {
+        let mut delta = vec![5];
+        delta.extend([0xff; 10]);
+        delta.push(1);
+
+        let result = apply_delta(b"short", &delta);
+
+        assert!(result.unwrap_err().to_string().contains("too large"));
+    }
+
+    #[test]
+    fn read_pack_entry_header_returns_err_given_over_long_size() {
+        let header = [0xff; 12];
+
+        let result = read_pack_entry_header(&mut &header[..]);
+
+        assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn read_ofs_delta_offset_returns_err_given_over_long_offset() {
+        let offset = [0xff; 12];
+
+        let result = read_ofs_delta_offset(&mut &offset[..]);
+
+        assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn read_returns_err_given_packed_object_with_oversized_size() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let id = "707a178071655bed661318a5344557fe3e9a6ce1";
+        write_pack(
+            temp_dir.path(),
+            "pack-1",
+            &[(id, PackEntry::Full(PACK_BLOB, b"x"))],
+        );
+        let pack_path = temp_dir.path().join("pack").join("pack-1.pack");
+        let mut pack = std::fs::read(&pack_path).unwrap();
+        // make the blob's header claim a size of nearly 2^60 bytes
+        let mut header = vec![0x80 | (PACK_BLOB << 4) | 1];
+        header.extend([0xff; 7]);
+        header.push(0x7f);
+        pack.splice(12..13, header);
+        std::fs::write(&pack_path, pack).unwrap();
+
+        let odb = ObjectDb::open(temp_dir.path(), ObjectFormat::Sha1).unwrap();
+        let result = odb.read(&ObjectId::from_hex(id).unwrap());
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,510 @@
+//! A reader for the reftable ref storage format.
+//!
+//! A reftable repository keeps its refs in a stack of tables in
+//! `.git/reftable/`, listed oldest first in `tables.list`. Each table is a
+//! sorted sequence of blocks; only the ref blocks are read here, the index,
+//! object and log blocks that may follow them are skipped.
+//!
+//! See https://git-scm.com/docs/reftable for the format.
+
+use super::{RefValue, Refs};
+use crate::git::object::{ObjectId, SHA1_LEN, SHA256_LEN};
+use crate::Error;
+
+use std::path::Path;
+
+const REFTABLE_DIR: &str = "reftable";
+const TABLES_LIST_FILE: &str = "tables.list";
+const MAGIC: &[u8] = b"REFT";
+const HEADER_LEN_V1: usize = 24;
+const HEADER_LEN_V2: usize = 28;
+const FOOTER_LEN_V1: usize = 68;
+const FOOTER_LEN_V2: usize = 72;
+const BLOCK_TYPE_REF: u8 = b'r';
+const VALUE_DELETION: u8 = 0;
+const VALUE_ONE_ID: u8 = 1;
+const VALUE_TWO_IDS: u8 = 2;
+const VALUE_SYMREF: u8 = 3;
+
+/// A record from a single table. A `None` value is a deletion (a tombstone)
+/// hiding the ref in older tables.
+type RefRecord = (Vec<u8>, Option<RefValue>);
+
+/// Read and merge the tables of the reftable stack in the given git dir.
+/// Returns `None` if the directory does not use reftable storage.
+pub fn read_stack(git_dir: &Path) -> Result<Option<Refs>, Error> {
+    let reftable_dir = git_dir.join(REFTABLE_DIR);
+    let list_file = reftable_dir.join(TABLES_LIST_FILE);
+    if !list_file.is_file() {
+        return Ok(None);
+    }
+    let list = std::fs::read_to_string(&list_file).map_err(|e| {
+        Error::Git(format!(
+            "could not read '{}': {e}",
+            list_file.to_string_lossy()
+        ))
+    })?;
+    let mut refs = Refs::new();
+    // tables are listed oldest first, so newer records replace older ones
+    for table_name in list.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let table_file = reftable_dir.join(table_name);
+        let data = std::fs::read(&table_file).map_err(|e| {
+            Error::Git(format!(
+                "could not read '{}': {e}",
+                table_file.to_string_lossy()
+            ))
+        })?;
+        let records = parse_table(&data).map_err(|e| {
+            Error::Git(format!(
+                "could not parse reftable '{}': {e}",
+                table_file.to_string_lossy()
+            ))
+        })?;
+        for (name, value) in records {
+            match value {
+                Some(value) => refs.insert(name, value),
+                None => refs.remove(&name),
+            };
+        }
+    }
+    Ok(Some(refs))
+}
+
+/// Parse the ref records of a single table.
+fn parse_table(data: &[u8]) -> Result<Vec<RefRecord>, String> {
+    if !data.starts_with(MAGIC) || data.len() < HEADER_LEN_V1 {
+        return Err("not a reftable".to_string());
+    }
+    let (header_len, footer_len, hash_len) = match data[4] {
+        1 => (HEADER_LEN_V1, FOOTER_LEN_V1, SHA1_LEN),
+        2 => {
+            let hash_len = match data.get(HEADER_LEN_V1..HEADER_LEN_V2) {
+                Some(b"sha1") => SHA1_LEN,
+                Some(b"s256") => SHA256_LEN,
+                _ => return Err("unknown hash function".to_string()),
+            };
+            (HEADER_LEN_V2, FOOTER_LEN_V2, hash_len)
+        }
+        version => return Err(format!("unsupported version {version}")),
+    };
+    let block_size = read_u24(data, 5)?;
+    let blocks_end = data
+        .len()
+        .checked_sub(footer_len)
+        .filter(|end| *end >= header_len)
+        .ok_or("table is truncated")?;
+
+    let mut records = vec![];
+    let mut block_start = 0;
+    // the first block shares its start with the file header
+    let mut block_header_len = header_len;
+    while block_start + block_header_len < blocks_end {
+        let type_pos = block_start + block_header_len;
+        if data[type_pos] != BLOCK_TYPE_REF {
+            break;
+        }
+        let block_len = read_u24(data, type_pos + 1)?;
+        let block_end = block_start
+            .checked_add(block_len)
+            .filter(|end| *end <= blocks_end && *end >= type_pos + 4)
+            .ok_or("invalid block length")?;
+        parse_ref_block(
+            &data[block_start..block_end],
+            block_header_len + 4,
+            hash_len,
+            &mut records,
+        )?;
+        // blocks in an aligned table are padded with zeros up to the block
+        // size, whereas in an unaligned table the next block follows directly
+        block_start = if block_size > block_len && data.get(block_end) == Some(&0) {
+            block_start + block_size
+        } else {
+            block_end
+        };
+        block_header_len = 0;
+    }
+    Ok(records)
+}
+
+/// Parse the records of a ref block, starting at `records_start`. Each record
+/// stores its name as a prefix shared with the previous record's name plus a
+/// suffix.
+fn parse_ref_block(
+    block: &[u8],
+    records_start: usize,
+    hash_len: usize,
+    records: &mut Vec<RefRecord>,
+) -> Result<(), String> {
+    let restart_count = match block.get(block.len().saturating_sub(2)..) {
+        Some([high, low]) => usize::from(u16::from_be_bytes([*high, *low])),
+        _ => return Err("block is truncated".to_string()),
+    };
+    let records_end = (block.len() - 2)
+        .checked_sub(3 * restart_count)
+        .filter(|end| *end >= records_start)
+        .ok_or("invalid restart count")?;
+    let mut pos = records_start;
+    let mut name: Vec<u8> = vec![];
+    while pos < records_end {
+        let prefix_len = read_varint(block, &mut pos)? as usize;
+        let suffix_and_type = read_varint(block, &mut pos)?;
+        let suffix_len = (suffix_and_type >> 3) as usize;
+        let value_type = (suffix_and_type & 0x7) as u8;
+        if prefix_len > name.len() {
+            return Err("invalid name prefix length".to_string());
+        }
+        name.truncate(prefix_len);
+        name.extend_from_slice(take(block, &mut pos, suffix_len)?);
+        // the update index is only needed to order log records
+        read_varint(block, &mut pos)?;
+        let value = match value_type {
+            VALUE_DELETION => None,
+            VALUE_ONE_ID | VALUE_TWO_IDS => {
+                let target = ObjectId::from_bytes(take(block, &mut pos, hash_len)?);
+                let peeled = if value_type == VALUE_TWO_IDS {
+                    Some(ObjectId::from_bytes(take(block, &mut pos, hash_len)?))
+                } else {
+                    None
+                };
+                Some(RefValue::Object { target, peeled })
+            }
+            VALUE_SYMREF => {
+                let target_len = read_varint(block, &mut pos)? as usize;
+                let target = take(block, &mut pos, target_len)?;
+                Some(RefValue::Symref(
+                    String::from_utf8_lossy(target).to_string(),
+                ))
+            }
+            _ => return Err(format!("invalid ref value type {value_type}")),
+        };
+        records.push((name.clone(), value));
+    }
+    Ok(())
+}
+
+fn take<'a>(data: &'a [u8], pos: &mut usize, len: usize) -> Result<&'a [u8], String> {
+    // lengths are read from the table, so may be corrupt
+    let bytes = pos
+        .checked_add(len)
+        .and_then(|end| data.get(*pos..end))
+        .ok_or("record is truncated")?;
+    *pos += len;
+    Ok(bytes)
+}
+
+fn read_u24(data: &[u8], pos: usize) -> Result<usize, String> {
+    match pos.checked_add(3).and_then(|end| data.get(pos..end)) {
+        Some([a, b, c]) => Ok(usize::from(*a) << 16 | usize::from(*b) << 8 | usize::from(*c)),
+        _ => Err("table is truncated".to_string()),
+    }
+}
+
+/// Read a variable length integer, encoded in the same way as the offsets of
+/// deltas in pack files.
+fn read_varint(data: &[u8], pos: &mut usize) -> Result<u64, String> {
+    let mut byte = *data.get(*pos).ok_or("record is truncated")?;
+    *pos += 1;
+    let mut value = u64::from(byte & 0x7f);
+    while byte & 0x80 != 0 {
+        byte = *data.get(*pos).ok_or("record is truncated")?;
+        *pos += 1;
+        value = value
+            .checked_add(1)
+            .and_then(|v| v.checked_mul(1 << 7))
+            .ok_or("varint overflows")?
+            | u64::from(byte & 0x7f);
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    /// A ref record to write to a test table.
+    pub enum TestRecord<'a> {
+        Object(&'a str, &'a str),
+        Peeled(&'a str, &'a str, &'a str),
+        Symref(&'a str, &'a str),
+        Deletion(&'a str),
+    }
+
+    impl TestRecord<'_> {
+        fn name(&self) -> &str {
+            match self {
+                TestRecord::Object(name, _)
+                | TestRecord::Peeled(name, _, _)
+                | TestRecord::Symref(name, _)
+                | TestRecord::Deletion(name) => name,
+            }
+        }
+    }
+
+    fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+        let mut bytes = vec![(value & 0x7f) as u8];
+        value >>= 7;
+        while value != 0 {
+            value -= 1;
+            bytes.push(0x80 | (value & 0x7f) as u8);
+            value >>= 7;
+        }
+        out.extend(bytes.iter().rev());
+    }
+
+    fn write_record(out: &mut Vec<u8>, record: &TestRecord, previous_name: &str) -> usize {
+        let name = record.name().as_bytes();
+        let prefix_len = name
+            .iter()
+            .zip(previous_name.as_bytes())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let (value_type, value) = match record {
+            TestRecord::Object(_, id) => (
+                VALUE_ONE_ID,
+                ObjectId::from_hex(id).unwrap().as_bytes().to_vec(),
+            ),
+            TestRecord::Peeled(_, id, peeled) => {
+                let mut value = ObjectId::from_hex(id).unwrap().as_bytes().to_vec();
+                value.extend_from_slice(ObjectId::from_hex(peeled).unwrap().as_bytes());
+                (VALUE_TWO_IDS, value)
+            }
+            TestRecord::Symref(_, target) => {
+                let mut value = vec![];
+                write_varint(&mut value, target.len() as u64);
+                value.extend_from_slice(target.as_bytes());
+                (VALUE_SYMREF, value)
+            }
+            TestRecord::Deletion(_) => (VALUE_DELETION, vec![]),
+        };
+        write_varint(out, prefix_len as u64);
+        write_varint(
+            out,
+            ((name.len() - prefix_len) as u64) << 3 | u64::from(value_type),
+        );
+        out.extend_from_slice(&name[prefix_len..]);
+        write_varint(out, 0);
+        out.extend(value);
+        prefix_len
+    }
+
+    /// Build a version 1 table holding the given records (which must be
+    /// sorted by name), with `records_per_block` records in each ref block.
+    /// Blocks are padded to `block_size` if it is non-zero.
+    pub fn build_table(
+        records: &[TestRecord],
+        records_per_block: usize,
+        block_size: usize,
+    ) -> Vec<u8> {
+        let mut header = MAGIC.to_vec();
+        header.push(1);
+        header.extend_from_slice(&(block_size as u32).to_be_bytes()[1..]);
+        header.extend_from_slice(&1u64.to_be_bytes());
+        header.extend_from_slice(&1u64.to_be_bytes());
+
+        let mut data = header.clone();
+        for (i, chunk) in records.chunks(records_per_block.max(1)).enumerate() {
+            let block_start = if i == 0 { 0 } else { data.len() };
+            data.push(BLOCK_TYPE_REF);
+            let len_pos = data.len();
+            data.extend_from_slice(&[0, 0, 0]);
+            let mut restarts = vec![];
+            let mut previous_name = "";
+            for record in chunk {
+                let record_start = data.len() - block_start;
+                if write_record(&mut data, record, previous_name) == 0 {
+                    restarts.push(record_start);
+                }
+                previous_name = record.name();
+            }
+            for restart in &restarts {
+                data.extend_from_slice(&(*restart as u32).to_be_bytes()[1..]);
+            }
+            data.extend_from_slice(&(restarts.len() as u16).to_be_bytes());
+            let block_len = data.len() - block_start;
+            data[len_pos..len_pos + 3].copy_from_slice(&(block_len as u32).to_be_bytes()[1..]);
+            if block_size > 0 {
+                data.resize(block_start + block_size, 0);
+            }
+        }
+        // the footer's block offsets are not read, so are left as zero
+        data.extend_from_slice(&header);
+        data.extend_from_slice(&[0; FOOTER_LEN_V1 - HEADER_LEN_V1]);
+        data
+    }
+
+    /// Write a reftable stack of the given tables, oldest first, into
+    /// `git_dir`.
+    pub fn write_stack(git_dir: &Path, tables: &[Vec<u8>]) {
+        let reftable_dir = git_dir.join(REFTABLE_DIR);
+        std::fs::create_dir_all(&reftable_dir).unwrap();
+        let mut list = String::new();
+        for (i, table) in tables.iter().enumerate() {
+            let name = format!("0x{:012x}-0x{:012x}-{i:08x}.ref", i + 1, i + 1);
+            std::fs::write(reftable_dir.join(&name), table).unwrap();
+            list.push_str(&format!("{name}\n"));
+        }
+        std::fs::write(reftable_dir.join(TABLES_LIST_FILE), list).unwrap();
+    }
+
+    const ID_A: &str = "e2bf29060f42743538be07c164820cdeca0d9d2b";
+    const ID_B: &str = "d9f35e0d4c0b0c6e4ea0d8dc7e2fbc83c4cfa8f7";
+    const ID_C: &str = "3ab03e1a4cd3ba9e0bd4dfea6fa0b4e71ec0c7b6";
+
+    fn object(hex: &str) -> RefValue {
+        RefValue::Object {
+            target: ObjectId::from_hex(hex).unwrap(),
+            peeled: None,
+        }
+    }
+
+    #[test]
+    fn read_stack_returns_none_given_no_tables_list() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+
+        let refs = read_stack(tmp_dir.path()).unwrap();
+
+        assert_eq!(refs, None);
+    }
+
+    #[test]
+    fn read_stack_returns_refs_of_single_table() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let table = build_table(
+            &[
+                TestRecord::Symref("HEAD", "refs/heads/main"),
+                TestRecord::Object("refs/heads/feature", ID_A),
+                TestRecord::Object("refs/heads/main", ID_B),
+                TestRecord::Peeled("refs/tags/v1.0", ID_C, ID_A),
+            ],
+            16,
+            4096,
+        );
+        write_stack(tmp_dir.path(), &[table]);
+
+        let refs = read_stack(tmp_dir.path()).unwrap().unwrap();
+
+        assert_eq!(
+            refs,
+            Refs::from([
+                (
+                    b"HEAD".to_vec(),
+                    RefValue::Symref("refs/heads/main".to_string())
+                ),
+                (b"refs/heads/feature".to_vec(), object(ID_A)),
+                (b"refs/heads/main".to_vec(), object(ID_B)),
+                (
+                    b"refs/tags/v1.0".to_vec(),
+                    RefValue::Object {
+                        target: ObjectId::from_hex(ID_C).unwrap(),
+                        peeled: ObjectId::from_hex(ID_A),
+                    }
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn read_stack_reads_all_blocks_given_aligned_and_unaligned_tables() {
+        let records: Vec<String> = (0..20)
+            .map(|i| format!("refs/heads/branch-{i:02}"))
+            .collect();
+        let test_records: Vec<TestRecord> = records
+            .iter()
+            .map(|name| TestRecord::Object(name, ID_A))
+            .collect();
+
+        for block_size in [0, 256] {
+            let tmp_dir = tempfile::tempdir().unwrap();
+            write_stack(tmp_dir.path(), &[build_table(&test_records, 3, block_size)]);
+
+            let refs = read_stack(tmp_dir.path()).unwrap().unwrap();
+
+            assert!(refs.keys().eq(records.iter().map(|r| r.as_bytes())));
+        }
+    }
+
+    #[test]
+    fn read_stack_prefers_newer_tables_and_removes_deleted_refs() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let old = build_table(
+            &[
+                TestRecord::Object("refs/heads/deleted", ID_A),
+                TestRecord::Object("refs/heads/main", ID_A),
+                TestRecord::Object("refs/heads/unchanged", ID_A),
+            ],
+            16,
+            0,
+        );
+        let new = build_table(
+            &[
+                TestRecord::Deletion("refs/heads/deleted"),
+                TestRecord::Object("refs/heads/main", ID_B),
+                TestRecord::Object("refs/remotes/origin/main", ID_C),
+            ],
+            16,
+            0,
+        );
+        write_stack(tmp_dir.path(), &[old, new]);
+
+        let refs = read_stack(tmp_dir.path()).unwrap().unwrap();
+
+        assert_eq!(
+            refs,
+            Refs::from([
+                (b"refs/heads/main".to_vec(), object(ID_B)),
+                (b"refs/heads/unchanged".to_vec(), object(ID_A)),
+                (b"refs/remotes/origin/main".to_vec(), object(ID_C)),
+            ])
+        );
+    }
+
+    #[test]
+    fn read_stack_returns_err_given_invalid_table() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        write_stack(tmp_dir.path(), &[b"not a table".to_vec()]);
+
+        let result = read_stack(tmp_dir.path());
+
+        assert!(matches!(result, Err(Error::Git(e)) if e.contains("not a reftable")));
+    }
+
+    #[test]
+    fn take_returns_err_given_length_overflows() {
+        let mut pos = 1;
+
+        let result = take(&[1, 2, 3], &mut pos, usize::MAX);
+
+        assert_eq!(result, Err("record is truncated".to_string()));
+        assert_eq!(pos, 1);
+    }
+
+    #[test]
+    fn parse_ref_block_returns_err_given_symref_target_length_overflows() {
+        let mut block = vec![0, (1 << 3) | VALUE_SYMREF, b'a', 0];
+        write_varint(&mut block, u64::MAX >> 1);
+        block.extend([0, 0]);
+
+        let result = parse_ref_block(&block, 0, 20, &mut vec![]);
+
+        assert_eq!(result, Err("record is truncated".to_string()));
+    }
+
+    #[test]
+    fn read_u24_returns_err_given_position_overflows() {
+        let result = read_u24(&[1, 2, 3], usize::MAX - 1);
+
+        assert_eq!(result, Err("table is truncated".to_string()));
+    }
+
+    #[test]
+    fn read_varint_decodes_multi_byte_values() {
+        for value in [0, 127, 128, 16511, 16512, 1 << 40] {
+            let mut data = vec![];
+            write_varint(&mut data, value);
+            let mut pos = 0;
+
+            assert_eq!(read_varint(&data, &mut pos), Ok(value));
+            assert_eq!(pos, data.len());
+        }
+    }
+}
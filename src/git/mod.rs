@@ -1,32 +1,328 @@
+pub mod cli;
+pub mod config;
 pub mod fs;
+pub mod object;
 
 use std::fmt::Display;
 
+use serde::{Deserialize, Serialize};
+
 use crate::Error;
 
-#[derive(Debug, Clone, PartialEq, Copy)]
+#[derive(Debug, Clone, PartialEq, Copy, Default)]
 pub enum BranchType {
+    #[default]
     Local,
     Remote,
+    Tag,
+    /// An entry in the stash, named like `stash@{0}`.
+    Stash,
+}
+
+/// How branches are read from the repository.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// Read the repository's files directly.
+    #[default]
+    Fs,
+    /// Run the `git` command.
+    Git,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+/// How remote-tracking branches are written when printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum RemoteFormat {
+    /// With the `remotes/` prefix, e.g., `remotes/origin/main`.
+    #[default]
+    Full,
+    /// Without the `remotes/` prefix, e.g., `origin/main`.
+    Short,
+    /// Only the name of the branch on the remote, e.g., `main`.
+    Branch,
+}
+
+/// How many commits a branch has that another does not (ahead), and vice
+/// versa (behind).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AheadBehind {
+    pub ahead: usize,
+    pub behind: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct Branch {
     pub name: String,
+    /// The raw bytes of the branch's name, if it is not valid UTF-8. `name`
+    /// then holds a lossy copy, for display and filtering only.
+    pub raw_name: Option<Vec<u8>>,
     pub branch_type: BranchType,
+    /// The remote a remote-tracking branch belongs to, e.g., `origin`. The
+    /// branch's name starts with this, followed by a `/`.
+    pub remote: Option<String>,
+    /// The full name of the ref a symbolic ref points to, e.g.,
+    /// `refs/remotes/origin/main` for `origin/HEAD`. The target (and commit)
+    /// of a symbolic ref are those of the ref it points to.
+    pub symref: Option<String>,
+    /// Whether this is the branch `HEAD` points to, i.e., the branch checked
+    /// out in the current worktree.
+    pub is_head: bool,
+    /// The path of another worktree this branch is checked out in, if any.
+    pub worktree: Option<std::path::PathBuf>,
+    /// The full name of the ref this branch tracks, e.g.,
+    /// `refs/remotes/origin/main`.
+    pub upstream: Option<String>,
+    /// Whether the branch has an upstream configured that no longer exists.
+    pub upstream_gone: bool,
+    /// The ID of the object the branch's ref points to.
+    pub target: Option<object::ObjectId>,
+    /// The commit an annotated tag points to. This is `None` for branches and
+    /// lightweight tags, whose target is already a commit.
+    pub peeled: Option<object::ObjectId>,
+    /// The commit at the tip of the branch, if it could be read.
+    pub commit: Option<object::Commit>,
+    /// How far the branch is ahead of and behind its upstream (or the base
+    /// given by `--base`).
+    pub ahead_behind: Option<AheadBehind>,
+    /// The message of a stash entry, e.g., `WIP on main: e2bf290 Add a
+    /// feature`.
+    pub message: Option<String>,
+    /// The description of a local branch, as set by `git branch
+    /// --edit-description` and stored in `branch.<name>.description`.
+    pub description: Option<String>,
+    /// Whether the branch matches a protected pattern, so must not be
+    /// selected.
+    pub protected: bool,
+}
+
+impl Branch {
+    /// Set the branch's name from raw bytes, which git does not require to be
+    /// valid UTF-8.
+    pub fn set_name(&mut self, name: &[u8]) {
+        match std::str::from_utf8(name) {
+            Ok(name) => {
+                self.name = name.to_string();
+                self.raw_name = None;
+            }
+            Err(_) => {
+                self.name = String::from_utf8_lossy(name).to_string();
+                self.raw_name = Some(name.to_vec());
+            }
+        }
+    }
+
+    /// The full name of the branch's ref, e.g., `refs/remotes/origin/main`.
+    pub fn ref_name(&self) -> String {
+        match self.branch_type {
+            BranchType::Local => format!("refs/heads/{}", self.name),
+            BranchType::Remote => format!("refs/remotes/{}", self.name),
+            BranchType::Tag => format!("refs/tags/{}", self.name),
+            BranchType::Stash => format!("refs/{}", self.name),
+        }
+    }
+
+    /// The position of a stash entry in the stash, e.g., `2` for
+    /// `stash@{2}`. This is `None` for branches and tags.
+    pub fn stash_index(&self) -> Option<usize> {
+        if self.branch_type != BranchType::Stash {
+            return None;
+        }
+        self.name
+            .strip_prefix("stash@{")?
+            .strip_suffix('}')?
+            .parse()
+            .ok()
+    }
+
+    /// The ID of the commit at the tip of the branch, peeling annotated tags.
+    pub fn tip(&self) -> Option<&object::ObjectId> {
+        self.peeled.as_ref().or(self.target.as_ref())
+    }
+
+    /// The name of a remote-tracking branch on its remote, e.g., `main` for
+    /// `origin/main`. This is the same as `name` for other branches.
+    pub fn branch_name(&self) -> &str {
+        self.remote
+            .as_deref()
+            .and_then(|remote| self.name.strip_prefix(remote))
+            .and_then(|name| name.strip_prefix('/'))
+            .unwrap_or(&self.name)
+    }
+
+    /// Display the branch, writing remote-tracking branches in the given
+    /// format.
+    pub fn display(&self, remote_format: RemoteFormat) -> BranchDisplay<'_> {
+        BranchDisplay {
+            branch: self,
+            remote_format,
+        }
+    }
+
+    /// The short name of the branch's upstream, e.g., `origin/main`.
+    pub fn upstream_name(&self) -> Option<&str> {
+        self.upstream.as_deref().map(short_ref_name)
+    }
+
+    /// The short name of the ref a symbolic ref points to, e.g., `origin/main`.
+    pub fn symref_name(&self) -> Option<&str> {
+        self.symref.as_deref().map(short_ref_name)
+    }
+}
+
+/// Strip the `refs/remotes/`, `refs/heads/` or `refs/tags/` prefix from a ref
+/// name, as git does when showing an upstream or a symbolic ref's target.
+fn short_ref_name(ref_name: &str) -> &str {
+    ["refs/remotes/", "refs/heads/", "refs/tags/"]
+        .iter()
+        .find_map(|prefix| ref_name.strip_prefix(prefix))
+        .unwrap_or(ref_name)
 }
 
 impl Display for Branch {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if self.branch_type == BranchType::Remote {
-            return write!(f, "remotes/{}", self.name);
+        self.display(RemoteFormat::Full).fmt(f)
+    }
+}
+
+/// Displays a branch with remote-tracking branches in a chosen format. See
+/// `Branch::display`.
+pub struct BranchDisplay<'a> {
+    branch: &'a Branch,
+    remote_format: RemoteFormat,
+}
+
+impl BranchDisplay<'_> {
+    /// The bytes of the displayed branch. Unlike `to_string`, this keeps the
+    /// raw bytes of names that are not valid UTF-8, so git can resolve them.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let branch = self.branch;
+        let Some(raw_name) = &branch.raw_name else {
+            return self.to_string().into_bytes();
+        };
+        let (prefix, name): (&[u8], &[u8]) = match (branch.branch_type, self.remote_format) {
+            (BranchType::Local | BranchType::Stash, _)
+            | (BranchType::Remote, RemoteFormat::Short) => (b"", raw_name),
+            (BranchType::Remote, RemoteFormat::Full) => (b"remotes/", raw_name),
+            (BranchType::Remote, RemoteFormat::Branch) => (
+                b"",
+                branch
+                    .remote
+                    .as_deref()
+                    .and_then(|remote| raw_name.strip_prefix(remote.as_bytes()))
+                    .and_then(|name| name.strip_prefix(b"/"))
+                    .unwrap_or(raw_name),
+            ),
+            (BranchType::Tag, _) => (b"tags/", raw_name),
+        };
+        [prefix, name].concat()
+    }
+}
+
+impl Display for BranchDisplay<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let branch = self.branch;
+        match (branch.branch_type, self.remote_format) {
+            (BranchType::Local | BranchType::Stash, _) => write!(f, "{}", branch.name),
+            (BranchType::Remote, RemoteFormat::Full) => write!(f, "remotes/{}", branch.name),
+            (BranchType::Remote, RemoteFormat::Short) => write!(f, "{}", branch.name),
+            (BranchType::Remote, RemoteFormat::Branch) => write!(f, "{}", branch.branch_name()),
+            (BranchType::Tag, _) => write!(f, "tags/{}", branch.name),
         }
-        write!(f, "{}", self.name)
     }
 }
 
+/// Set the remote of each remote-tracking branch from the names of the
+/// configured remotes. As remote names can contain `/`, the longest remote
+/// the branch's name starts with is used. Branches that belong to no
+/// configured remote are assumed to belong to the first part of their name.
+pub fn assign_remotes(branches: &mut [Branch], remotes: &[&str]) {
+    for branch in branches
+        .iter_mut()
+        .filter(|b| b.branch_type == BranchType::Remote)
+    {
+        let configured = remotes
+            .iter()
+            .filter(|remote| {
+                branch
+                    .name
+                    .strip_prefix(**remote)
+                    .is_some_and(|rest| rest.starts_with('/'))
+            })
+            .max_by_key(|remote| remote.len());
+        branch.remote = match configured {
+            Some(remote) => Some(remote.to_string()),
+            None => branch.name.split_once('/').map(|(r, _)| r.to_string()),
+        };
+    }
+}
+
+/// Gets the entries of a repository's stash.
+pub trait StashGetter {
+    /// The stash entries, newest (`stash@{0}`) first, as branches of type
+    /// `BranchType::Stash`.
+    fn stashes(&self) -> Result<Vec<Branch>, Error>;
+}
+
+/// Reads settings from a repository's git config.
+pub trait ConfigGetter {
+    /// All the values set for `<section>.<key>`, in the order they are set.
+    fn config_values(&self, section: &str, key: &str) -> Result<Vec<String>, Error>;
+}
+
+/// Gets the branches recently checked out in a repository.
+pub trait RecentGetter {
+    /// The names of the local branches HEAD has been switched to or from,
+    /// most recently visited first and without duplicates. The branches may
+    /// no longer exist.
+    fn recent_branches(&self) -> Result<Vec<String>, Error>;
+}
+
+/// Find the branches visited by the checkouts recorded in HEAD's reflog,
+/// given the messages of its entries newest first. A checkout is recorded
+/// as `checkout: moving from <old> to <new>`, where either side may be a
+/// commit rather than a branch if HEAD was detached.
+pub fn recent_checkouts<'a>(messages: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut recent: Vec<String> = vec![];
+    for message in messages {
+        let Some(moves) = message.strip_prefix("checkout: moving from ") else {
+            continue;
+        };
+        let Some((from, to)) = moves.rsplit_once(" to ") else {
+            continue;
+        };
+        for name in [to, from] {
+            if !recent.iter().any(|r| r == name) {
+                recent.push(name.to_string());
+            }
+        }
+    }
+    recent
+}
+
 pub trait BranchGetter {
-    fn branches(&self) -> Result<Vec<Branch>, Error>;
+    /// The repository's branches, remote-tracking branches and tags. Refs
+    /// that are skipped, e.g., as they are malformed, are described in
+    /// `warnings` rather than failing the whole listing.
+    fn branches(&self, warnings: &mut Vec<String>) -> Result<Vec<Branch>, Error>;
+
+    /// Resolve a revision (e.g., `HEAD`, a branch or tag name, or a full
+    /// object ID) to the commit it refers to.
+    fn resolve_rev(&self, rev: &str) -> Result<object::ObjectId, Error>;
+
+    /// Which of `commits` are reachable from `from`, i.e., are `from` or one
+    /// of its ancestors.
+    fn reachable(
+        &self,
+        from: &object::ObjectId,
+        commits: &[object::ObjectId],
+    ) -> Result<Vec<bool>, Error>;
+
+    /// For each `(commit, base)` pair, count the commits reachable from
+    /// `commit` but not from `base`, and vice versa.
+    fn ahead_behind(
+        &self,
+        pairs: &[(object::ObjectId, object::ObjectId)],
+    ) -> Result<Vec<AheadBehind>, Error>;
 }
 
 #[cfg(test)]
@@ -38,6 +334,7 @@ mod tests {
         let branch = Branch {
             name: "some_name".to_string(),
             branch_type: BranchType::Remote,
+            ..Default::default()
         };
 
         let branch_str = branch.to_string();
@@ -45,11 +342,274 @@ mod tests {
         assert_eq!(branch_str, "remotes/some_name")
     }
 
+    #[test]
+    fn to_string_prepends_tags_if_tag() {
+        let branch = Branch {
+            name: "v1.0".to_string(),
+            branch_type: BranchType::Tag,
+            ..Default::default()
+        };
+
+        let branch_str = branch.to_string();
+
+        assert_eq!(branch_str, "tags/v1.0")
+    }
+
+    fn make_remote_branch(name: &str, remote: &str) -> Branch {
+        Branch {
+            name: name.to_string(),
+            branch_type: BranchType::Remote,
+            remote: Some(remote.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn display_writes_remote_branch_in_given_format() {
+        let branch = make_remote_branch("fork/alice/feature/x", "fork/alice");
+
+        let display = |format| branch.display(format).to_string();
+
+        assert_eq!(display(RemoteFormat::Full), "remotes/fork/alice/feature/x");
+        assert_eq!(display(RemoteFormat::Short), "fork/alice/feature/x");
+        assert_eq!(display(RemoteFormat::Branch), "feature/x");
+    }
+
+    #[test]
+    fn display_does_not_change_local_branches_and_tags() {
+        let local = Branch {
+            name: "feature/x".to_string(),
+            ..Default::default()
+        };
+        let tag = Branch {
+            name: "v1.0".to_string(),
+            branch_type: BranchType::Tag,
+            ..Default::default()
+        };
+
+        for format in [
+            RemoteFormat::Full,
+            RemoteFormat::Short,
+            RemoteFormat::Branch,
+        ] {
+            assert_eq!(local.display(format).to_string(), "feature/x");
+            assert_eq!(tag.display(format).to_string(), "tags/v1.0");
+        }
+    }
+
+    #[test]
+    fn stash_index_parses_index_of_stash_entry() {
+        let stash = Branch {
+            name: "stash@{12}".to_string(),
+            branch_type: BranchType::Stash,
+            ..Default::default()
+        };
+        let branch = Branch {
+            name: "stash@{1}".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(stash.stash_index(), Some(12));
+        assert_eq!(stash.to_string(), "stash@{12}");
+        assert_eq!(branch.stash_index(), None);
+    }
+
+    #[test]
+    fn set_name_keeps_raw_bytes_given_non_utf8_name() {
+        let mut branch = Branch::default();
+
+        branch.set_name(b"caf\xe9");
+
+        assert_eq!(branch.name, "caf\u{fffd}");
+        assert_eq!(branch.raw_name, Some(b"caf\xe9".to_vec()));
+
+        branch.set_name("café".as_bytes());
+
+        assert_eq!(branch.name, "café");
+        assert_eq!(branch.raw_name, None);
+    }
+
+    #[test]
+    fn to_bytes_writes_raw_name_in_given_format() {
+        let mut branch = make_remote_branch("", "origin");
+        branch.set_name(b"origin/caf\xe9");
+        let mut tag = Branch {
+            branch_type: BranchType::Tag,
+            ..Default::default()
+        };
+        tag.set_name(b"v\xff");
+
+        let to_bytes = |format| branch.display(format).to_bytes();
+
+        assert_eq!(to_bytes(RemoteFormat::Full), b"remotes/origin/caf\xe9");
+        assert_eq!(to_bytes(RemoteFormat::Short), b"origin/caf\xe9");
+        assert_eq!(to_bytes(RemoteFormat::Branch), b"caf\xe9");
+        assert_eq!(tag.display(RemoteFormat::Full).to_bytes(), b"tags/v\xff");
+        assert_eq!(
+            make_remote_branch("origin/main", "origin")
+                .display(RemoteFormat::Full)
+                .to_bytes(),
+            b"remotes/origin/main"
+        );
+    }
+
+    #[test]
+    fn recent_checkouts_lists_visited_branches_most_recent_first() {
+        let messages = [
+            "checkout: moving from feature to main",
+            "commit: fix a bug",
+            "checkout: moving from main to feature",
+            "checkout: moving from bugfix to main",
+            "reset: moving to HEAD~1",
+        ];
+
+        let recent = recent_checkouts(messages);
+
+        assert_eq!(recent, vec!["main", "feature", "bugfix"]);
+    }
+
+    #[test]
+    fn recent_checkouts_ignores_other_reflog_entries() {
+        let messages = [
+            "commit (initial): first",
+            "rebase (finish): returning to refs/heads/main",
+        ];
+
+        let recent = recent_checkouts(messages);
+
+        assert!(recent.is_empty());
+    }
+
+    #[test]
+    fn assign_remotes_uses_longest_configured_remote() {
+        let mut branches = vec![
+            Branch {
+                name: "fork/alice/main".to_string(),
+                branch_type: BranchType::Remote,
+                ..Default::default()
+            },
+            Branch {
+                name: "origin/feature/x".to_string(),
+                branch_type: BranchType::Remote,
+                ..Default::default()
+            },
+            Branch {
+                name: "unknown/main".to_string(),
+                branch_type: BranchType::Remote,
+                ..Default::default()
+            },
+            Branch {
+                name: "origin/main".to_string(),
+                branch_type: BranchType::Local,
+                ..Default::default()
+            },
+        ];
+
+        assign_remotes(&mut branches, &["origin", "fork", "fork/alice"]);
+
+        let remotes: Vec<_> = branches.iter().map(|b| b.remote.as_deref()).collect();
+        assert_eq!(
+            remotes,
+            vec![Some("fork/alice"), Some("origin"), Some("unknown"), None]
+        );
+        assert_eq!(branches[0].branch_name(), "main");
+        assert_eq!(branches[1].branch_name(), "feature/x");
+    }
+
+    #[test]
+    fn ref_name_returns_full_ref_of_local_branch() {
+        let branch = Branch {
+            name: "feature/x".to_string(),
+            branch_type: BranchType::Local,
+            ..Default::default()
+        };
+
+        assert_eq!(branch.ref_name(), "refs/heads/feature/x");
+    }
+
+    #[test]
+    fn ref_name_returns_full_ref_of_remote_branch() {
+        let branch = Branch {
+            name: "origin/main".to_string(),
+            branch_type: BranchType::Remote,
+            ..Default::default()
+        };
+
+        assert_eq!(branch.ref_name(), "refs/remotes/origin/main");
+    }
+
+    #[test]
+    fn ref_name_returns_full_ref_of_tag() {
+        let branch = Branch {
+            name: "v1.0".to_string(),
+            branch_type: BranchType::Tag,
+            ..Default::default()
+        };
+
+        assert_eq!(branch.ref_name(), "refs/tags/v1.0");
+    }
+
+    #[test]
+    fn tip_returns_peeled_commit_of_annotated_tag() {
+        let target = object::ObjectId::from_hex("6e442625fe1a269a068c369e58ce11295dcdeb0c");
+        let peeled = object::ObjectId::from_hex("a513aa4efda3f06f316feb6b5df23d32480c435e");
+        let tag = Branch {
+            name: "v1.0".to_string(),
+            branch_type: BranchType::Tag,
+            target: target.clone(),
+            peeled: peeled.clone(),
+            ..Default::default()
+        };
+        let branch = Branch {
+            name: "main".to_string(),
+            target: target.clone(),
+            ..Default::default()
+        };
+
+        assert_eq!(tag.tip(), peeled.as_ref());
+        assert_eq!(branch.tip(), target.as_ref());
+    }
+
+    #[test]
+    fn upstream_name_strips_remotes_prefix() {
+        let branch = Branch {
+            name: "main".to_string(),
+            upstream: Some("refs/remotes/origin/main".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(branch.upstream_name(), Some("origin/main"));
+    }
+
+    #[test]
+    fn upstream_name_strips_heads_prefix() {
+        let branch = Branch {
+            name: "topic".to_string(),
+            upstream: Some("refs/heads/main".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(branch.upstream_name(), Some("main"));
+    }
+
+    #[test]
+    fn symref_name_strips_remotes_prefix() {
+        let branch = Branch {
+            name: "origin/HEAD".to_string(),
+            branch_type: BranchType::Remote,
+            symref: Some("refs/remotes/origin/main".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(branch.symref_name(), Some("origin/main"));
+    }
+
     #[test]
     fn to_string_returns_name_if_local_branch() {
         let branch = Branch {
             name: "some_name".to_string(),
             branch_type: BranchType::Local,
+            ..Default::default()
         };
 
         let branch_str = branch.to_string();
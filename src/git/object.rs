@@ -0,0 +1,353 @@
+use std::fmt::Display;
+
+use crate::Error;
+
+/// The length in bytes of a SHA-1 object ID.
+pub const SHA1_LEN: usize = 20;
+/// The length in bytes of a SHA-256 object ID.
+pub const SHA256_LEN: usize = 32;
+
+/// The hash function a repository names its objects with, set by the
+/// `extensions.objectFormat` config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ObjectFormat {
+    #[default]
+    Sha1,
+    Sha256,
+}
+
+impl ObjectFormat {
+    pub fn from_name(name: &str) -> Option<ObjectFormat> {
+        match name.to_ascii_lowercase().as_str() {
+            "sha1" => Some(ObjectFormat::Sha1),
+            "sha256" => Some(ObjectFormat::Sha256),
+            _ => None,
+        }
+    }
+
+    /// The length in bytes of an object ID.
+    pub fn hash_len(&self) -> usize {
+        match self {
+            ObjectFormat::Sha1 => SHA1_LEN,
+            ObjectFormat::Sha256 => SHA256_LEN,
+        }
+    }
+}
+
+/// The ID (hash) of a git object.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ObjectId(Vec<u8>);
+
+impl ObjectId {
+    pub fn from_bytes(bytes: &[u8]) -> ObjectId {
+        ObjectId(bytes.to_vec())
+    }
+
+    /// Parse a full-length hexadecimal object ID, returning `None` if the
+    /// string is not valid hex or has an odd length.
+    pub fn from_hex(hex: &str) -> Option<ObjectId> {
+        // 'from_str_radix' would also accept a leading '+'
+        if hex.is_empty()
+            || !hex.len().is_multiple_of(2)
+            || !hex.bytes().all(|b| b.is_ascii_hexdigit())
+        {
+            return None;
+        }
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+            .collect::<Option<Vec<u8>>>()?;
+        Some(ObjectId(bytes))
+    }
+
+    /// Parse a hexadecimal object ID, returning `None` unless it is the
+    /// length of a SHA-1 or SHA-256 ID.
+    pub fn from_full_hex(hex: &str) -> Option<ObjectId> {
+        if hex.len() != 2 * SHA1_LEN && hex.len() != 2 * SHA256_LEN {
+            return None;
+        }
+        ObjectId::from_hex(hex)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn to_hex(&self) -> String {
+        self.0.iter().map(|b| format!("{b:02x}")).collect()
+    }
+}
+
+impl Display for ObjectId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_hex())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectKind {
+    Commit,
+    Tree,
+    Blob,
+    Tag,
+}
+
+impl ObjectKind {
+    pub fn from_name(name: &str) -> Option<ObjectKind> {
+        match name {
+            "commit" => Some(ObjectKind::Commit),
+            "tree" => Some(ObjectKind::Tree),
+            "blob" => Some(ObjectKind::Blob),
+            "tag" => Some(ObjectKind::Tag),
+            _ => None,
+        }
+    }
+}
+
+/// An object's type and its (decompressed) content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Object {
+    pub kind: ObjectKind,
+    pub data: Vec<u8>,
+}
+
+/// The ID of the object an annotated tag points to, read from its `object`
+/// header.
+pub fn tag_target(data: &[u8]) -> Option<ObjectId> {
+    String::from_utf8_lossy(data)
+        .lines()
+        .take_while(|line| !line.is_empty())
+        .find_map(|line| line.strip_prefix("object "))
+        .and_then(ObjectId::from_hex)
+}
+
+/// The identity and timestamp in a commit's `author` or `committer` header.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Signature {
+    pub name: String,
+    pub email: String,
+    /// Seconds since the Unix epoch.
+    pub time: i64,
+    /// The author's or committer's offset from UTC, in minutes.
+    pub tz_offset: i32,
+}
+
+impl Signature {
+    /// Parse the value of an `author` or `committer` header, e.g.,
+    /// `A U Thor <author@example.com> 1700000000 +0100`.
+    pub fn parse(value: &str) -> Option<Signature> {
+        let (name, rest) = value.split_once('<')?;
+        let (email, when) = rest.rsplit_once('>')?;
+        let mut when = when.split_whitespace();
+        let time = when.next()?.parse::<i64>().ok()?;
+        let tz_offset = when.next().and_then(parse_tz_offset).unwrap_or(0);
+        Some(Signature {
+            name: name.trim().to_string(),
+            email: email.to_string(),
+            time,
+            tz_offset,
+        })
+    }
+}
+
+fn parse_tz_offset(tz: &str) -> Option<i32> {
+    let (sign, digits) = if let Some(digits) = tz.strip_prefix('-') {
+        (-1, digits)
+    } else {
+        (1, tz.strip_prefix('+')?)
+    };
+    if digits.len() != 4 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let hours = digits[..2].parse::<i32>().ok()?;
+    let minutes = digits[2..].parse::<i32>().ok()?;
+    Some(sign * (hours * 60 + minutes))
+}
+
+/// The headers and subject line of a commit object.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Commit {
+    pub id: ObjectId,
+    pub tree: ObjectId,
+    pub parents: Vec<ObjectId>,
+    pub author: Signature,
+    pub committer: Signature,
+    /// The first paragraph of the commit message, joined into one line.
+    pub subject: String,
+}
+
+impl Commit {
+    pub fn parse(id: ObjectId, data: &[u8]) -> Result<Commit, Error> {
+        let text = String::from_utf8_lossy(data);
+        let invalid = |what: &str| Error::Git(format!("invalid commit {id}: {what}"));
+        let (headers, message) = text.split_once("\n\n").unwrap_or((&text, ""));
+        let mut tree = None;
+        let mut parents = vec![];
+        let mut author = None;
+        let mut committer = None;
+        for line in headers.lines() {
+            // multi-line headers (e.g., 'gpgsig') continue with a space
+            let (key, value) = match line.split_once(' ') {
+                Some(header) => header,
+                None => continue,
+            };
+            match key {
+                "tree" => tree = ObjectId::from_hex(value),
+                "parent" => {
+                    parents.push(ObjectId::from_hex(value).ok_or_else(|| invalid("bad parent"))?)
+                }
+                "author" => author = Signature::parse(value),
+                "committer" => committer = Signature::parse(value),
+                _ => {}
+            }
+        }
+        let subject = message
+            .lines()
+            .skip_while(|l| l.trim().is_empty())
+            .take_while(|l| !l.trim().is_empty())
+            .map(|l| l.trim())
+            .collect::<Vec<_>>()
+            .join(" ");
+        Ok(Commit {
+            tree: tree.ok_or_else(|| invalid("missing tree"))?,
+            parents,
+            author: author.ok_or_else(|| invalid("missing author"))?,
+            committer: committer.ok_or_else(|| invalid("missing committer"))?,
+            subject,
+            id,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMMIT: &str = "tree 4b825dc642cb6eb9a060e54bf8d69288fbee4904\n\
+        parent e2bf29060f42743538be07c164820cdeca0d9d2b\n\
+        parent a9c68440003151dd3cf7ffa4eaedd425d221d268\n\
+        author A U Thor <author@example.com> 1700000000 +0130\n\
+        committer C O Mitter <committer@example.com> 1700000100 -0800\n\
+        gpgsig -----BEGIN PGP SIGNATURE-----\n \n abcdef\n -----END PGP SIGNATURE-----\n\
+        \n\
+        Merge the feature\n\
+        into main\n\
+        \n\
+        Some more detail.\n";
+
+    #[test]
+    fn object_id_round_trips_hex() {
+        let hex = "e2bf29060f42743538be07c164820cdeca0d9d2b";
+
+        let id = ObjectId::from_hex(hex).unwrap();
+
+        assert_eq!(id.as_bytes().len(), 20);
+        assert_eq!(id.to_string(), hex);
+    }
+
+    #[test]
+    fn object_id_from_hex_returns_none_given_invalid_hex() {
+        assert_eq!(ObjectId::from_hex("xyz0"), None);
+        assert_eq!(ObjectId::from_hex("abc"), None);
+        assert_eq!(ObjectId::from_hex(""), None);
+        assert_eq!(ObjectId::from_hex("+f+f"), None);
+    }
+
+    #[test]
+    fn object_id_from_full_hex_accepts_only_sha1_and_sha256_lengths() {
+        let sha1 = "e2bf29060f42743538be07c164820cdeca0d9d2b";
+        let sha256 = "6f4e2a1c9d0b8e7f3a5c2d1e0f9b8a7c6d5e4f3a2b1c0d9e8f7a6b5c4d3e2f1a";
+
+        assert_eq!(ObjectId::from_full_hex(sha1).unwrap().as_bytes().len(), 20);
+        assert_eq!(
+            ObjectId::from_full_hex(sha256).unwrap().as_bytes().len(),
+            32
+        );
+        assert_eq!(ObjectId::from_full_hex(&sha1[..38]), None);
+        assert_eq!(ObjectId::from_full_hex(&format!("{sha1}00")), None);
+    }
+
+    #[test]
+    fn signature_parse_reads_name_email_and_time() {
+        let sig = Signature::parse("A U Thor <author@example.com> 1700000000 -0130").unwrap();
+
+        assert_eq!(
+            sig,
+            Signature {
+                name: "A U Thor".to_string(),
+                email: "author@example.com".to_string(),
+                time: 1700000000,
+                tz_offset: -90,
+            }
+        );
+    }
+
+    #[test]
+    fn signature_parse_ignores_non_ascii_tz_offset() {
+        let sig = Signature::parse("A U Thor <author@example.com> 1700000000 +1é2").unwrap();
+
+        assert_eq!(sig.time, 1700000000);
+        assert_eq!(sig.tz_offset, 0);
+    }
+
+    #[test]
+    fn signature_parse_returns_none_given_no_time() {
+        assert_eq!(Signature::parse("A U Thor <author@example.com>"), None);
+    }
+
+    #[test]
+    fn tag_target_reads_object_header() {
+        let data = "object e2bf29060f42743538be07c164820cdeca0d9d2b\n\
+            type commit\n\
+            tag v1.0.0\n\
+            \n\
+            object a9c68440003151dd3cf7ffa4eaedd425d221d268\n";
+
+        let target = tag_target(data.as_bytes());
+
+        assert_eq!(
+            target.unwrap().to_string(),
+            "e2bf29060f42743538be07c164820cdeca0d9d2b"
+        );
+    }
+
+    #[test]
+    fn commit_parse_reads_headers_and_subject() {
+        let id = ObjectId::from_hex("707a178071655bed661318a5344557fe3e9a6ce1").unwrap();
+
+        let commit = Commit::parse(id.clone(), COMMIT.as_bytes()).unwrap();
+
+        assert_eq!(commit.id, id);
+        assert_eq!(
+            commit.tree.to_string(),
+            "4b825dc642cb6eb9a060e54bf8d69288fbee4904"
+        );
+        assert_eq!(
+            commit
+                .parents
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "e2bf29060f42743538be07c164820cdeca0d9d2b",
+                "a9c68440003151dd3cf7ffa4eaedd425d221d268"
+            ]
+        );
+        assert_eq!(commit.author.name, "A U Thor");
+        assert_eq!(commit.author.tz_offset, 90);
+        assert_eq!(commit.committer.email, "committer@example.com");
+        assert_eq!(commit.committer.time, 1700000100);
+        assert_eq!(commit.committer.tz_offset, -480);
+        assert_eq!(commit.subject, "Merge the feature into main");
+    }
+
+    #[test]
+    fn commit_parse_returns_err_given_missing_tree() {
+        let id = ObjectId::from_hex("707a178071655bed661318a5344557fe3e9a6ce1").unwrap();
+        let data = "author A <a@b.c> 1 +0000\ncommitter A <a@b.c> 1 +0000\n\nmsg\n";
+
+        let result = Commit::parse(id, data.as_bytes());
+
+        assert!(result.unwrap_err().to_string().contains("missing tree"));
+    }
+}
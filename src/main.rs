@@ -3,6 +3,11 @@ mod config;
 mod git;
 mod re;
 mod select;
+mod sort;
+
+use std::collections::{HashMap, HashSet};
+
+use git::object::ObjectId;
 
 #[derive(thiserror::Error, Debug, Eq, PartialEq)]
 pub enum Error {
@@ -21,15 +26,39 @@ pub enum Error {
 }
 
 fn main() {
-    let args = parse_args(std::env::args());
+    let mut args = parse_args(std::env::args());
     let config = read_config();
-    let getter = git::fs::FsBranchGetter {
-        repo_dir: args.git_dir.clone(),
-    };
+    // stash entries are listed newest first, and recent branches most
+    // recently checked out first, unless asked otherwise
+    if args.sort.is_empty() && !args.stash && args.recent.is_none() {
+        args.sort.extend(config.sort);
+    }
+    args.protected = config.protected.clone();
     let selector = select::DialogueSelector {
         theme: config.theme(),
+        preselect: args.gone,
+        columns: config.columns.clone(),
+        date_format: config.date_format,
     };
-    bselect(&args, getter, selector, &mut std::io::stdout()).unwrap_or_else(|e| {
+    let stdout = &mut std::io::stdout();
+    let stderr = &mut std::io::stderr();
+    let result = match args.backend.unwrap_or(config.backend) {
+        git::Backend::Fs => {
+            let getter = git::fs::FsBranchGetter {
+                repo_dir: args.git_dir.clone(),
+                env: git::fs::GitEnv::from_env(),
+            };
+            bselect(&args, getter, selector, stdout, stderr)
+        }
+        git::Backend::Git => {
+            let getter = git::cli::GitCliBranchGetter {
+                repo_dir: args.git_dir.clone(),
+                env: vec![],
+            };
+            bselect(&args, getter, selector, stdout, stderr)
+        }
+    };
+    result.unwrap_or_else(|e| {
         eprintln!("{e}");
         std::process::exit(1);
     })
@@ -37,22 +66,70 @@ fn main() {
 
 fn bselect(
     args: &cli::Args,
-    branch_getter: impl git::BranchGetter,
+    branch_getter: impl git::BranchGetter + git::ConfigGetter + git::RecentGetter + git::StashGetter,
     selector: impl select::BranchSelector,
     stdout: &mut dyn std::io::Write,
+    stderr: &mut dyn std::io::Write,
 ) -> Result<(), Error> {
     if args.config {
         writeln!(stdout, "{}", config::file::config_path()?.to_string_lossy())
             .map_err(|e| Error::Terminal(format!("cannot write config path: {e}")))?;
         return Ok(());
     }
-    let branches = filter_branches(branch_getter.branches()?, args.all, &args.filters)?;
-    let selected_branches = selector.select_branches(branches)?;
-    let branch_names = selected_branches
+    let all_branches = if args.stash {
+        branch_getter.stashes()?
+    } else {
+        let mut warnings = vec![];
+        let branches = branch_getter.branches(&mut warnings)?;
+        for warning in warnings {
+            writeln!(stderr, "bselect: warning: {warning}")
+                .map_err(|e| Error::Terminal(format!("cannot write to stderr: {e}")))?;
+        }
+        branches
+    };
+    // upstreams may be filtered out of the list, so find their tips first
+    let tips: HashMap<String, ObjectId> = all_branches
+        .iter()
+        .filter_map(|b| Some((b.ref_name(), b.tip()?.clone())))
+        .collect();
+    let branches = match args.recent {
+        Some(_) => recent_branches(&branch_getter, all_branches)?,
+        None => all_branches,
+    };
+    let branches = filter_merged(&branch_getter, branches, args)?;
+    let mut branches = filter_branches(branches, args)?;
+    if let Some(n) = args.recent {
+        branches.truncate(n);
+    }
+    // the counts are only shown when selecting, and can take a walk of each
+    // branch's history, so only count them then
+    if !args.no_interactive {
+        count_ahead_behind(&branch_getter, &mut branches, &tips, args.base.as_deref())?;
+    }
+    sort::sort_branches(&mut branches, &args.sort);
+    if !args.allow_protected {
+        mark_protected(&branch_getter, &mut branches, &args.protected)?;
+    }
+    let mut selected_branches = if args.no_interactive {
+        branches
+    } else {
+        selector.select_branches(branches)?
+    };
+    // protected branches can still be toggled, e.g., by selecting all, and
+    // are listed by '--no-interactive', so leave them out
+    selected_branches.retain(|b| !b.protected);
+    // dropping a stash entry renumbers the newer ones, so write the oldest
+    // first
+    selected_branches.sort_by_key(|b| std::cmp::Reverse(b.stash_index()));
+    // write the raw bytes of names, as branch names need not be valid UTF-8
+    let mut output = selected_branches
         .iter()
-        .map(|b| b.to_string())
-        .collect::<Vec<_>>();
-    writeln!(stdout, "{}", branch_names.join(" "))
+        .map(|b| b.display(args.remote_format).to_bytes())
+        .collect::<Vec<_>>()
+        .join(&b' ');
+    output.push(b'\n');
+    stdout
+        .write_all(&output)
         .map_err(|e| Error::Terminal(format!("cannot write to stdout: {e}")))
 }
 
@@ -80,15 +157,152 @@ fn read_config() -> config::Config {
     }
 }
 
-fn filter_branches(
+/// Set how many commits each local branch is ahead of and behind `base`, if
+/// given, or otherwise its upstream (whose tip is looked up in `tips`).
+fn count_ahead_behind(
+    branch_getter: &impl git::BranchGetter,
+    branches: &mut [git::Branch],
+    tips: &HashMap<String, ObjectId>,
+    base: Option<&str>,
+) -> Result<(), Error> {
+    let base = base.map(|rev| branch_getter.resolve_rev(rev)).transpose()?;
+    let mut idxs = vec![];
+    let mut pairs = vec![];
+    for (i, branch) in branches.iter().enumerate() {
+        if branch.branch_type != git::BranchType::Local {
+            continue;
+        }
+        let branch_base = match &base {
+            Some(id) => Some(id),
+            None => branch.upstream.as_ref().and_then(|u| tips.get(u)),
+        };
+        if let (Some(tip), Some(branch_base)) = (&branch.target, branch_base) {
+            idxs.push(i);
+            pairs.push((tip.clone(), branch_base.clone()));
+        }
+    }
+    if pairs.is_empty() {
+        return Ok(());
+    }
+    for (i, counts) in idxs.into_iter().zip(branch_getter.ahead_behind(&pairs)?) {
+        branches[i].ahead_behind = Some(counts);
+    }
+    Ok(())
+}
+
+/// Keep only the branches that are merged into the `--merged` revision and not
+/// merged into the `--no-merged` revision, if given.
+fn filter_merged(
+    branch_getter: &impl git::BranchGetter,
     branches: Vec<git::Branch>,
-    include_remotes: bool,
+    args: &cli::Args,
+) -> Result<Vec<git::Branch>, Error> {
+    let mut branches = branches;
+    for (rev, keep_merged) in [(&args.merged, true), (&args.no_merged, false)] {
+        if let Some(rev) = rev {
+            let merged = merged_into(branch_getter, &branches, rev)?;
+            branches = branches
+                .into_iter()
+                .zip(merged)
+                .filter(|(_, merged)| *merged == Some(keep_merged))
+                .map(|(b, _)| b)
+                .collect();
+        }
+    }
+    Ok(branches)
+}
+
+/// Whether each branch's tip is reachable from `rev`, or `None` if the
+/// branch's tip is not known.
+fn merged_into(
+    branch_getter: &impl git::BranchGetter,
+    branches: &[git::Branch],
+    rev: &str,
+) -> Result<Vec<Option<bool>>, Error> {
+    let tips: Vec<ObjectId> = branches.iter().filter_map(|b| b.tip().cloned()).collect();
+    let reachable = branch_getter.reachable(&branch_getter.resolve_rev(rev)?, &tips)?;
+    let merged: HashSet<&ObjectId> = tips
+        .iter()
+        .zip(reachable)
+        .filter(|(_, reachable)| *reachable)
+        .map(|(tip, _)| tip)
+        .collect();
+    Ok(branches
+        .iter()
+        .map(|b| b.tip().map(|tip| merged.contains(tip)))
+        .collect())
+}
+
+/// Mark the branches whose names, without any remote, match a protected
+/// pattern. Patterns set by the repository's `bselect.protected` git config
+/// take the place of those from the config file, so an empty value there
+/// protects nothing.
+fn mark_protected(
+    branch_getter: &impl git::ConfigGetter,
+    branches: &mut [git::Branch],
     patterns: &[String],
+) -> Result<(), Error> {
+    let repo_patterns = branch_getter.config_values("bselect", "protected")?;
+    let patterns = if repo_patterns.is_empty() {
+        patterns
+    } else {
+        &repo_patterns
+    };
+    let globs = re::compile_globs(patterns)?;
+    for branch in branches {
+        branch.protected = globs.iter().any(|g| g.is_match(branch.branch_name()));
+    }
+    Ok(())
+}
+
+/// Keep only the local branches that have been checked out, in the order
+/// they were last checked out, most recent first.
+fn recent_branches(
+    branch_getter: &impl git::RecentGetter,
+    branches: Vec<git::Branch>,
 ) -> Result<Vec<git::Branch>, Error> {
-    let re_patterns = re::compile_filters(patterns)?;
+    let mut branches: HashMap<String, git::Branch> = branches
+        .into_iter()
+        .filter(|b| b.branch_type == git::BranchType::Local)
+        .map(|b| (b.name.clone(), b))
+        .collect();
+    Ok(branch_getter
+        .recent_branches()?
+        .iter()
+        .filter_map(|name| branches.remove(name))
+        .collect())
+}
+
+/// Whether the branch is of a kind (local, remote-tracking, tag or stash
+/// entry) that the arguments ask to list.
+fn is_listed_kind(branch: &git::Branch, args: &cli::Args) -> bool {
+    match branch.branch_type {
+        git::BranchType::Local => !args.remotes_only,
+        git::BranchType::Remote if args.remote.is_empty() => args.all || args.remotes_only,
+        git::BranchType::Remote => branch
+            .remote
+            .as_ref()
+            .is_some_and(|remote| args.remote.contains(remote)),
+        git::BranchType::Tag => args.tags,
+        git::BranchType::Stash => args.stash,
+    }
+}
+
+fn filter_branches(
+    branches: Vec<git::Branch>,
+    args: &cli::Args,
+) -> Result<Vec<git::Branch>, Error> {
+    let re_patterns = re::compile_filters(&args.filters)?;
     let out: Vec<git::Branch> = branches
         .into_iter()
-        .filter(|b| include_remotes || b.branch_type == git::BranchType::Local)
+        .filter(|b| is_listed_kind(b, args))
+        // a symbolic ref such as 'origin/HEAD' is printed as 'HEAD' given
+        // '--remote-format branch', which git takes to be the current branch
+        .filter(|b| b.symref.is_none())
+        .filter(|b| !(args.exclude_current && b.is_head))
+        .filter(|b| !(args.exclude_worktrees && b.worktree.is_some()))
+        .filter(|b| !args.tracking || b.upstream.is_some())
+        .filter(|b| !args.gone || b.upstream_gone)
         .filter(|b| re::matches_regex(b, &re_patterns))
         .collect();
     if out.is_empty() {
@@ -112,29 +326,140 @@ mod tests {
             git::Branch {
                 name: "feature/xyz".to_string(),
                 branch_type: git::BranchType::Local,
+                ..Default::default()
             },
             git::Branch {
                 name: "123-add_a_new_feature".to_string(),
                 branch_type: git::BranchType::Local,
+                ..Default::default()
             },
             git::Branch {
                 name: "ABC".to_string(),
                 branch_type: git::BranchType::Remote,
+                ..Default::default()
             },
             git::Branch {
                 name: "456-fix_a_bug".to_string(),
                 branch_type: git::BranchType::Local,
+                ..Default::default()
             },
         ]
     }
 
+    #[derive(Default)]
     struct SimpleGetter {
         branches: Vec<git::Branch>,
+        recent: Vec<String>,
+        protected: Vec<String>,
+        warnings: Vec<String>,
+        /// Fail to count commits ahead and behind, to check they are not
+        /// counted.
+        fail_ahead_behind: bool,
     }
     impl git::BranchGetter for SimpleGetter {
-        fn branches(&self) -> Result<Vec<git::Branch>, Error> {
+        fn branches(&self, warnings: &mut Vec<String>) -> Result<Vec<git::Branch>, Error> {
+            warnings.extend(self.warnings.iter().cloned());
             Ok(self.branches.clone())
         }
+
+        fn resolve_rev(&self, rev: &str) -> Result<ObjectId, Error> {
+            self.branches
+                .iter()
+                .find(|b| b.name == rev || (rev == "HEAD" && b.is_head))
+                .and_then(|b| b.target.clone())
+                .ok_or_else(|| Error::Git(format!("could not resolve '{rev}'")))
+        }
+
+        fn reachable(&self, from: &ObjectId, commits: &[ObjectId]) -> Result<Vec<bool>, Error> {
+            let reached = self.ancestors(from);
+            Ok(commits.iter().map(|c| reached.contains(c)).collect())
+        }
+
+        fn ahead_behind(
+            &self,
+            pairs: &[(ObjectId, ObjectId)],
+        ) -> Result<Vec<git::AheadBehind>, Error> {
+            if self.fail_ahead_behind {
+                return Err(Error::Git("cannot count commits".to_string()));
+            }
+            Ok(pairs
+                .iter()
+                .map(|(commit, base)| {
+                    let (commit, base) = (self.ancestors(commit), self.ancestors(base));
+                    git::AheadBehind {
+                        ahead: commit.difference(&base).count(),
+                        behind: base.difference(&commit).count(),
+                    }
+                })
+                .collect())
+        }
+    }
+    impl git::StashGetter for SimpleGetter {
+        fn stashes(&self) -> Result<Vec<git::Branch>, Error> {
+            Ok(self
+                .branches
+                .iter()
+                .filter(|b| b.branch_type == git::BranchType::Stash)
+                .cloned()
+                .collect())
+        }
+    }
+    impl git::ConfigGetter for SimpleGetter {
+        fn config_values(&self, section: &str, key: &str) -> Result<Vec<String>, Error> {
+            match (section, key) {
+                ("bselect", "protected") => Ok(self.protected.clone()),
+                _ => Ok(vec![]),
+            }
+        }
+    }
+    impl git::RecentGetter for SimpleGetter {
+        fn recent_branches(&self) -> Result<Vec<String>, Error> {
+            Ok(self.recent.clone())
+        }
+    }
+    impl SimpleGetter {
+        /// Walk the graph formed by the parents of the branches' commits.
+        fn ancestors(&self, from: &ObjectId) -> HashSet<ObjectId> {
+            let parents: HashMap<&ObjectId, &Vec<ObjectId>> = self
+                .branches
+                .iter()
+                .filter_map(|b| b.commit.as_ref())
+                .map(|c| (&c.id, &c.parents))
+                .collect();
+            let mut reached = HashSet::new();
+            let mut to_visit = vec![from.clone()];
+            while let Some(id) = to_visit.pop() {
+                if let Some(ps) = parents.get(&id) {
+                    to_visit.extend(ps.iter().cloned());
+                }
+                reached.insert(id);
+            }
+            reached
+        }
+    }
+
+    fn commit_id(n: u8) -> ObjectId {
+        ObjectId::from_hex(&format!("{n:x}").repeat(40)).unwrap()
+    }
+
+    /// Give the branches commits forming the history:
+    ///   feature/xyz (1) <- 123-add_a_new_feature (2) <- ABC (3)
+    ///   456-fix_a_bug (4), whose parent is 1
+    fn add_commits(branches: &mut [git::Branch]) {
+        for (i, (n, parents)) in [(1, vec![]), (2, vec![1]), (3, vec![2]), (4, vec![1])]
+            .into_iter()
+            .enumerate()
+        {
+            branches[i].target = Some(commit_id(n));
+            branches[i].commit = Some(git::object::Commit {
+                id: commit_id(n),
+                tree: commit_id(n),
+                parents: parents.into_iter().map(commit_id).collect(),
+                author: Default::default(),
+                committer: Default::default(),
+                subject: String::new(),
+            });
+        }
     }
 
     struct SimpleSelector {
@@ -151,11 +476,14 @@ mod tests {
     fn bselect_writes_expected_branches_with_no_args() {
         let args = parse_args(to_string_iter!(["bselect"]));
         let branches = make_branches();
-        let branch_getter = SimpleGetter { branches };
+        let branch_getter = SimpleGetter {
+            branches,
+            ..Default::default()
+        };
         let selector = SimpleSelector { idxs: vec![0, 2] };
         let mut stdout = Vec::new();
 
-        let result = bselect(&args, branch_getter, selector, &mut stdout);
+        let result = bselect(&args, branch_getter, selector, &mut stdout, &mut Vec::new());
 
         assert!(result.is_ok());
         assert_eq!(
@@ -168,13 +496,16 @@ mod tests {
     fn bselect_writes_expected_branches_with_all_arg() {
         let args = parse_args(to_string_iter!(["bselect", "--all"]));
         let branches = make_branches();
-        let branch_getter = SimpleGetter { branches };
+        let branch_getter = SimpleGetter {
+            branches,
+            ..Default::default()
+        };
         let selector = SimpleSelector {
             idxs: vec![0, 2, 3],
         };
         let mut stdout = Vec::new();
 
-        let result = bselect(&args, branch_getter, selector, &mut stdout);
+        let result = bselect(&args, branch_getter, selector, &mut stdout, &mut Vec::new());
 
         assert!(result.is_ok());
         assert_eq!(
@@ -190,12 +521,16 @@ mod tests {
         branches.push(git::Branch {
             name: "feature/no_2".to_string(),
             branch_type: git::BranchType::Local,
+            ..Default::default()
         });
-        let branch_getter = SimpleGetter { branches };
+        let branch_getter = SimpleGetter {
+            branches,
+            ..Default::default()
+        };
         let selector = SimpleSelector { idxs: vec![0, 1] };
         let mut stdout = Vec::new();
 
-        let result = bselect(&args, branch_getter, selector, &mut stdout);
+        let result = bselect(&args, branch_getter, selector, &mut stdout, &mut Vec::new());
 
         assert!(result.is_ok());
         assert_eq!(
@@ -213,19 +548,24 @@ mod tests {
             git::Branch {
                 name: "some_other_branch-123".to_string(),
                 branch_type: git::BranchType::Local,
+                ..Default::default()
             },
         );
         branches.push(git::Branch {
             name: "feature/no_2".to_string(),
             branch_type: git::BranchType::Local,
+            ..Default::default()
         });
-        let branch_getter = SimpleGetter { branches };
+        let branch_getter = SimpleGetter {
+            branches,
+            ..Default::default()
+        };
         let selector = SimpleSelector {
             idxs: vec![0, 1, 2, 3],
         };
         let mut stdout = Vec::new();
 
-        let result = bselect(&args, branch_getter, selector, &mut stdout);
+        let result = bselect(&args, branch_getter, selector, &mut stdout, &mut Vec::new());
 
         assert!(result.is_ok());
         assert_eq!(
@@ -248,23 +588,29 @@ mod tests {
             git::Branch {
                 name: "some_other_branch-123".to_string(),
                 branch_type: git::BranchType::Local,
+                ..Default::default()
             },
         );
         branches.push(git::Branch {
             name: "feature/no_2".to_string(),
             branch_type: git::BranchType::Local,
+            ..Default::default()
         });
         branches.push(git::Branch {
             name: "feature/remote_feature".to_string(),
             branch_type: git::BranchType::Remote,
+            ..Default::default()
         });
-        let branch_getter = SimpleGetter { branches };
+        let branch_getter = SimpleGetter {
+            branches,
+            ..Default::default()
+        };
         let selector = SimpleSelector {
             idxs: vec![1, 2, 4],
         };
         let mut stdout = Vec::new();
 
-        let result = bselect(&args, branch_getter, selector, &mut stdout);
+        let result = bselect(&args, branch_getter, selector, &mut stdout, &mut Vec::new());
 
         assert!(result.is_ok());
         assert_eq!(
@@ -273,15 +619,781 @@ mod tests {
         );
     }
 
+    #[test]
+    fn bselect_does_not_list_current_branch_given_exclude_current_arg() {
+        let args = parse_args(to_string_iter!(["bselect", "--exclude-current"]));
+        let mut branches = make_branches();
+        branches[0].is_head = true;
+        let branch_getter = SimpleGetter {
+            branches,
+            ..Default::default()
+        };
+        let selector = SimpleSelector { idxs: vec![0, 1] };
+        let mut stdout = Vec::new();
+
+        let result = bselect(&args, branch_getter, selector, &mut stdout, &mut Vec::new());
+
+        assert!(result.is_ok());
+        assert_eq!(
+            String::from_utf8(stdout).unwrap(),
+            "123-add_a_new_feature 456-fix_a_bug\n"
+        );
+    }
+
+    #[test]
+    fn bselect_does_not_list_worktree_branches_given_exclude_worktrees_arg() {
+        let args = parse_args(to_string_iter!(["bselect", "--exclude-worktrees"]));
+        let mut branches = make_branches();
+        branches[1].worktree = Some(std::path::PathBuf::from("/some/worktree"));
+        let branch_getter = SimpleGetter {
+            branches,
+            ..Default::default()
+        };
+        let selector = SimpleSelector { idxs: vec![0, 1] };
+        let mut stdout = Vec::new();
+
+        let result = bselect(&args, branch_getter, selector, &mut stdout, &mut Vec::new());
+
+        assert!(result.is_ok());
+        assert_eq!(
+            String::from_utf8(stdout).unwrap(),
+            "feature/xyz 456-fix_a_bug\n"
+        );
+    }
+
+    #[test]
+    fn bselect_lists_only_branches_with_upstream_given_tracking_arg() {
+        let args = parse_args(to_string_iter!(["bselect", "--tracking", "--all"]));
+        let mut branches = make_branches();
+        branches[1].upstream = Some("refs/remotes/origin/123-add_a_new_feature".to_string());
+        branches[3].upstream = Some("refs/heads/main".to_string());
+        let branch_getter = SimpleGetter {
+            branches,
+            ..Default::default()
+        };
+        let selector = SimpleSelector { idxs: vec![0, 1] };
+        let mut stdout = Vec::new();
+
+        let result = bselect(&args, branch_getter, selector, &mut stdout, &mut Vec::new());
+
+        assert!(result.is_ok());
+        assert_eq!(
+            String::from_utf8(stdout).unwrap(),
+            "123-add_a_new_feature 456-fix_a_bug\n"
+        );
+    }
+
+    #[test]
+    fn bselect_lists_only_branches_with_gone_upstream_given_gone_arg() {
+        let args = parse_args(to_string_iter!(["bselect", "--gone"]));
+        let mut branches = make_branches();
+        branches[0].upstream = Some("refs/remotes/origin/feature/xyz".to_string());
+        branches[1].upstream = Some("refs/remotes/origin/123-add_a_new_feature".to_string());
+        branches[1].upstream_gone = true;
+        let branch_getter = SimpleGetter {
+            branches,
+            ..Default::default()
+        };
+        let selector = SimpleSelector { idxs: vec![0] };
+        let mut stdout = Vec::new();
+
+        let result = bselect(&args, branch_getter, selector, &mut stdout, &mut Vec::new());
+
+        assert!(result.is_ok());
+        assert_eq!(
+            String::from_utf8(stdout).unwrap(),
+            "123-add_a_new_feature\n"
+        );
+    }
+
+    #[test]
+    fn bselect_sorts_branches_given_sort_arg() {
+        let args = parse_args(to_string_iter!(["bselect", "--all", "--sort", "-name"]));
+        let branches = make_branches();
+        let branch_getter = SimpleGetter {
+            branches,
+            ..Default::default()
+        };
+        let selector = SimpleSelector {
+            idxs: vec![0, 1, 2, 3],
+        };
+        let mut stdout = Vec::new();
+
+        let result = bselect(&args, branch_getter, selector, &mut stdout, &mut Vec::new());
+
+        assert!(result.is_ok());
+        assert_eq!(
+            String::from_utf8(stdout).unwrap(),
+            "feature/xyz remotes/ABC 456-fix_a_bug 123-add_a_new_feature\n"
+        );
+    }
+
+    #[test]
+    fn bselect_lists_only_merged_branches_given_merged_arg() {
+        let args = parse_args(to_string_iter!(["bselect", "--all", "--merged", "ABC"]));
+        let mut branches = make_branches();
+        add_commits(&mut branches);
+        let branch_getter = SimpleGetter {
+            branches,
+            ..Default::default()
+        };
+        let selector = SimpleSelector {
+            idxs: vec![0, 1, 2],
+        };
+        let mut stdout = Vec::new();
+
+        let result = bselect(&args, branch_getter, selector, &mut stdout, &mut Vec::new());
+
+        assert!(result.is_ok());
+        assert_eq!(
+            String::from_utf8(stdout).unwrap(),
+            "feature/xyz 123-add_a_new_feature remotes/ABC\n"
+        );
+    }
+
+    #[test]
+    fn bselect_merged_defaults_to_head() {
+        let args = parse_args(to_string_iter!(["bselect", "--merged"]));
+        let mut branches = make_branches();
+        add_commits(&mut branches);
+        branches[3].is_head = true;
+        let branch_getter = SimpleGetter {
+            branches,
+            ..Default::default()
+        };
+        let selector = SimpleSelector { idxs: vec![0, 1] };
+        let mut stdout = Vec::new();
+
+        let result = bselect(&args, branch_getter, selector, &mut stdout, &mut Vec::new());
+
+        assert!(result.is_ok());
+        assert_eq!(
+            String::from_utf8(stdout).unwrap(),
+            "feature/xyz 456-fix_a_bug\n"
+        );
+    }
+
+    #[test]
+    fn bselect_lists_only_unmerged_branches_given_no_merged_arg() {
+        let args = parse_args(to_string_iter!(["bselect", "--no-merged", "ABC"]));
+        let mut branches = make_branches();
+        add_commits(&mut branches);
+        let branch_getter = SimpleGetter {
+            branches,
+            ..Default::default()
+        };
+        let selector = SimpleSelector { idxs: vec![0] };
+        let mut stdout = Vec::new();
+
+        let result = bselect(&args, branch_getter, selector, &mut stdout, &mut Vec::new());
+
+        assert!(result.is_ok());
+        assert_eq!(String::from_utf8(stdout).unwrap(), "456-fix_a_bug\n");
+    }
+
+    fn make_remote_branches() -> Vec<git::Branch> {
+        let remote_branch = |name: &str, remote: &str| git::Branch {
+            name: name.to_string(),
+            branch_type: git::BranchType::Remote,
+            remote: Some(remote.to_string()),
+            ..Default::default()
+        };
+        let mut branches = make_branches();
+        branches.extend([
+            remote_branch("origin/main", "origin"),
+            remote_branch("origin/feature/xyz", "origin"),
+            remote_branch("fork/alice/main", "fork/alice"),
+        ]);
+        branches
+    }
+
+    #[test]
+    fn bselect_lists_branches_of_given_remotes_given_remote_args() {
+        let args = parse_args(to_string_iter!([
+            "bselect",
+            "--remote",
+            "origin",
+            "--remote",
+            "fork/alice"
+        ]));
+        let branch_getter = SimpleGetter {
+            branches: make_remote_branches(),
+            ..Default::default()
+        };
+        let selector = SimpleSelector {
+            idxs: vec![0, 3, 4, 5],
+        };
+        let mut stdout = Vec::new();
+
+        let result = bselect(&args, branch_getter, selector, &mut stdout, &mut Vec::new());
+
+        assert!(result.is_ok());
+        assert_eq!(
+            String::from_utf8(stdout).unwrap(),
+            "feature/xyz remotes/origin/main remotes/origin/feature/xyz remotes/fork/alice/main\n"
+        );
+    }
+
+    #[test]
+    fn bselect_lists_only_remote_branches_given_remotes_only_arg() {
+        let args = parse_args(to_string_iter!(["bselect", "--remotes-only"]));
+        let branch_getter = SimpleGetter {
+            branches: make_remote_branches(),
+            ..Default::default()
+        };
+        let selector = SimpleSelector {
+            idxs: vec![0, 1, 2, 3],
+        };
+        let mut stdout = Vec::new();
+
+        let result = bselect(&args, branch_getter, selector, &mut stdout, &mut Vec::new());
+
+        assert!(result.is_ok());
+        assert_eq!(
+            String::from_utf8(stdout).unwrap(),
+            "remotes/ABC remotes/origin/main remotes/origin/feature/xyz remotes/fork/alice/main\n"
+        );
+    }
+
+    #[test]
+    fn bselect_writes_remote_branches_in_given_remote_format() {
+        let branch_getter = || SimpleGetter {
+            branches: make_remote_branches(),
+            ..Default::default()
+        };
+        let select = |format: &str| {
+            let args = parse_args(to_string_iter!([
+                "bselect",
+                "--remotes-only",
+                "--remote",
+                "fork/alice",
+                "--remote-format",
+                format
+            ]));
+            let selector = SimpleSelector { idxs: vec![0] };
+            let mut stdout = Vec::new();
+            bselect(
+                &args,
+                branch_getter(),
+                selector,
+                &mut stdout,
+                &mut Vec::new(),
+            )
+            .unwrap();
+            String::from_utf8(stdout).unwrap()
+        };
+
+        assert_eq!(select("full"), "remotes/fork/alice/main\n");
+        assert_eq!(select("short"), "fork/alice/main\n");
+        assert_eq!(select("branch"), "main\n");
+    }
+
+    #[test]
+    fn bselect_writes_warnings_of_branch_getter_to_stderr() {
+        let args = parse_args(to_string_iter!(["bselect"]));
+        let branch_getter = SimpleGetter {
+            branches: make_branches(),
+            warnings: vec!["ignoring malformed ref 'refs/heads/x'".to_string()],
+            ..Default::default()
+        };
+        let selector = SimpleSelector { idxs: vec![0] };
+        let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+
+        let result = bselect(&args, branch_getter, selector, &mut stdout, &mut stderr);
+
+        assert!(result.is_ok());
+        assert_eq!(String::from_utf8(stdout).unwrap(), "feature/xyz\n");
+        assert_eq!(
+            String::from_utf8(stderr).unwrap(),
+            "bselect: warning: ignoring malformed ref 'refs/heads/x'\n"
+        );
+    }
+
+    #[test]
+    fn bselect_does_not_write_symbolic_refs() {
+        let mut branches = make_remote_branches();
+        branches.push(git::Branch {
+            name: "origin/HEAD".to_string(),
+            branch_type: git::BranchType::Remote,
+            remote: Some("origin".to_string()),
+            symref: Some("refs/remotes/origin/main".to_string()),
+            ..Default::default()
+        });
+        let args = parse_args(to_string_iter!([
+            "bselect",
+            "--no-interactive",
+            "--remotes-only",
+            "--remote-format",
+            "branch"
+        ]));
+        let branch_getter = SimpleGetter {
+            branches,
+            ..Default::default()
+        };
+        let selector = SimpleSelector { idxs: vec![] };
+        let mut stdout = Vec::new();
+
+        let result = bselect(&args, branch_getter, selector, &mut stdout, &mut Vec::new());
+
+        assert!(result.is_ok());
+        let stdout = String::from_utf8(stdout).unwrap();
+        assert_eq!(stdout, "ABC main feature/xyz main\n");
+        assert!(!stdout.split_whitespace().any(|name| name == "HEAD"));
+    }
+
+    #[test]
+    fn bselect_writes_raw_bytes_of_non_utf8_branch_names() {
+        let args = parse_args(to_string_iter!(["bselect", "caf"]));
+        let mut branch = git::Branch::default();
+        branch.set_name(b"caf\xe9");
+        let mut branches = make_branches();
+        branches.push(branch);
+        let branch_getter = SimpleGetter {
+            branches,
+            ..Default::default()
+        };
+        let selector = SimpleSelector { idxs: vec![0] };
+        let mut stdout = Vec::new();
+
+        let result = bselect(&args, branch_getter, selector, &mut stdout, &mut Vec::new());
+
+        assert!(result.is_ok());
+        assert_eq!(stdout, b"caf\xe9\n");
+    }
+
+    fn make_stash(index: usize, message: &str) -> git::Branch {
+        git::Branch {
+            name: format!("stash@{{{index}}}"),
+            branch_type: git::BranchType::Stash,
+            message: Some(message.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn bselect_lists_only_stash_entries_given_stash_arg() {
+        let args = parse_args(to_string_iter!(["bselect", "--stash"]));
+        let mut branches = make_branches();
+        branches.extend([
+            make_stash(0, "WIP on main: e2bf290 Add a feature"),
+            make_stash(1, "On feature/xyz: try something"),
+        ]);
+        let branch_getter = SimpleGetter {
+            branches,
+            ..Default::default()
+        };
+        let selector = SimpleSelector { idxs: vec![0, 1] };
+        let mut stdout = Vec::new();
+
+        let result = bselect(&args, branch_getter, selector, &mut stdout, &mut Vec::new());
+
+        assert!(result.is_ok());
+        assert_eq!(String::from_utf8(stdout).unwrap(), "stash@{1} stash@{0}\n");
+    }
+
+    #[test]
+    fn bselect_does_not_write_protected_branches() {
+        let mut args = parse_args(to_string_iter!(["bselect"]));
+        args.protected = vec!["feature/*".to_string(), "456-*".to_string()];
+        let branch_getter = SimpleGetter {
+            branches: make_branches(),
+            ..Default::default()
+        };
+        let selector = SimpleSelector {
+            idxs: vec![0, 1, 2],
+        };
+        let mut stdout = Vec::new();
+
+        let result = bselect(&args, branch_getter, selector, &mut stdout, &mut Vec::new());
+
+        assert!(result.is_ok());
+        assert_eq!(
+            String::from_utf8(stdout).unwrap(),
+            "123-add_a_new_feature\n"
+        );
+    }
+
+    #[test]
+    fn bselect_uses_protected_patterns_of_repo_over_config() {
+        let mut args = parse_args(to_string_iter!(["bselect"]));
+        args.protected = vec!["feature/*".to_string()];
+        let branch_getter = SimpleGetter {
+            branches: make_branches(),
+            protected: vec!["123-*".to_string()],
+            ..Default::default()
+        };
+        let selector = SimpleSelector {
+            idxs: vec![0, 1, 2],
+        };
+        let mut stdout = Vec::new();
+
+        let result = bselect(&args, branch_getter, selector, &mut stdout, &mut Vec::new());
+
+        assert!(result.is_ok());
+        assert_eq!(
+            String::from_utf8(stdout).unwrap(),
+            "feature/xyz 456-fix_a_bug\n"
+        );
+    }
+
+    #[test]
+    fn bselect_writes_protected_branches_given_allow_protected_arg() {
+        let mut args = parse_args(to_string_iter!(["bselect", "--allow-protected"]));
+        args.protected = vec!["feature/*".to_string()];
+        let branch_getter = SimpleGetter {
+            branches: make_branches(),
+            ..Default::default()
+        };
+        let selector = SimpleSelector { idxs: vec![0, 1] };
+        let mut stdout = Vec::new();
+
+        let result = bselect(&args, branch_getter, selector, &mut stdout, &mut Vec::new());
+
+        assert!(result.is_ok());
+        assert_eq!(
+            String::from_utf8(stdout).unwrap(),
+            "feature/xyz 123-add_a_new_feature\n"
+        );
+    }
+
+    #[test]
+    fn bselect_writes_all_matching_branches_given_no_interactive_arg() {
+        let args = parse_args(to_string_iter!(["bselect", "--no-interactive", "feature"]));
+        let branch_getter = SimpleGetter {
+            branches: make_branches(),
+            ..Default::default()
+        };
+        let selector = SimpleSelector { idxs: vec![] };
+        let mut stdout = Vec::new();
+
+        let result = bselect(&args, branch_getter, selector, &mut stdout, &mut Vec::new());
+
+        assert!(result.is_ok());
+        assert_eq!(
+            String::from_utf8(stdout).unwrap(),
+            "feature/xyz 123-add_a_new_feature\n"
+        );
+    }
+
+    #[test]
+    fn bselect_does_not_count_ahead_behind_given_no_interactive_arg() {
+        let args = parse_args(to_string_iter!([
+            "bselect",
+            "--no-interactive",
+            "--base",
+            "feature/xyz",
+            "feature"
+        ]));
+        let mut branches = make_branches();
+        add_commits(&mut branches);
+        let branch_getter = SimpleGetter {
+            branches,
+            fail_ahead_behind: true,
+            ..Default::default()
+        };
+        let selector = SimpleSelector { idxs: vec![] };
+        let mut stdout = Vec::new();
+
+        let result = bselect(&args, branch_getter, selector, &mut stdout, &mut Vec::new());
+
+        assert!(result.is_ok());
+        assert_eq!(
+            String::from_utf8(stdout).unwrap(),
+            "feature/xyz 123-add_a_new_feature\n"
+        );
+    }
+
+    #[test]
+    fn bselect_does_not_write_protected_branches_given_no_interactive_arg() {
+        let mut args = parse_args(to_string_iter!(["bselect", "--list"]));
+        args.protected = vec!["feature/*".to_string()];
+        let branch_getter = SimpleGetter {
+            branches: make_branches(),
+            ..Default::default()
+        };
+        let selector = SimpleSelector { idxs: vec![] };
+        let mut stdout = Vec::new();
+
+        let result = bselect(&args, branch_getter, selector, &mut stdout, &mut Vec::new());
+
+        assert!(result.is_ok());
+        assert_eq!(
+            String::from_utf8(stdout).unwrap(),
+            "123-add_a_new_feature 456-fix_a_bug\n"
+        );
+    }
+
+    fn make_recent() -> Vec<String> {
+        [
+            "456-fix_a_bug",
+            "deleted",
+            "ABC",
+            "feature/xyz",
+            "456-fix_a_bug",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+    }
+
+    #[test]
+    fn bselect_lists_existing_local_branches_most_recent_first_given_recent_arg() {
+        let args = parse_args(to_string_iter!(["bselect", "--recent"]));
+        let branch_getter = SimpleGetter {
+            branches: make_branches(),
+            recent: make_recent(),
+            ..Default::default()
+        };
+        let selector = SimpleSelector { idxs: vec![0, 1] };
+        let mut stdout = Vec::new();
+
+        let result = bselect(&args, branch_getter, selector, &mut stdout, &mut Vec::new());
+
+        assert!(result.is_ok());
+        assert_eq!(
+            String::from_utf8(stdout).unwrap(),
+            "456-fix_a_bug feature/xyz\n"
+        );
+    }
+
+    #[test]
+    fn bselect_lists_n_recent_branches_matching_filters_given_recent_arg() {
+        let args = parse_args(to_string_iter!(["bselect", "--recent", "1", "feature"]));
+        let branch_getter = SimpleGetter {
+            branches: make_branches(),
+            recent: make_recent(),
+            ..Default::default()
+        };
+        let selector = SimpleSelector { idxs: vec![0] };
+        let mut stdout = Vec::new();
+
+        let result = bselect(&args, branch_getter, selector, &mut stdout, &mut Vec::new());
+
+        assert!(result.is_ok());
+        assert_eq!(String::from_utf8(stdout).unwrap(), "feature/xyz\n");
+    }
+
+    #[test]
+    fn bselect_returns_err_given_recent_arg_and_no_recent_branches() {
+        let args = parse_args(to_string_iter!(["bselect", "--recent"]));
+        let branch_getter = SimpleGetter {
+            branches: make_branches(),
+            ..Default::default()
+        };
+        let selector = SimpleSelector { idxs: vec![] };
+        let mut stdout = Vec::new();
+
+        let result = bselect(&args, branch_getter, selector, &mut stdout, &mut Vec::new());
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn bselect_filters_stash_entries_by_message() {
+        let args = parse_args(to_string_iter!(["bselect", "--stash", "try"]));
+        let branches = vec![
+            make_stash(0, "WIP on main: e2bf290 Add a feature"),
+            make_stash(1, "On feature/xyz: try something"),
+            make_stash(2, "On main: try something else"),
+        ];
+        let branch_getter = SimpleGetter {
+            branches,
+            ..Default::default()
+        };
+        let selector = SimpleSelector { idxs: vec![0, 1] };
+        let mut stdout = Vec::new();
+
+        let result = bselect(&args, branch_getter, selector, &mut stdout, &mut Vec::new());
+
+        assert!(result.is_ok());
+        assert_eq!(String::from_utf8(stdout).unwrap(), "stash@{2} stash@{1}\n");
+    }
+
+    fn make_tag(name: &str) -> git::Branch {
+        git::Branch {
+            name: name.to_string(),
+            branch_type: git::BranchType::Tag,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn bselect_does_not_list_tags_without_tags_arg() {
+        let args = parse_args(to_string_iter!(["bselect", "--all"]));
+        let mut branches = make_branches();
+        branches.push(make_tag("v1.0"));
+        let branch_getter = SimpleGetter {
+            branches,
+            ..Default::default()
+        };
+        let selector = SimpleSelector {
+            idxs: vec![0, 1, 2, 3],
+        };
+        let mut stdout = Vec::new();
+
+        let result = bselect(&args, branch_getter, selector, &mut stdout, &mut Vec::new());
+
+        assert!(result.is_ok());
+        assert_eq!(
+            String::from_utf8(stdout).unwrap(),
+            "feature/xyz 123-add_a_new_feature remotes/ABC 456-fix_a_bug\n"
+        );
+    }
+
+    #[test]
+    fn bselect_lists_tags_matching_filter_given_tags_arg() {
+        let args = parse_args(to_string_iter!(["bselect", "--tags", "^tags/v1"]));
+        let mut branches = make_branches();
+        branches.extend([make_tag("v1.0"), make_tag("v1.1"), make_tag("v2.0")]);
+        let branch_getter = SimpleGetter {
+            branches,
+            ..Default::default()
+        };
+        let selector = SimpleSelector { idxs: vec![0, 1] };
+        let mut stdout = Vec::new();
+
+        let result = bselect(&args, branch_getter, selector, &mut stdout, &mut Vec::new());
+
+        assert!(result.is_ok());
+        assert_eq!(String::from_utf8(stdout).unwrap(), "tags/v1.0 tags/v1.1\n");
+    }
+
+    #[test]
+    fn bselect_uses_peeled_commit_of_annotated_tag_given_merged_arg() {
+        let args = parse_args(to_string_iter!(["bselect", "--tags", "--merged", "ABC"]));
+        let mut branches = make_branches();
+        add_commits(&mut branches);
+        branches.push(git::Branch {
+            target: Some(commit_id(9)),
+            peeled: Some(commit_id(2)),
+            ..make_tag("merged")
+        });
+        branches.push(git::Branch {
+            target: Some(commit_id(8)),
+            peeled: Some(commit_id(4)),
+            ..make_tag("unmerged")
+        });
+        let branch_getter = SimpleGetter {
+            branches,
+            ..Default::default()
+        };
+        let selector = SimpleSelector {
+            idxs: vec![0, 1, 2],
+        };
+        let mut stdout = Vec::new();
+
+        let result = bselect(&args, branch_getter, selector, &mut stdout, &mut Vec::new());
+
+        assert!(result.is_ok());
+        assert_eq!(
+            String::from_utf8(stdout).unwrap(),
+            "feature/xyz 123-add_a_new_feature tags/merged\n"
+        );
+    }
+
+    #[test]
+    fn bselect_returns_err_given_merged_ref_does_not_resolve() {
+        let args = parse_args(to_string_iter!(["bselect", "--merged", "nope"]));
+        let mut branches = make_branches();
+        add_commits(&mut branches);
+        let branch_getter = SimpleGetter {
+            branches,
+            ..Default::default()
+        };
+        let selector = SimpleSelector { idxs: vec![0] };
+        let mut stdout = Vec::new();
+
+        let result = bselect(&args, branch_getter, selector, &mut stdout, &mut Vec::new());
+
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("could not resolve 'nope'"));
+    }
+
+    #[test]
+    fn count_ahead_behind_counts_against_upstream() {
+        let mut branches = make_branches();
+        add_commits(&mut branches);
+        branches[1].upstream = Some("refs/remotes/ABC".to_string());
+        branches[3].upstream = Some("refs/heads/123-add_a_new_feature".to_string());
+        let tips = branches
+            .iter()
+            .map(|b| (b.ref_name(), b.target.clone().unwrap()))
+            .collect();
+        let branch_getter = SimpleGetter {
+            branches: branches.clone(),
+            ..Default::default()
+        };
+
+        count_ahead_behind(&branch_getter, &mut branches, &tips, None).unwrap();
+
+        let counts: Vec<_> = branches.iter().map(|b| b.ahead_behind).collect();
+        assert_eq!(
+            counts,
+            vec![
+                None,
+                Some(git::AheadBehind {
+                    ahead: 0,
+                    behind: 1
+                }),
+                None,
+                Some(git::AheadBehind {
+                    ahead: 1,
+                    behind: 1
+                })
+            ]
+        );
+    }
+
+    #[test]
+    fn count_ahead_behind_counts_local_branches_against_base() {
+        let mut branches = make_branches();
+        add_commits(&mut branches);
+        branches[1].upstream = Some("refs/remotes/ABC".to_string());
+        let branch_getter = SimpleGetter {
+            branches: branches.clone(),
+            ..Default::default()
+        };
+
+        count_ahead_behind(
+            &branch_getter,
+            &mut branches,
+            &HashMap::new(),
+            Some("feature/xyz"),
+        )
+        .unwrap();
+
+        let counts: Vec<_> = branches.iter().map(|b| b.ahead_behind).collect();
+        assert_eq!(
+            counts,
+            vec![
+                Some(git::AheadBehind::default()),
+                Some(git::AheadBehind {
+                    ahead: 1,
+                    behind: 0
+                }),
+                None,
+                Some(git::AheadBehind {
+                    ahead: 1,
+                    behind: 0
+                })
+            ]
+        );
+    }
+
     #[test]
     fn bselect_returns_err_given_no_branches_match_pattern() {
         let args = parse_args(to_string_iter!(["bselect", "no_match"]));
         let branches = make_branches();
-        let branch_getter = SimpleGetter { branches };
+        let branch_getter = SimpleGetter {
+            branches,
+            ..Default::default()
+        };
         let selector = SimpleSelector { idxs: vec![0, 2] };
         let mut stdout = Vec::new();
 
-        let result = bselect(&args, branch_getter, selector, &mut stdout);
+        let result = bselect(&args, branch_getter, selector, &mut stdout, &mut Vec::new());
 
         assert!(result.is_err());
         assert!(result
@@ -294,11 +1406,14 @@ mod tests {
     fn bselect_prints_config_path_given_config_arg_true() {
         let args = parse_args(to_string_iter!(["bselect", "--config"]));
         let branches = make_branches();
-        let branch_getter = SimpleGetter { branches };
+        let branch_getter = SimpleGetter {
+            branches,
+            ..Default::default()
+        };
         let selector = SimpleSelector { idxs: vec![0, 2] };
         let mut stdout = Vec::new();
 
-        bselect(&args, branch_getter, selector, &mut stdout).unwrap();
+        bselect(&args, branch_getter, selector, &mut stdout, &mut Vec::new()).unwrap();
 
         assert!(String::from_utf8(stdout)
             .unwrap()
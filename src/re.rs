@@ -10,16 +10,62 @@ pub fn compile_filters(filters: &[String]) -> Result<Vec<regex::Regex>, Error> {
     Ok(patterns)
 }
 
+/// Compile wildcard patterns, e.g., `release/*`, that match whole branch
+/// names. As in `git branch --list`, `*` matches any characters, including
+/// `/`.
+pub fn compile_globs(globs: &[String]) -> Result<Vec<regex::Regex>, Error> {
+    globs
+        .iter()
+        .map(|g| string_to_regex(&glob_to_regex(g)))
+        .collect()
+}
+
+fn glob_to_regex(glob: &str) -> String {
+    let chars: Vec<char> = glob.chars().collect();
+    let mut re = String::from("^");
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' => re.push_str(".*"),
+            '?' => re.push('.'),
+            '[' => match chars[i + 1..].iter().position(|c| *c == ']') {
+                Some(len) if len > 0 => {
+                    let class: String = chars[i + 1..i + 1 + len].iter().collect();
+                    let class = match class.strip_prefix('!') {
+                        Some(negated) => format!("^{negated}"),
+                        None => class,
+                    };
+                    re.push_str(&format!("[{}]", class.replace('\\', "\\\\")));
+                    i += len + 1;
+                }
+                _ => re.push_str(&regex::escape("[")),
+            },
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+        i += 1;
+    }
+    re.push('$');
+    re
+}
+
 fn string_to_regex(s: &str) -> Result<regex::Regex, Error> {
     regex::Regex::new(s).map_err(|e| Error::Regex(format!("{e}")))
 }
 
+/// Whether any of the patterns match the branch's name, as printed, its
+/// description or the message of a stash entry.
 pub fn matches_regex(branch: &git::Branch, patterns: &[regex::Regex]) -> bool {
     if patterns.is_empty() {
         return true;
     }
+    let name = format!("{branch}");
     for pattern in patterns {
-        if pattern.is_match(&format!("{branch}")) {
+        if pattern.is_match(&name)
+            || [&branch.description, &branch.message]
+                .into_iter()
+                .flatten()
+                .any(|text| pattern.is_match(text))
+        {
             return true;
         }
     }
@@ -28,6 +74,44 @@ pub fn matches_regex(branch: &git::Branch, patterns: &[regex::Regex]) -> bool {
 
 #[cfg(test)]
 mod tests {
+    mod compile_globs {
+        use super::super::*;
+
+        fn globs(patterns: &[&str]) -> Vec<regex::Regex> {
+            let patterns: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+            compile_globs(&patterns).unwrap()
+        }
+
+        #[test]
+        fn matches_whole_name() {
+            let patterns = globs(&["main"]);
+
+            assert!(patterns[0].is_match("main"));
+            assert!(!patterns[0].is_match("maintenance"));
+            assert!(!patterns[0].is_match("old-main"));
+        }
+
+        #[test]
+        fn star_matches_across_slashes() {
+            let patterns = globs(&["release/*"]);
+
+            assert!(patterns[0].is_match("release/1.0"));
+            assert!(patterns[0].is_match("release/1.0/hotfix"));
+            assert!(!patterns[0].is_match("release"));
+        }
+
+        #[test]
+        fn matches_characters_and_classes() {
+            let patterns = globs(&["v?.[0-9]", "[!a-z]*.x"]);
+
+            assert!(patterns[0].is_match("v1.2"));
+            assert!(!patterns[0].is_match("v1.a"));
+            assert!(!patterns[0].is_match("v1x2"));
+            assert!(patterns[1].is_match("A.x"));
+            assert!(!patterns[1].is_match("a.x"));
+        }
+    }
+
     mod matches_regex {
         use super::super::*;
 
@@ -36,6 +120,7 @@ mod tests {
             let branch = git::Branch {
                 name: "x".to_string(),
                 branch_type: git::BranchType::Local,
+                ..Default::default()
             };
 
             assert!(matches_regex(&branch, &[]));
@@ -46,6 +131,7 @@ mod tests {
             let branch = git::Branch {
                 name: "a_name".to_string(),
                 branch_type: git::BranchType::Local,
+                ..Default::default()
             };
             let patterns = ["x", "[0-9]+", "a_"]
                 .into_iter()
@@ -60,6 +146,7 @@ mod tests {
             let branch = git::Branch {
                 name: "a_name".to_string(),
                 branch_type: git::BranchType::Remote,
+                ..Default::default()
             };
             let patterns = ["remotes/.+"]
                 .into_iter()
@@ -69,11 +156,44 @@ mod tests {
             assert!(matches_regex(&branch, &patterns));
         }
 
+        #[test]
+        fn returns_true_given_pattern_matches_stash_message() {
+            let branch = git::Branch {
+                name: "stash@{0}".to_string(),
+                branch_type: git::BranchType::Stash,
+                message: Some("On main: try something".to_string()),
+                ..Default::default()
+            };
+            let patterns = ["^On main"]
+                .into_iter()
+                .map(|p| regex::Regex::new(p).unwrap())
+                .collect::<Vec<_>>();
+
+            assert!(matches_regex(&branch, &patterns));
+        }
+
+        #[test]
+        fn returns_true_given_pattern_matches_description() {
+            let branch = git::Branch {
+                name: "feature".to_string(),
+                branch_type: git::BranchType::Local,
+                description: Some("Add a feature\nFixes #123".to_string()),
+                ..Default::default()
+            };
+            let patterns = ["#123"]
+                .into_iter()
+                .map(|p| regex::Regex::new(p).unwrap())
+                .collect::<Vec<_>>();
+
+            assert!(matches_regex(&branch, &patterns));
+        }
+
         #[test]
         fn returns_false_if_no_patterns_matched() {
             let branch = git::Branch {
                 name: "a_branch".to_string(),
                 branch_type: git::BranchType::Local,
+                ..Default::default()
             };
             let patterns = ["x", "[0-9]+"]
                 .into_iter()
@@ -0,0 +1,233 @@
+use serde::{Deserialize, Serialize};
+
+use crate::git;
+
+const SHORT_SHA_LEN: usize = 7;
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Details of a branch's tip commit that can be shown next to its name.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Column {
+    /// The abbreviated ID of the commit.
+    Sha,
+    /// The committer date.
+    Date,
+    /// The author's name.
+    Author,
+    /// The first line of the commit message.
+    Subject,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DateFormat {
+    /// How long ago the commit was made, e.g., `3 weeks ago`.
+    #[default]
+    Relative,
+    /// The date and time in the committer's timezone, e.g.,
+    /// `2023-11-14 22:13:20 +0000`.
+    Iso,
+}
+
+pub fn default_columns() -> Vec<Column> {
+    vec![Column::Date, Column::Author, Column::Subject]
+}
+
+impl Column {
+    /// The (unstyled) text of the column for a branch, which is empty if the
+    /// detail is not known.
+    pub fn text(&self, branch: &git::Branch, date_format: DateFormat, now: i64) -> String {
+        let commit = branch.commit.as_ref();
+        let text = match self {
+            Column::Sha => branch
+                .target
+                .as_ref()
+                .map(|id| id.to_hex().chars().take(SHORT_SHA_LEN).collect()),
+            Column::Date => commit.map(|c| match date_format {
+                DateFormat::Relative => format_relative_date(c.committer.time, now),
+                DateFormat::Iso => format_iso_date(c.committer.time, c.committer.tz_offset),
+            }),
+            Column::Author => commit.map(|c| c.author.name.clone()),
+            Column::Subject => commit.map(|c| c.subject.clone()),
+        };
+        text.unwrap_or_default()
+    }
+}
+
+fn plural(count: i64, unit: &str) -> String {
+    if count == 1 {
+        format!("{count} {unit}")
+    } else {
+        format!("{count} {unit}s")
+    }
+}
+
+/// Format a time relative to `now`, rounding the way `git log --date=relative`
+/// does.
+pub fn format_relative_date(time: i64, now: i64) -> String {
+    if time > now {
+        return "in the future".to_string();
+    }
+    let mut diff = now - time;
+    if diff < 90 {
+        return format!("{} ago", plural(diff, "second"));
+    }
+    diff = (diff + 30) / 60;
+    if diff < 90 {
+        return format!("{} ago", plural(diff, "minute"));
+    }
+    diff = (diff + 30) / 60;
+    if diff < 36 {
+        return format!("{} ago", plural(diff, "hour"));
+    }
+    diff = (diff + 12) / 24;
+    if diff < 14 {
+        return format!("{} ago", plural(diff, "day"));
+    }
+    if diff < 70 {
+        return format!("{} ago", plural((diff + 3) / 7, "week"));
+    }
+    if diff < 365 {
+        return format!("{} ago", plural((diff + 15) / 30, "month"));
+    }
+    if diff < 1825 {
+        let total_months = (diff * 12 * 2 + 365) / (365 * 2);
+        let years = plural(total_months / 12, "year");
+        return match total_months % 12 {
+            0 => format!("{years} ago"),
+            months => format!("{years}, {} ago", plural(months, "month")),
+        };
+    }
+    format!("{} ago", plural((diff + 183) / 365, "year"))
+}
+
+/// Format a time in the given timezone (in minutes from UTC) like
+/// `git log --date=iso`.
+pub fn format_iso_date(time: i64, tz_offset: i32) -> String {
+    let local = time + i64::from(tz_offset) * 60;
+    let (year, month, day) = civil_from_days(local.div_euclid(SECONDS_PER_DAY));
+    let seconds = local.rem_euclid(SECONDS_PER_DAY);
+    let sign = if tz_offset < 0 { '-' } else { '+' };
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} {sign}{:02}{:02}",
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60,
+        tz_offset.abs() / 60,
+        tz_offset.abs() % 60,
+    )
+}
+
+/// Convert days since the Unix epoch to a (year, month, day) date in the
+/// proleptic Gregorian calendar.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::object::{Commit, ObjectId, Signature};
+
+    const HOUR: i64 = 60 * 60;
+    const DAY: i64 = 24 * HOUR;
+
+    fn make_branch() -> git::Branch {
+        let id = ObjectId::from_hex("e2bf29060f42743538be07c164820cdeca0d9d2b").unwrap();
+        git::Branch {
+            name: "main".to_string(),
+            target: Some(id.clone()),
+            commit: Some(Commit {
+                id,
+                tree: ObjectId::from_hex("4b825dc642cb6eb9a060e54bf8d69288fbee4904").unwrap(),
+                parents: vec![],
+                author: Signature {
+                    name: "A U Thor".to_string(),
+                    ..Default::default()
+                },
+                committer: Signature {
+                    time: 1700000000,
+                    tz_offset: 60,
+                    ..Default::default()
+                },
+                subject: "Add a feature".to_string(),
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn text_returns_commit_details() {
+        let branch = make_branch();
+        let now = 1700000000 + 3 * DAY;
+
+        let text = |c: Column| c.text(&branch, DateFormat::Relative, now);
+
+        assert_eq!(text(Column::Sha), "e2bf290");
+        assert_eq!(text(Column::Date), "3 days ago");
+        assert_eq!(text(Column::Author), "A U Thor");
+        assert_eq!(text(Column::Subject), "Add a feature");
+    }
+
+    #[test]
+    fn text_returns_iso_date_given_iso_format() {
+        let branch = make_branch();
+
+        let text = Column::Date.text(&branch, DateFormat::Iso, 0);
+
+        assert_eq!(text, "2023-11-14 23:13:20 +0100");
+    }
+
+    #[test]
+    fn text_returns_empty_string_given_no_commit() {
+        let branch = git::Branch {
+            name: "main".to_string(),
+            ..Default::default()
+        };
+
+        for column in [Column::Sha, Column::Date, Column::Author, Column::Subject] {
+            assert_eq!(column.text(&branch, DateFormat::Relative, 0), "");
+        }
+    }
+
+    #[test]
+    fn format_relative_date_rounds_like_git() {
+        let now = 1700000000;
+
+        let ago = |diff: i64| format_relative_date(now - diff, now);
+
+        assert_eq!(ago(1), "1 second ago");
+        assert_eq!(ago(89), "89 seconds ago");
+        assert_eq!(ago(90), "2 minutes ago");
+        assert_eq!(ago(2 * HOUR), "2 hours ago");
+        assert_eq!(ago(35 * HOUR), "35 hours ago");
+        assert_eq!(ago(36 * HOUR), "2 days ago");
+        assert_eq!(ago(15 * DAY), "2 weeks ago");
+        assert_eq!(ago(100 * DAY), "3 months ago");
+        assert_eq!(ago(365 * DAY), "1 year ago");
+        assert_eq!(ago(500 * DAY), "1 year, 4 months ago");
+        assert_eq!(ago(3000 * DAY), "8 years ago");
+        assert_eq!(format_relative_date(now + 1, now), "in the future");
+    }
+
+    #[test]
+    fn format_iso_date_uses_timezone_offset() {
+        assert_eq!(format_iso_date(0, 0), "1970-01-01 00:00:00 +0000");
+        assert_eq!(
+            format_iso_date(951782400, -330),
+            "2000-02-28 18:30:00 -0530"
+        );
+        assert_eq!(format_iso_date(-1, 0), "1969-12-31 23:59:59 +0000");
+    }
+}
@@ -1,6 +1,11 @@
+pub mod columns;
 pub mod theme;
 
+use std::collections::HashSet;
+use std::io::IsTerminal;
+
 use dialoguer as dlg;
+use dlg::console;
 
 use crate::git;
 use crate::Error;
@@ -11,12 +16,135 @@ pub trait BranchSelector {
 
 pub struct DialogueSelector {
     pub theme: theme::ConsoleTheme,
+    /// Whether all branches start off selected.
+    pub preselect: bool,
+    /// The commit details to show, in order, after each branch's name.
+    pub columns: Vec<columns::Column>,
+    pub date_format: columns::DateFormat,
+}
+
+const COLUMN_SEPARATOR: &str = "  ";
+
+impl DialogueSelector {
+    /// Format the branches as rows of aligned columns: the branch itself
+    /// followed by the details of its tip commit. Columns that are empty for
+    /// every branch are left out.
+    fn format_items(&self, branches: &[git::Branch], now: i64) -> Vec<String> {
+        let rows: Vec<Vec<String>> = branches
+            .iter()
+            .map(|b| {
+                self.columns
+                    .iter()
+                    .map(|c| c.text(b, self.date_format, now))
+                    .collect()
+            })
+            .collect();
+        let names: Vec<String> = branches
+            .iter()
+            .map(|b| self.theme.format_branch(b))
+            .collect();
+        let name_width = max_width(names.iter());
+        let widths: Vec<usize> = (0..self.columns.len())
+            .map(|i| max_width(rows.iter().map(|r| &r[i])))
+            .collect();
+        names
+            .into_iter()
+            .zip(rows)
+            .map(|(name, row)| {
+                // the last non-empty cell is not padded, to avoid trailing
+                // whitespace
+                let last = match row.iter().rposition(|cell| !cell.is_empty()) {
+                    Some(i) => i,
+                    None => return name,
+                };
+                let mut item = pad(&name, name_width);
+                for (i, cell) in row.iter().enumerate().take(last + 1) {
+                    if widths[i] == 0 {
+                        continue;
+                    }
+                    item.push_str(COLUMN_SEPARATOR);
+                    if !cell.is_empty() {
+                        let style = self.theme.column_style(self.columns[i]);
+                        item.push_str(&style.apply_to(cell));
+                    }
+                    if i < last {
+                        item.push_str(&" ".repeat(widths[i] - console::measure_text_width(cell)));
+                    }
+                }
+                item
+            })
+            .collect()
+    }
+}
+
+/// Formats items using a `ConsoleTheme`, showing the items of protected
+/// branches as locked, whether or not they have been checked.
+struct ProtectingTheme<'a> {
+    theme: &'a theme::ConsoleTheme,
+    protected: HashSet<&'a str>,
+}
+
+impl dlg::theme::Theme for ProtectingTheme<'_> {
+    fn format_multi_select_prompt_item(
+        &self,
+        f: &mut dyn std::fmt::Write,
+        text: &str,
+        checked: bool,
+        active: bool,
+    ) -> std::fmt::Result {
+        if self.protected.contains(text) {
+            self.theme.format_protected_item(f, text, active)
+        } else {
+            self.theme
+                .format_multi_select_prompt_item(f, text, checked, active)
+        }
+    }
+}
+
+fn max_width<'a>(cells: impl Iterator<Item = &'a String>) -> usize {
+    cells
+        .map(|c| console::measure_text_width(c))
+        .max()
+        .unwrap_or(0)
+}
+
+fn pad(text: &str, width: usize) -> String {
+    let padding = width.saturating_sub(console::measure_text_width(text));
+    format!("{text}{}", " ".repeat(padding))
+}
+
+fn unix_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
 }
 
 impl BranchSelector for DialogueSelector {
     fn select_branches(&self, branches: Vec<git::Branch>) -> Result<Vec<git::Branch>, Error> {
-        let idxs = match dlg::MultiSelect::with_theme(&self.theme)
-            .items(&branches)
+        // the prompt reads keys from stdin and draws on stderr
+        if !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+            return Err(Error::Terminal(
+                "cannot select branches as stdin or stderr is not a terminal, use '--no-interactive' to print all matching branches".to_string(),
+            ));
+        }
+        let items = self.format_items(&branches, unix_now());
+        let theme = ProtectingTheme {
+            theme: &self.theme,
+            protected: items
+                .iter()
+                .zip(&branches)
+                .filter(|(_, b)| b.protected)
+                .map(|(item, _)| item.as_str())
+                .collect(),
+        };
+        let defaults: Vec<bool> = branches
+            .iter()
+            .map(|b| self.preselect && !b.protected)
+            .collect();
+        let idxs = match dlg::MultiSelect::with_theme(&theme)
+            .items(&items)
+            .defaults(&defaults)
             .interact_opt()
         {
             Ok(opt) => match opt {
@@ -29,3 +157,154 @@ impl BranchSelector for DialogueSelector {
         Ok(selected.collect())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::object::{Commit, ObjectId, Signature};
+    use columns::{Column, DateFormat};
+    use theme::style::Style;
+    use theme::styled_string::StyledString;
+
+    const NOW: i64 = 1700000000;
+
+    fn make_selector(columns: Vec<Column>) -> DialogueSelector {
+        DialogueSelector {
+            theme: theme::ConsoleTheme {
+                current_branch_marker: StyledString::default(),
+                worktree_branch_marker: StyledString::default(),
+                date_style: Style::default(),
+                author_style: Style::default(),
+                sha_style: Style::default(),
+                ..Default::default()
+            },
+            preselect: false,
+            columns,
+            date_format: DateFormat::Relative,
+        }
+    }
+
+    fn make_branch(name: &str, author: &str, days_ago: i64, subject: &str) -> git::Branch {
+        let id = ObjectId::from_hex("e2bf29060f42743538be07c164820cdeca0d9d2b").unwrap();
+        git::Branch {
+            name: name.to_string(),
+            target: Some(id.clone()),
+            commit: Some(Commit {
+                id,
+                tree: ObjectId::from_hex("4b825dc642cb6eb9a060e54bf8d69288fbee4904").unwrap(),
+                parents: vec![],
+                author: Signature {
+                    name: author.to_string(),
+                    ..Default::default()
+                },
+                committer: Signature {
+                    time: NOW - days_ago * 24 * 60 * 60,
+                    ..Default::default()
+                },
+                subject: subject.to_string(),
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn format_items_aligns_commit_details_in_columns() {
+        let selector = make_selector(vec![Column::Date, Column::Author, Column::Subject]);
+        let branches = vec![
+            make_branch("main", "A U Thor", 3, "Add a feature"),
+            make_branch("feature/long", "Me", 12, "Fix a bug"),
+        ];
+
+        let items = selector.format_items(&branches, NOW);
+
+        assert_eq!(
+            items,
+            vec![
+                "main          3 days ago   A U Thor  Add a feature",
+                "feature/long  12 days ago  Me        Fix a bug",
+            ]
+        );
+    }
+
+    #[test]
+    fn format_items_uses_configured_columns() {
+        let selector = make_selector(vec![Column::Sha, Column::Author]);
+        let branches = vec![make_branch("main", "A U Thor", 3, "Add a feature")];
+
+        let items = selector.format_items(&branches, NOW);
+
+        assert_eq!(items, vec!["main  e2bf290  A U Thor"]);
+    }
+
+    #[test]
+    fn format_items_leaves_out_details_of_branches_without_commits() {
+        let selector = make_selector(vec![Column::Date, Column::Subject]);
+        let branches = vec![
+            make_branch("main", "A U Thor", 3, "Add a feature"),
+            git::Branch {
+                name: "no_commit".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        let items = selector.format_items(&branches, NOW);
+
+        assert_eq!(
+            items,
+            vec!["main       3 days ago  Add a feature", "no_commit"]
+        );
+    }
+
+    #[test]
+    fn format_items_returns_names_given_no_columns() {
+        let selector = make_selector(vec![]);
+        let branches = vec![make_branch("main", "A U Thor", 3, "Add a feature")];
+
+        let items = selector.format_items(&branches, NOW);
+
+        assert_eq!(items, vec!["main"]);
+    }
+
+    #[test]
+    fn format_items_styles_columns_using_theme() {
+        let mut selector = make_selector(vec![Column::Author]);
+        selector.theme.author_style = Style {
+            foreground: Some("magenta".to_string()),
+            ..Default::default()
+        };
+        let branches = vec![make_branch("main", "A U Thor", 3, "Add a feature")];
+
+        let items = selector.format_items(&branches, NOW);
+
+        assert_eq!(items, vec!["main  \u{1b}[35mA U Thor\u{1b}[0m"]);
+    }
+
+    #[test]
+    fn protecting_theme_shows_protected_items_as_locked_even_if_checked() {
+        use dlg::theme::Theme;
+
+        let console_theme = theme::ConsoleTheme {
+            protected_item_style: Style::default(),
+            protected_item_prefix: StyledString {
+                value: "[-]".to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let theme = ProtectingTheme {
+            theme: &console_theme,
+            protected: HashSet::from(["main"]),
+        };
+        let (mut protected, mut other) = (String::new(), String::new());
+
+        theme
+            .format_multi_select_prompt_item(&mut protected, "main", true, true)
+            .unwrap();
+        theme
+            .format_multi_select_prompt_item(&mut other, "feature", true, false)
+            .unwrap();
+
+        assert_eq!(protected, "> [-] main");
+        assert_eq!(other, "  [x] feature");
+    }
+}
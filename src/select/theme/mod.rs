@@ -1,8 +1,11 @@
 pub mod style;
 pub mod styled_string;
 
+use dialoguer::console;
 use serde::{Deserialize, Serialize};
 
+use crate::git;
+use crate::select::columns::Column;
 use style::Style;
 use styled_string::StyledString;
 
@@ -18,6 +21,18 @@ pub struct ConsoleTheme {
     pub inactive_item_prefix: StyledString,
     pub active_item_style: Style,
     pub inactive_item_style: Style,
+    pub protected_item_prefix: StyledString,
+    pub protected_item_style: Style,
+    pub current_branch_marker: StyledString,
+    pub worktree_branch_marker: StyledString,
+    pub upstream_style: Style,
+    pub description_style: Style,
+    pub ahead_marker: StyledString,
+    pub behind_marker: StyledString,
+    pub sha_style: Style,
+    pub date_style: Style,
+    pub author_style: Style,
+    pub subject_style: Style,
 }
 
 fn default_name() -> String {
@@ -46,10 +61,168 @@ impl Default for ConsoleTheme {
             },
             active_item_style: Style::default(),
             inactive_item_style: Style::default(),
+            protected_item_prefix: StyledString {
+                value: "[-]".to_string(),
+                style: Style {
+                    dim: true,
+                    ..Default::default()
+                },
+            },
+            protected_item_style: Style {
+                dim: true,
+                ..Default::default()
+            },
+            current_branch_marker: StyledString {
+                value: "*".to_string(),
+                style: Style {
+                    foreground: Some("green".to_string()),
+                    ..Default::default()
+                },
+            },
+            worktree_branch_marker: StyledString {
+                value: "+".to_string(),
+                style: Style {
+                    foreground: Some("cyan".to_string()),
+                    ..Default::default()
+                },
+            },
+            upstream_style: Style {
+                foreground: Some("blue".to_string()),
+                ..Default::default()
+            },
+            description_style: Style {
+                dim: true,
+                ..Default::default()
+            },
+            ahead_marker: StyledString {
+                value: "↑".to_string(),
+                style: Style {
+                    foreground: Some("green".to_string()),
+                    ..Default::default()
+                },
+            },
+            behind_marker: StyledString {
+                value: "↓".to_string(),
+                style: Style {
+                    foreground: Some("red".to_string()),
+                    ..Default::default()
+                },
+            },
+            sha_style: Style {
+                foreground: Some("yellow".to_string()),
+                ..Default::default()
+            },
+            date_style: Style {
+                foreground: Some("green".to_string()),
+                ..Default::default()
+            },
+            author_style: Style {
+                foreground: Some("magenta".to_string()),
+                ..Default::default()
+            },
+            subject_style: Style::default(),
         }
     }
 }
 
+impl ConsoleTheme {
+    /// Format a branch for display as an item in the selection list.
+    ///
+    /// The branch checked out in the current worktree is marked using
+    /// `current_branch_marker` and branches checked out in other worktrees
+    /// using `worktree_branch_marker`. Other branches are padded to keep
+    /// alignment. The ref a symbolic ref points to, or a branch's upstream, if
+    /// it has one, follows its name, then the number of commits it is ahead of
+    /// and behind its upstream, and then the first line of its description.
+    pub fn format_branch(&self, branch: &git::Branch) -> String {
+        let mut item = self.format_marker(branch);
+        item.push_str(&branch.to_string());
+        if let Some(message) = &branch.message {
+            item.push_str(&format!(": {message}"));
+        }
+        if let Some(symref) = branch.symref_name() {
+            item.push_str(&format!(" -> {symref}"));
+        }
+        if let Some(upstream) = branch.upstream_name() {
+            let upstream = if branch.upstream_gone {
+                format!("{upstream}: gone")
+            } else {
+                upstream.to_string()
+            };
+            item.push_str(&format!(" -> {}", self.upstream_style.apply_to(&upstream)));
+        }
+        if let Some(counts) = branch.ahead_behind {
+            for (count, marker) in [
+                (counts.ahead, &self.ahead_marker),
+                (counts.behind, &self.behind_marker),
+            ] {
+                if count > 0 {
+                    let counter = format!("{}{count}", marker.value);
+                    item.push_str(&format!(" {}", marker.style.apply_to(&counter)));
+                }
+            }
+        }
+        if let Some(description) = branch.description.as_deref().and_then(|d| d.lines().next()) {
+            item.push_str(&format!(
+                " - {}",
+                self.description_style.apply_to(description)
+            ));
+        }
+        item
+    }
+
+    /// The style of a column of commit details.
+    pub fn column_style(&self, column: Column) -> &Style {
+        match column {
+            Column::Sha => &self.sha_style,
+            Column::Date => &self.date_style,
+            Column::Author => &self.author_style,
+            Column::Subject => &self.subject_style,
+        }
+    }
+
+    /// Format an item that cannot be checked, using `protected_item_prefix`
+    /// in place of a checkbox. The item's own styling is replaced by
+    /// `protected_item_style`, to grey it out.
+    pub fn format_protected_item(
+        &self,
+        f: &mut dyn std::fmt::Write,
+        text: &str,
+        active: bool,
+    ) -> std::fmt::Result {
+        let prefix = if active {
+            &self.active_item_prefix
+        } else {
+            &self.inactive_item_prefix
+        };
+        let text = console::strip_ansi_codes(text);
+        write!(
+            f,
+            "{prefix}{} {}",
+            self.protected_item_prefix,
+            self.protected_item_style.apply_to(&text)
+        )
+    }
+
+    fn format_marker(&self, branch: &git::Branch) -> String {
+        let marker_width = console::measure_text_width(&self.current_branch_marker.value).max(
+            console::measure_text_width(&self.worktree_branch_marker.value),
+        );
+        if marker_width == 0 {
+            return String::new();
+        }
+        let marker = if branch.is_head {
+            &self.current_branch_marker
+        } else if branch.worktree.is_some() {
+            &self.worktree_branch_marker
+        } else {
+            return " ".repeat(marker_width + 1);
+        };
+        let padding = marker_width - console::measure_text_width(&marker.value);
+        format!("{marker}{} ", " ".repeat(padding))
+    }
+}
+
 impl dialoguer::theme::Theme for ConsoleTheme {
     fn format_multi_select_prompt_item(
         &self,
@@ -139,6 +312,37 @@ mod tests {
         assert_eq!(out, "  [x] text");
     }
 
+    #[test]
+    fn format_protected_item_replaces_checkbox_and_styles_of_text() {
+        let theme = ConsoleTheme {
+            protected_item_prefix: StyledString {
+                value: "[-]".to_string(),
+                ..Default::default()
+            },
+            protected_item_style: Style::default(),
+            ..Default::default()
+        };
+        let mut out = String::new();
+
+        theme
+            .format_protected_item(&mut out, "main \u{1b}[34morigin/main\u{1b}[0m", true)
+            .unwrap();
+
+        assert_eq!(out, "> [-] main origin/main");
+    }
+
+    #[test]
+    fn default_theme_dims_protected_item() {
+        let theme = ConsoleTheme::default();
+        let mut out = String::new();
+
+        theme
+            .format_protected_item(&mut out, "main", false)
+            .unwrap();
+
+        assert_eq!(out, "  \u{1b}[2m[-]\u{1b}[0m \u{1b}[2mmain\u{1b}[0m");
+    }
+
     #[test]
     fn theme_read_from_json_formats_to_expected_string() {
         let json = r#"
@@ -169,4 +373,280 @@ mod tests {
 
         assert_eq!(out, "👉 \u{1b}[32m✓\u{1b}[0m some_branch");
     }
+
+    #[test]
+    fn format_branch_marks_current_branch() {
+        let theme = ConsoleTheme {
+            current_branch_marker: StyledString {
+                value: "*".to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let branch = git::Branch {
+            name: "main".to_string(),
+            is_head: true,
+            ..Default::default()
+        };
+
+        assert_eq!(theme.format_branch(&branch), "* main");
+    }
+
+    #[test]
+    fn format_branch_pads_branch_that_is_not_current() {
+        let theme = ConsoleTheme {
+            current_branch_marker: StyledString {
+                value: "->".to_string(),
+                ..Default::default()
+            },
+            worktree_branch_marker: StyledString::default(),
+            ..Default::default()
+        };
+        let branch = git::Branch {
+            name: "other".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(theme.format_branch(&branch), "   other");
+    }
+
+    #[test]
+    fn format_branch_marks_branch_checked_out_in_other_worktree() {
+        let theme = ConsoleTheme {
+            current_branch_marker: StyledString {
+                value: "*".to_string(),
+                ..Default::default()
+            },
+            worktree_branch_marker: StyledString {
+                value: "wt".to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let branch = git::Branch {
+            name: "feature".to_string(),
+            worktree: Some(std::path::PathBuf::from("/some/worktree")),
+            ..Default::default()
+        };
+        let current = git::Branch {
+            name: "main".to_string(),
+            is_head: true,
+            ..Default::default()
+        };
+
+        assert_eq!(theme.format_branch(&branch), "wt feature");
+        assert_eq!(theme.format_branch(&current), "*  main");
+    }
+
+    #[test]
+    fn format_branch_does_not_pad_given_empty_marker() {
+        let theme = ConsoleTheme {
+            current_branch_marker: StyledString::default(),
+            worktree_branch_marker: StyledString::default(),
+            ..Default::default()
+        };
+        let branch = git::Branch {
+            name: "main".to_string(),
+            is_head: true,
+            ..Default::default()
+        };
+
+        assert_eq!(theme.format_branch(&branch), "main");
+    }
+
+    #[test]
+    fn format_branch_shows_upstream_after_name() {
+        let theme = ConsoleTheme {
+            current_branch_marker: StyledString::default(),
+            worktree_branch_marker: StyledString::default(),
+            upstream_style: Style::default(),
+            ..Default::default()
+        };
+        let branch = git::Branch {
+            name: "main".to_string(),
+            upstream: Some("refs/remotes/origin/main".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(theme.format_branch(&branch), "main -> origin/main");
+    }
+
+    #[test]
+    fn format_branch_shows_target_of_symbolic_ref() {
+        let theme = ConsoleTheme {
+            current_branch_marker: StyledString::default(),
+            worktree_branch_marker: StyledString::default(),
+            ..Default::default()
+        };
+        let branch = git::Branch {
+            name: "origin/HEAD".to_string(),
+            branch_type: git::BranchType::Remote,
+            symref: Some("refs/remotes/origin/main".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            theme.format_branch(&branch),
+            "remotes/origin/HEAD -> origin/main"
+        );
+    }
+
+    #[test]
+    fn format_branch_shows_message_of_stash_entry() {
+        let theme = ConsoleTheme {
+            current_branch_marker: StyledString::default(),
+            worktree_branch_marker: StyledString::default(),
+            ..Default::default()
+        };
+        let branch = git::Branch {
+            name: "stash@{0}".to_string(),
+            branch_type: git::BranchType::Stash,
+            message: Some("WIP on main: e2bf290 Add a feature".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            theme.format_branch(&branch),
+            "stash@{0}: WIP on main: e2bf290 Add a feature"
+        );
+    }
+
+    #[test]
+    fn format_branch_shows_first_line_of_description_after_upstream() {
+        let theme = ConsoleTheme {
+            current_branch_marker: StyledString::default(),
+            worktree_branch_marker: StyledString::default(),
+            upstream_style: Style::default(),
+            description_style: Style::default(),
+            ..Default::default()
+        };
+        let branch = git::Branch {
+            name: "feature".to_string(),
+            upstream: Some("refs/remotes/origin/feature".to_string()),
+            description: Some("Add a feature\nin two lines".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            theme.format_branch(&branch),
+            "feature -> origin/feature - Add a feature"
+        );
+    }
+
+    #[test]
+    fn format_branch_marks_gone_upstream() {
+        let theme = ConsoleTheme {
+            current_branch_marker: StyledString::default(),
+            worktree_branch_marker: StyledString::default(),
+            upstream_style: Style::default(),
+            ..Default::default()
+        };
+        let branch = git::Branch {
+            name: "feature".to_string(),
+            upstream: Some("refs/remotes/origin/feature".to_string()),
+            upstream_gone: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            theme.format_branch(&branch),
+            "feature -> origin/feature: gone"
+        );
+    }
+
+    #[test]
+    fn default_theme_colours_upstream() {
+        let theme = ConsoleTheme::default();
+        let branch = git::Branch {
+            name: "main".to_string(),
+            upstream: Some("refs/remotes/origin/main".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            theme.format_branch(&branch),
+            "  main -> \u{1b}[34morigin/main\u{1b}[0m"
+        );
+    }
+
+    #[test]
+    fn default_theme_colours_current_branch_marker() {
+        let theme = ConsoleTheme::default();
+        let branch = git::Branch {
+            name: "main".to_string(),
+            is_head: true,
+            ..Default::default()
+        };
+
+        assert_eq!(theme.format_branch(&branch), "\u{1b}[32m*\u{1b}[0m main");
+    }
+
+    #[test]
+    fn format_branch_shows_commits_ahead_and_behind() {
+        let theme = ConsoleTheme {
+            current_branch_marker: StyledString::default(),
+            worktree_branch_marker: StyledString::default(),
+            upstream_style: Style::default(),
+            ahead_marker: StyledString {
+                value: "+".to_string(),
+                ..Default::default()
+            },
+            behind_marker: StyledString {
+                value: "-".to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut branch = git::Branch {
+            name: "main".to_string(),
+            upstream: Some("refs/remotes/origin/main".to_string()),
+            ahead_behind: Some(git::AheadBehind {
+                ahead: 3,
+                behind: 12,
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(theme.format_branch(&branch), "main -> origin/main +3 -12");
+        branch.ahead_behind = Some(git::AheadBehind {
+            ahead: 0,
+            behind: 2,
+        });
+        assert_eq!(theme.format_branch(&branch), "main -> origin/main -2");
+        branch.ahead_behind = Some(git::AheadBehind::default());
+        assert_eq!(theme.format_branch(&branch), "main -> origin/main");
+    }
+
+    #[test]
+    fn default_theme_colours_ahead_and_behind_counts() {
+        let theme = ConsoleTheme::default();
+        let branch = git::Branch {
+            name: "main".to_string(),
+            ahead_behind: Some(git::AheadBehind {
+                ahead: 3,
+                behind: 12,
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            theme.format_branch(&branch),
+            "  main \u{1b}[32m↑3\u{1b}[0m \u{1b}[31m↓12\u{1b}[0m"
+        );
+    }
+
+    #[test]
+    fn default_theme_dims_description() {
+        let theme = ConsoleTheme::default();
+        let branch = git::Branch {
+            name: "feature".to_string(),
+            description: Some("Add a feature".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            theme.format_branch(&branch),
+            "  feature - \u{1b}[2mAdd a feature\u{1b}[0m"
+        );
+    }
 }
@@ -13,6 +13,8 @@ pub struct Style {
     pub fg_bright: bool,
     #[serde(default)]
     pub bg_bright: bool,
+    #[serde(default)]
+    pub dim: bool,
 }
 
 impl Style {
@@ -40,6 +42,9 @@ impl From<Style> for console::Style {
         if item.bg_bright {
             style = style.on_bright();
         };
+        if item.dim {
+            style = style.dim();
+        }
         style
     }
 }
@@ -70,6 +75,7 @@ mod tests {
         assert_eq!(style.background, None);
         assert!(!style.fg_bright);
         assert!(!style.bg_bright);
+        assert!(!style.dim);
     }
 
     #[test]
@@ -89,6 +95,7 @@ mod tests {
             background: Some("green".to_string()),
             fg_bright: true,
             bg_bright: false,
+            dim: false,
         };
 
         let console_style = console::Style::from(style);
@@ -108,6 +115,7 @@ mod tests {
             background: None,
             fg_bright: false,
             bg_bright: true,
+            dim: false,
         };
 
         let console_style = console::Style::from(style);
@@ -123,6 +131,7 @@ mod tests {
             background: Some("green".to_string()),
             fg_bright: true,
             bg_bright: true,
+            dim: false,
         };
 
         let console_style = console::Style::from(style);
@@ -142,6 +151,7 @@ mod tests {
             background: Some("green".to_string()),
             fg_bright: true,
             bg_bright: false,
+            dim: false,
         };
 
         let styled_str = style.apply_to("text");
@@ -56,6 +56,7 @@ mod tests {
             foreground: Some("red".to_string()),
             bg_bright: true,
             fg_bright: true,
+            dim: true,
         };
         let ss = StyledString {
             value: "my_text".to_string(),
@@ -67,7 +68,8 @@ mod tests {
             .red()
             .on_green()
             .bright()
-            .on_bright();
+            .on_bright()
+            .dim();
         let expected_obj = expected_style.apply_to("my_text");
         assert_eq!(format!("{ss}"), format!("{expected_obj}"));
     }
@@ -0,0 +1,295 @@
+use serde::{Deserialize, Serialize};
+
+use crate::git;
+
+/// What to sort branches by, mirroring the keys of `git branch --sort`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortField {
+    /// The branch's name.
+    Name,
+    /// The date of the branch's tip commit.
+    CommitterDate,
+    /// Whether the branch is local, remote-tracking, a tag or a stash entry, in
+    /// that order.
+    Type,
+    /// The remote a remote-tracking branch belongs to, local branches and tags
+    /// first.
+    Remote,
+}
+
+/// A sort field and direction, written like `committerdate` or, to reverse the
+/// order, `-committerdate`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct SortKey {
+    pub field: SortField,
+    pub descending: bool,
+}
+
+impl std::str::FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (descending, name) = match s.strip_prefix('-') {
+            Some(name) => (true, name),
+            None => (false, s),
+        };
+        let field = match name {
+            "name" | "refname" => SortField::Name,
+            "committerdate" => SortField::CommitterDate,
+            "type" => SortField::Type,
+            "remote" => SortField::Remote,
+            _ => {
+                return Err(format!(
+                    "unknown sort key '{name}' (expected one of: name, committerdate, type, remote)"
+                ))
+            }
+        };
+        Ok(SortKey { field, descending })
+    }
+}
+
+impl TryFrom<String> for SortKey {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<SortKey> for String {
+    fn from(key: SortKey) -> String {
+        let name = match key.field {
+            SortField::Name => "name",
+            SortField::CommitterDate => "committerdate",
+            SortField::Type => "type",
+            SortField::Remote => "remote",
+        };
+        if key.descending {
+            format!("-{name}")
+        } else {
+            name.to_string()
+        }
+    }
+}
+
+impl SortKey {
+    fn compare(&self, a: &git::Branch, b: &git::Branch) -> std::cmp::Ordering {
+        let ordering = match self.field {
+            SortField::Name => a.name.cmp(&b.name),
+            SortField::CommitterDate => committer_time(a).cmp(&committer_time(b)),
+            SortField::Type => type_rank(a).cmp(&type_rank(b)),
+            SortField::Remote => remote_name(a).cmp(&remote_name(b)),
+        };
+        if self.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+}
+
+fn committer_time(branch: &git::Branch) -> i64 {
+    // branches whose commit could not be read sort as the oldest
+    branch
+        .commit
+        .as_ref()
+        .map_or(i64::MIN, |c| c.committer.time)
+}
+
+fn type_rank(branch: &git::Branch) -> u8 {
+    match branch.branch_type {
+        git::BranchType::Local => 0,
+        git::BranchType::Remote => 1,
+        git::BranchType::Tag => 2,
+        git::BranchType::Stash => 3,
+    }
+}
+
+fn remote_name(branch: &git::Branch) -> Option<&str> {
+    match branch.branch_type {
+        git::BranchType::Local | git::BranchType::Tag | git::BranchType::Stash => None,
+        git::BranchType::Remote => branch.remote.as_deref(),
+    }
+}
+
+/// Sort branches by the given keys. As with `git branch --sort`, the last key
+/// is the primary one. The sort is stable, so ties keep the order the branches
+/// were given in.
+pub fn sort_branches(branches: &mut [git::Branch], keys: &[SortKey]) {
+    for key in keys {
+        branches.sort_by(|a, b| key.compare(a, b));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::object::{Commit, ObjectId, Signature};
+
+    fn make_branch(name: &str, branch_type: git::BranchType, time: Option<i64>) -> git::Branch {
+        let id = ObjectId::from_hex("e2bf29060f42743538be07c164820cdeca0d9d2b").unwrap();
+        git::Branch {
+            name: name.to_string(),
+            branch_type,
+            remote: match branch_type {
+                git::BranchType::Remote => name.split_once('/').map(|(r, _)| r.to_string()),
+                _ => None,
+            },
+            commit: time.map(|time| Commit {
+                id: id.clone(),
+                tree: id.clone(),
+                parents: vec![],
+                author: Signature::default(),
+                committer: Signature {
+                    time,
+                    ..Default::default()
+                },
+                subject: String::new(),
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn make_branches() -> Vec<git::Branch> {
+        vec![
+            make_branch("upstream/main", git::BranchType::Remote, Some(300)),
+            make_branch("main", git::BranchType::Local, Some(200)),
+            make_branch("origin/main", git::BranchType::Remote, Some(100)),
+            make_branch("feature", git::BranchType::Local, Some(400)),
+            make_branch("no_commit", git::BranchType::Local, None),
+        ]
+    }
+
+    fn sorted_names(keys: &[&str]) -> Vec<String> {
+        let keys: Vec<SortKey> = keys.iter().map(|k| k.parse().unwrap()).collect();
+        let mut branches = make_branches();
+        sort_branches(&mut branches, &keys);
+        branches.into_iter().map(|b| b.name).collect()
+    }
+
+    #[test]
+    fn sort_branches_keeps_order_given_no_keys() {
+        assert_eq!(
+            sorted_names(&[]),
+            vec![
+                "upstream/main",
+                "main",
+                "origin/main",
+                "feature",
+                "no_commit"
+            ]
+        );
+    }
+
+    #[test]
+    fn sort_branches_orders_by_name() {
+        assert_eq!(
+            sorted_names(&["name"]),
+            vec![
+                "feature",
+                "main",
+                "no_commit",
+                "origin/main",
+                "upstream/main"
+            ]
+        );
+        assert_eq!(
+            sorted_names(&["-name"]),
+            vec![
+                "upstream/main",
+                "origin/main",
+                "no_commit",
+                "main",
+                "feature"
+            ]
+        );
+    }
+
+    #[test]
+    fn sort_branches_orders_by_committer_date() {
+        assert_eq!(
+            sorted_names(&["committerdate"]),
+            vec![
+                "no_commit",
+                "origin/main",
+                "main",
+                "upstream/main",
+                "feature"
+            ]
+        );
+        assert_eq!(
+            sorted_names(&["-committerdate"]),
+            vec![
+                "feature",
+                "upstream/main",
+                "main",
+                "origin/main",
+                "no_commit"
+            ]
+        );
+    }
+
+    #[test]
+    fn sort_branches_keeps_order_of_ties() {
+        let mut branches = vec![
+            make_branch("b", git::BranchType::Remote, None),
+            make_branch("a", git::BranchType::Remote, None),
+            make_branch("c", git::BranchType::Local, None),
+        ];
+
+        sort_branches(&mut branches, &["type".parse().unwrap()]);
+
+        let names: Vec<_> = branches.iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(names, vec!["c", "b", "a"]);
+    }
+
+    #[test]
+    fn sort_branches_orders_by_remote() {
+        assert_eq!(
+            sorted_names(&["-remote"]),
+            vec![
+                "upstream/main",
+                "origin/main",
+                "main",
+                "feature",
+                "no_commit"
+            ]
+        );
+    }
+
+    #[test]
+    fn sort_branches_uses_last_key_as_primary_key() {
+        assert_eq!(
+            sorted_names(&["-committerdate", "type"]),
+            vec![
+                "feature",
+                "main",
+                "no_commit",
+                "upstream/main",
+                "origin/main"
+            ]
+        );
+    }
+
+    #[test]
+    fn sort_key_from_str_returns_err_given_unknown_key() {
+        let result = "size".parse::<SortKey>();
+
+        assert!(result.unwrap_err().contains("unknown sort key 'size'"));
+    }
+
+    #[test]
+    fn sort_key_round_trips_through_json() {
+        let key: SortKey = serde_json::from_str("\"-committerdate\"").unwrap();
+
+        assert_eq!(
+            key,
+            SortKey {
+                field: SortField::CommitterDate,
+                descending: true
+            }
+        );
+        assert_eq!(serde_json::to_string(&key).unwrap(), "\"-committerdate\"");
+    }
+}